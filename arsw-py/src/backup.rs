@@ -0,0 +1,304 @@
+//! `apsw.Backup` -- online backup, via [`arsw::Backup`].
+
+use crate::connection::Connection;
+use crate::error::pyerr;
+use pyo3::prelude::*;
+
+/// An in-progress copy of one database into another, created by
+/// [`Connection.backup`](crate::connection::Connection::backup).
+///
+/// Like [`crate::cursor::Cursor`], a backup belongs to the thread that
+/// created it -- every method but `done` and `close(force=True)` checks `owner_thread`
+/// first and raises `ThreadingViolationError` otherwise, for the same
+/// API-contract reasons (not memory-safety ones; see `Cursor`'s doc comment).
+///
+/// # Safety
+///
+/// `inner` borrows both the `dest` and `src` [`arsw::Connection`]s for as
+/// long as the backup runs; the `'static` lifetimes here are a lie the same
+/// way [`Cursor`](crate::cursor::Cursor)'s are, and for the same reason --
+/// [`Connection::close`] can tear down either connection's `arsw::Connection`
+/// out from under a `Backup` that still references it. `Connection` guards
+/// against that the same way it does for cursors: `Connection.backup`
+/// registers a weak reference to this `Backup` in *both* connections'
+/// `open_backups`, and `Connection.close` force-finishes any live backup it
+/// finds there before tearing down its `arsw::Connection`.
+#[pyclass(module = "apsw", weakref)]
+pub struct Backup {
+    /// Kept alive only so the Python `Connection` objects (and, through
+    /// them, the `arsw::Connection`s `inner` borrows) outlive this `Backup`
+    /// -- never read directly; see the struct-level safety comment.
+    _dest: Py<Connection>,
+    _src: Py<Connection>,
+    inner: Option<arsw::Backup<'static, 'static>>,
+    owner_thread: std::thread::ThreadId,
+}
+
+// SAFETY: see the struct-level safety comment and `Cursor`'s equivalent impl
+// -- every access to `inner` from a `#[pymethods]` fn goes through
+// `ensure_usable`/`ensure_usable_mut`, which reject any thread but
+// `owner_thread`, except `force_close` and `close(force=True)`. The FFI
+// calls `arsw::Backup` makes are themselves safe from any thread under the
+// bundled SQLite's `SQLITE_THREADSAFE=1` build.
+unsafe impl Send for Backup {}
+
+impl Backup {
+    pub(crate) fn new(
+        py: Python<'_>,
+        dest: Py<Connection>,
+        dest_name: &str,
+        src: Py<Connection>,
+        src_name: &str,
+    ) -> PyResult<Self> {
+        let inner = {
+            let dest_ref = dest.borrow(py);
+            let src_ref = src.borrow(py);
+            let backup = dest_ref.inner()?.backup(dest_name, src_ref.inner()?, src_name).map_err(pyerr)?;
+            // SAFETY: see the struct-level safety comment -- `dest`/`src`
+            // keep the borrowed `arsw::Connection`s alive for at least as
+            // long as `self.inner` does.
+            unsafe { std::mem::transmute::<arsw::Backup<'_, '_>, arsw::Backup<'static, 'static>>(backup) }
+        };
+        Ok(Backup {
+            _dest: dest,
+            _src: src,
+            inner: Some(inner),
+            owner_thread: std::thread::current().id(),
+        })
+    }
+
+    fn ensure_owner_thread(&self) -> PyResult<()> {
+        if std::thread::current().id() != self.owner_thread {
+            return Err(crate::exceptions::ThreadingViolationError::new_err(
+                "backup was created on a different thread",
+            ));
+        }
+        Ok(())
+    }
+
+    fn ensure_usable(&self) -> PyResult<&arsw::Backup<'static, 'static>> {
+        self.ensure_owner_thread()?;
+        self.inner
+            .as_ref()
+            .ok_or_else(|| crate::exceptions::BackupClosedError::new_err("backup is finished"))
+    }
+
+    fn ensure_usable_mut(&mut self) -> PyResult<&mut arsw::Backup<'static, 'static>> {
+        self.ensure_owner_thread()?;
+        self.inner
+            .as_mut()
+            .ok_or_else(|| crate::exceptions::BackupClosedError::new_err("backup is finished"))
+    }
+
+    /// Drop the backup without running its finish logic or checking
+    /// `owner_thread` -- called by [`Connection::close`] on every backup
+    /// still referencing it, from either side, before it tears down its
+    /// `arsw::Connection`.
+    pub(crate) fn force_close(&mut self) {
+        self.inner = None;
+    }
+}
+
+#[pymethods]
+impl Backup {
+    /// `Backup.remaining` -- pages left to copy as of the most recent
+    /// `step()` call, or `0` once the backup is done.
+    #[getter]
+    fn remaining(&self) -> PyResult<i32> {
+        self.ensure_owner_thread()?;
+        Ok(self.inner.as_ref().map_or(0, |inner| inner.remaining()))
+    }
+
+    /// `Backup.page_count` -- total pages in the source database as of the
+    /// most recent `step()` call.
+    #[getter]
+    fn page_count(&self) -> PyResult<i32> {
+        Ok(self.ensure_usable()?.page_count())
+    }
+
+    /// `Backup.done` -- `True` once `step()` has copied every page (or the
+    /// backup has been finished/closed some other way).
+    #[getter]
+    fn done(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    /// `Backup.step(npages=-1)` -- copy up to `npages` pages (every
+    /// remaining page by default). Returns `True` if pages remain, `False`
+    /// once the copy is complete -- and, on completion, finishes the backup
+    /// for you, matching `finish()`'s own effect.
+    #[pyo3(signature = (npages = -1))]
+    fn step(&mut self, npages: i32) -> PyResult<bool> {
+        let more = self.ensure_usable_mut()?.step(npages).map_err(pyerr)?;
+        if !more {
+            self.inner = None;
+        }
+        Ok(more)
+    }
+
+    /// `Backup.finish()` -- finish the backup now, however much has been
+    /// copied so far. A no-op if the backup is already finished/closed.
+    fn finish(&mut self) -> PyResult<()> {
+        self.ensure_owner_thread()?;
+        match self.inner.take() {
+            Some(inner) => inner.finish().map_err(pyerr),
+            None => Ok(()),
+        }
+    }
+
+    /// `Backup.close(force=False)` -- alias for `finish()`, for symmetry
+    /// with `Connection.close`/`Cursor.close`. `force=True` skips the
+    /// owner-thread check, for abandoning a backup from another thread.
+    #[pyo3(signature = (force = false))]
+    fn close(&mut self, force: bool) -> PyResult<()> {
+        if !force {
+            self.ensure_owner_thread()?;
+        }
+        match self.inner.take() {
+            Some(inner) => inner.finish().map_err(pyerr),
+            None => Ok(()),
+        }
+    }
+
+    fn __enter__(self_: Py<Self>) -> Py<Self> {
+        self_
+    }
+
+    /// Always finishes the backup, even when the `with` block raised --
+    /// an exception mid-copy must not leave the backup dangling.
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> PyResult<bool> {
+        self.finish()?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::Connection;
+    use pyo3::prelude::*;
+
+    fn seeded_connection<'py>(py: Python<'py>, path: &str) -> Bound<'py, PyAny> {
+        let conn = py.get_type_bound::<Connection>().call1((path,)).unwrap();
+        conn.downcast::<Connection>()
+            .unwrap()
+            .borrow()
+            .inner()
+            .unwrap()
+            .execute_script("CREATE TABLE t(x); INSERT INTO t VALUES (1), (2), (3)")
+            .unwrap();
+        conn
+    }
+
+    /// A full copy driven entirely through the `with ... as backup: step()`
+    /// context manager must leave the destination with the source's data,
+    /// and `done`/`remaining` must reflect completion.
+    #[test]
+    fn full_copy_via_context_manager() {
+        Python::with_gil(|py| {
+            let src = seeded_connection(py, ":memory:");
+            let dest = py.get_type_bound::<Connection>().call1((":memory:",)).unwrap();
+
+            let backup = dest.call_method1("backup", ("main", &src, "main")).unwrap();
+            let backup = backup.call_method0("__enter__").unwrap();
+            assert!(!backup.getattr("done").unwrap().extract::<bool>().unwrap());
+            backup.call_method0("step").unwrap();
+            assert!(backup.getattr("done").unwrap().extract::<bool>().unwrap());
+            assert_eq!(backup.getattr("remaining").unwrap().extract::<i32>().unwrap(), 0);
+            backup.call_method1("__exit__", (py.None(), py.None(), py.None())).unwrap();
+
+            let dest_ref = dest.downcast::<Connection>().unwrap().borrow();
+            let mut stmt = dest_ref.inner().unwrap().execute("SELECT count(*) FROM t").unwrap();
+            assert!(stmt.step().unwrap());
+            assert_eq!(stmt.row().unwrap()[0], arsw::value::Value::Integer(3));
+        });
+    }
+
+    /// `step(npages)` with a small `npages` must copy incrementally,
+    /// decreasing `remaining` each call, and finish the backup itself once
+    /// every page has been copied.
+    #[test]
+    fn incremental_copy_decreases_remaining() {
+        Python::with_gil(|py| {
+            let src = seeded_connection(py, ":memory:");
+            let dest = py.get_type_bound::<Connection>().call1((":memory:",)).unwrap();
+            let backup = dest.call_method1("backup", ("main", &src, "main")).unwrap();
+
+            let mut last_remaining = i32::MAX;
+            loop {
+                let more: bool = backup.call_method1("step", (1,)).unwrap().extract().unwrap();
+                let remaining: i32 = backup.getattr("remaining").unwrap().extract().unwrap();
+                assert!(remaining <= last_remaining);
+                last_remaining = remaining;
+                if !more {
+                    break;
+                }
+            }
+            assert!(backup.getattr("done").unwrap().extract::<bool>().unwrap());
+        });
+    }
+
+    /// `SQLITE_BUSY` from a locked source -- another connection to the same
+    /// file holding an exclusive lock -- must surface as `BusyError`, not a
+    /// generic error. `:memory:` connections can't share a lock with each
+    /// other, so this needs a real file on disk.
+    #[test]
+    fn busy_source_surfaces_as_busy_error() {
+        Python::with_gil(|py| {
+            let path = std::env::temp_dir().join(format!("arsw-py-backup-busy-test-{}.db", std::process::id()));
+            let path = path.to_str().unwrap();
+            let _ = std::fs::remove_file(path);
+
+            seeded_connection(py, path);
+            // A second connection to the same file, holding an exclusive
+            // lock that the backup's read of `src` will collide with.
+            let locker = py.get_type_bound::<Connection>().call1((path,)).unwrap();
+            locker
+                .downcast::<Connection>()
+                .unwrap()
+                .borrow()
+                .inner()
+                .unwrap()
+                .execute_script("PRAGMA locking_mode=EXCLUSIVE; INSERT INTO t VALUES (4)")
+                .unwrap();
+
+            let src = py.get_type_bound::<Connection>().call1((path,)).unwrap();
+            let dest = py.get_type_bound::<Connection>().call1((":memory:",)).unwrap();
+            let backup = dest.call_method1("backup", ("main", &src, "main")).unwrap();
+            let err = backup.call_method0("step").unwrap_err();
+            assert!(err.is_instance_of::<crate::exceptions::BusyError>(py));
+
+            drop(backup);
+            drop(src);
+            drop(locker);
+            let _ = std::fs::remove_file(path);
+        });
+    }
+
+    /// Any method but `close`/`finish` on an already-finished backup must
+    /// raise `BackupClosedError` rather than touching the freed handle.
+    #[test]
+    fn use_after_finish_raises() {
+        Python::with_gil(|py| {
+            let src = seeded_connection(py, ":memory:");
+            let dest = py.get_type_bound::<Connection>().call1((":memory:",)).unwrap();
+            let backup = dest.call_method1("backup", ("main", &src, "main")).unwrap();
+            backup.call_method0("finish").unwrap();
+            // A repeat finish/close is idempotent.
+            assert!(backup.call_method0("finish").is_ok());
+
+            let err = backup.call_method0("step").unwrap_err();
+            assert!(err.is_instance_of::<crate::exceptions::BackupClosedError>(py));
+            let err = backup.getattr("page_count").unwrap_err();
+            assert!(err.is_instance_of::<crate::exceptions::BackupClosedError>(py));
+            // `remaining` is the one property documented to read `0` once
+            // finished rather than raise.
+            assert_eq!(backup.getattr("remaining").unwrap().extract::<i32>().unwrap(), 0);
+        });
+    }
+}
@@ -0,0 +1,353 @@
+//! `apsw.Blob` -- incremental BLOB/TEXT I/O, via [`arsw::Blob`].
+
+use crate::connection::Connection;
+use crate::error::pyerr;
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// A handle for incremental reads/writes against a single BLOB or TEXT
+/// value, created by
+/// [`Connection.blob_open`](crate::connection::Connection::blob_open).
+/// Tracks its own file-like `position`, advanced by `read`/`readinto`/
+/// `write` and moved directly by `seek`.
+///
+/// Like [`crate::cursor::Cursor`] and [`crate::backup::Backup`], a blob
+/// belongs to the thread that created it, for the same API-contract (not
+/// memory-safety) reasons -- see `Cursor`'s doc comment.
+///
+/// # Safety
+///
+/// `inner` borrows the [`arsw::Connection`] owned by `_conn` under a lying
+/// `'static`, exactly as `Cursor`'s `statements`/`current` and `Backup`'s
+/// `inner` do; see either of their struct-level safety comments. `Connection`
+/// guards against this the same way: `Connection.blob_open` registers a weak
+/// reference to this `Blob` in `open_blobs`, and `Connection.close`
+/// force-closes any live blob it finds there before tearing down its
+/// `arsw::Connection`.
+#[pyclass(module = "apsw", weakref)]
+pub struct Blob {
+    _conn: Py<Connection>,
+    inner: Option<arsw::Blob<'static>>,
+    position: i64,
+    readonly_write_attempted: bool,
+    owner_thread: std::thread::ThreadId,
+}
+
+// SAFETY: see the struct-level safety comment and `Cursor`/`Backup`'s
+// equivalent impls -- every access to `inner` from a `#[pymethods]` fn goes
+// through `ensure_usable`/`ensure_usable_mut`, which reject any thread but
+// `owner_thread`, except `force_close` and `close(force=True)`. The FFI
+// calls `arsw::Blob` makes are themselves safe from any thread under the
+// bundled SQLite's `SQLITE_THREADSAFE=1` build.
+unsafe impl Send for Blob {}
+
+impl Blob {
+    pub(crate) fn new(
+        py: Python<'_>,
+        conn: Py<Connection>,
+        db: &str,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        writable: bool,
+    ) -> PyResult<Self> {
+        let inner = {
+            let conn_ref = conn.borrow(py);
+            let blob = conn_ref.inner()?.blob_open(db, table, column, rowid, writable).map_err(pyerr)?;
+            // SAFETY: see the struct-level safety comment -- `_conn` keeps
+            // the borrowed `arsw::Connection` alive for at least as long as
+            // `self.inner` does.
+            unsafe { std::mem::transmute::<arsw::Blob<'_>, arsw::Blob<'static>>(blob) }
+        };
+        Ok(Blob {
+            _conn: conn,
+            inner: Some(inner),
+            position: 0,
+            readonly_write_attempted: false,
+            owner_thread: std::thread::current().id(),
+        })
+    }
+
+    fn ensure_owner_thread(&self) -> PyResult<()> {
+        if std::thread::current().id() != self.owner_thread {
+            return Err(crate::exceptions::ThreadingViolationError::new_err(
+                "blob was created on a different thread",
+            ));
+        }
+        Ok(())
+    }
+
+    fn ensure_usable(&self) -> PyResult<&arsw::Blob<'static>> {
+        self.ensure_owner_thread()?;
+        self.inner.as_ref().ok_or_else(|| crate::exceptions::BlobClosedError::new_err("blob is closed"))
+    }
+
+    fn ensure_usable_mut(&mut self) -> PyResult<&mut arsw::Blob<'static>> {
+        self.ensure_owner_thread()?;
+        self.inner.as_mut().ok_or_else(|| crate::exceptions::BlobClosedError::new_err("blob is closed"))
+    }
+
+    /// Drop the blob without running its close logic or checking
+    /// `owner_thread` -- called by [`Connection::close`] on every blob
+    /// still referencing it, before it tears down its `arsw::Connection`.
+    pub(crate) fn force_close(&mut self) {
+        self.inner = None;
+    }
+}
+
+#[pymethods]
+impl Blob {
+    /// `Blob.length()` -- the value's size in bytes, fixed until
+    /// `reopen()`.
+    fn length(&self) -> PyResult<i32> {
+        Ok(self.ensure_usable()?.length())
+    }
+
+    /// `Blob.tell()` -- the current read/write position.
+    fn tell(&self) -> PyResult<i64> {
+        self.ensure_owner_thread()?;
+        Ok(self.position)
+    }
+
+    /// `Blob.seek(offset, whence=0)` -- move the read/write position.
+    /// `whence` is `0`/`SEEK_SET` (from the start), `1`/`SEEK_CUR` (from the
+    /// current position), or `2`/`SEEK_END` (from the end), matching
+    /// `io.IOBase.seek`. Raises `ValueError` if the target would fall
+    /// outside `0..=length()`.
+    #[pyo3(signature = (offset, whence = 0))]
+    fn seek(&mut self, offset: i64, whence: i32) -> PyResult<()> {
+        let length = self.ensure_usable()?.length() as i64;
+        let base = match whence {
+            0 => 0,
+            1 => self.position,
+            2 => length,
+            _ => return Err(PyValueError::new_err("whence must be 0 (SEEK_SET), 1 (SEEK_CUR), or 2 (SEEK_END)")),
+        };
+        let target = base + offset;
+        if !(0..=length).contains(&target) {
+            return Err(PyValueError::new_err(format!(
+                "seek target {target} is out of range for a blob of length {length}"
+            )));
+        }
+        self.position = target;
+        Ok(())
+    }
+
+    /// `Blob.read(n=-1)` -- up to `n` bytes from the current position (every
+    /// remaining byte if `n` is negative), advancing the position by
+    /// however many bytes were returned.
+    #[pyo3(signature = (n = -1))]
+    fn read<'py>(&mut self, py: Python<'py>, n: i64) -> PyResult<Bound<'py, PyBytes>> {
+        let blob = self.ensure_usable()?;
+        let remaining = blob.length() as i64 - self.position;
+        let n = if n < 0 { remaining } else { n.min(remaining) }.max(0);
+        let mut buf = vec![0u8; n as usize];
+        blob.read_at(self.position as i32, &mut buf).map_err(pyerr)?;
+        self.position += n;
+        Ok(PyBytes::new_bound(py, &buf))
+    }
+
+    /// `Blob.readinto(buffer)` -- read directly into a writable buffer
+    /// object (e.g. a `bytearray` or writable `memoryview`), up to
+    /// `len(buffer)` bytes or however many remain, whichever is fewer.
+    /// Returns the number of bytes actually read, advancing the position by
+    /// the same amount.
+    fn readinto(&mut self, py: Python<'_>, buffer: &Bound<'_, PyAny>) -> PyResult<usize> {
+        let pybuf = PyBuffer::<u8>::get_bound(buffer)?;
+        if pybuf.readonly() {
+            return Err(PyValueError::new_err("readinto buffer must be writable"));
+        }
+        let blob = self.ensure_usable()?;
+        let remaining = (blob.length() as i64 - self.position).max(0) as usize;
+        let n = pybuf.len_bytes().min(remaining);
+        let mut copied = vec![0u8; n];
+        blob.read_at(self.position as i32, &mut copied).map_err(pyerr)?;
+        let slice = pybuf
+            .as_mut_slice(py)
+            .ok_or_else(|| PyValueError::new_err("readinto buffer must be simple and C-contiguous"))?;
+        for (cell, byte) in slice.iter().zip(copied.iter()) {
+            cell.set(*byte);
+        }
+        self.position += n as i64;
+        Ok(n)
+    }
+
+    /// `Blob.write(data)` -- write `data` at the current position, advancing
+    /// it by `len(data)`. Raises `ValueError` if `data` would extend past
+    /// `length()` -- a blob's size is fixed at open time and cannot grow --
+    /// or `ReadOnlyError` (setting `readonly_write_attempted`) if this blob
+    /// was opened read-only.
+    fn write(&mut self, data: &[u8]) -> PyResult<()> {
+        self.readonly_write_attempted = false;
+        let length = self.ensure_usable()?.length() as i64;
+        if self.position + data.len() as i64 > length {
+            return Err(PyValueError::new_err(format!(
+                "write of {} byte(s) at position {} would extend past this blob's fixed length of {length} bytes",
+                data.len(),
+                self.position
+            )));
+        }
+        let position = self.position;
+        match self.ensure_usable()?.write_at(position as i32, data) {
+            Ok(()) => {
+                self.position += data.len() as i64;
+                Ok(())
+            }
+            Err(err) => {
+                if matches!(err, arsw::Error::Sqlite { code, .. } if code == arsw::ffi::SQLITE_READONLY) {
+                    self.readonly_write_attempted = true;
+                }
+                Err(pyerr(err))
+            }
+        }
+    }
+
+    /// `Blob.readonly_write_attempted` -- `True` if the most recent `write`
+    /// call failed because this blob was opened read-only.
+    #[getter]
+    fn readonly_write_attempted(&self) -> bool {
+        self.readonly_write_attempted
+    }
+
+    /// `Blob.reopen(rowid)` -- point this handle at a different row of the
+    /// same table/column and reset the position to `0`.
+    fn reopen(&mut self, rowid: i64) -> PyResult<()> {
+        self.ensure_usable_mut()?.reopen(rowid).map_err(pyerr)?;
+        self.position = 0;
+        Ok(())
+    }
+
+    /// `Blob.close(force=False)` -- close the blob; every later method
+    /// except `close` itself, `tell` and `readonly_write_attempted` then
+    /// raises `BlobClosedError`. `force=True`
+    /// skips the owner-thread check, for abandoning a blob from another
+    /// thread, matching `Cursor.close`/`Backup.close`.
+    #[pyo3(signature = (force = false))]
+    fn close(&mut self, force: bool) -> PyResult<()> {
+        if !force {
+            self.ensure_owner_thread()?;
+        }
+        match self.inner.take() {
+            Some(inner) => inner.close().map_err(pyerr),
+            None => Ok(()),
+        }
+    }
+
+    fn __enter__(self_: Py<Self>) -> Py<Self> {
+        self_
+    }
+
+    /// Always closes the blob, even when the `with` block raised.
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> PyResult<bool> {
+        self.close(false)?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::Connection;
+    use pyo3::prelude::*;
+    use pyo3::types::PyByteArray;
+
+    /// `Cursor.execute` has no parameter-binding support (see NOTES.md), so
+    /// the row's content is seeded via `zeroblob` and a writable `Blob`
+    /// rather than a bound parameter.
+    fn new_connection(py: Python<'_>) -> Bound<'_, PyAny> {
+        let conn = py.get_type_bound::<Connection>().call1((":memory:",)).unwrap();
+        conn.downcast::<Connection>()
+            .unwrap()
+            .borrow()
+            .inner()
+            .unwrap()
+            .execute_script("CREATE TABLE t(x); INSERT INTO t VALUES (zeroblob(11))")
+            .unwrap();
+        let seed = conn.call_method1("blob_open", ("main", "t", "x", 1i64, true)).unwrap();
+        seed.call_method1("write", (pyo3::types::PyBytes::new_bound(py, b"hello world"),)).unwrap();
+        seed.call_method0("close").unwrap();
+        conn
+    }
+
+    fn open_blob<'py>(conn: &Bound<'py, PyAny>) -> Bound<'py, PyAny> {
+        conn.call_method1("blob_open", ("main", "t", "x", 1i64, false)).unwrap()
+    }
+
+    /// `readinto` must read directly into a writable `bytearray`, advancing
+    /// `tell()` by the number of bytes actually copied.
+    #[test]
+    fn readinto_fills_a_bytearray() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let blob = open_blob(&conn);
+            let buf = PyByteArray::new_bound(py, &[0u8; 5]);
+            let n: usize = blob.call_method1("readinto", (&buf,)).unwrap().extract().unwrap();
+            assert_eq!(n, 5);
+            assert_eq!(buf.to_vec(), b"hello");
+            let tell: i64 = blob.call_method0("tell").unwrap().extract().unwrap();
+            assert_eq!(tell, 5);
+        });
+    }
+
+    /// `readinto` must refuse a read-only buffer (e.g. `bytes`) rather than
+    /// silently doing nothing.
+    #[test]
+    fn readinto_rejects_a_readonly_buffer() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let blob = open_blob(&conn);
+            let readonly = pyo3::types::PyBytes::new_bound(py, &[0u8; 5]);
+            let err = blob.call_method1("readinto", (readonly,)).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        });
+    }
+
+    /// Every `seek` `whence` (`SEEK_SET`/`SEEK_CUR`/`SEEK_END`) must resolve
+    /// against the right base, and an out-of-range target must raise.
+    #[test]
+    fn seek_whence_combinations() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let blob = open_blob(&conn);
+            let length: i32 = blob.call_method0("length").unwrap().extract().unwrap();
+            assert_eq!(length, 11); // "hello world"
+
+            blob.call_method1("seek", (4, 0)).unwrap(); // SEEK_SET
+            assert_eq!(blob.call_method0("tell").unwrap().extract::<i64>().unwrap(), 4);
+
+            blob.call_method1("seek", (2, 1)).unwrap(); // SEEK_CUR
+            assert_eq!(blob.call_method0("tell").unwrap().extract::<i64>().unwrap(), 6);
+
+            blob.call_method1("seek", (0, 2)).unwrap(); // SEEK_END
+            assert_eq!(blob.call_method0("tell").unwrap().extract::<i64>().unwrap(), 11);
+
+            let err = blob.call_method1("seek", (1, 2)).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            let err = blob.call_method1("seek", (-1, 0)).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            let err = blob.call_method1("seek", (0, 3)).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        });
+    }
+
+    /// A blob whose underlying row was deleted out from under it must map
+    /// `SQLITE_ABORT` to `BlobExpiredError`, not a generic error.
+    #[test]
+    fn expired_blob_maps_to_blob_expired_error() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let blob = open_blob(&conn);
+            conn.downcast::<Connection>().unwrap().borrow().inner().unwrap().execute_script("DELETE FROM t WHERE rowid = 1").unwrap();
+
+            let err = blob.call_method1("read", (1,)).unwrap_err();
+            assert!(err.is_instance_of::<crate::exceptions::BlobExpiredError>(py));
+        });
+    }
+}
@@ -0,0 +1,761 @@
+//! `apsw.Connection` -- thin pyo3 wrapper around [`arsw::Connection`].
+
+use crate::error::pyerr;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyWeakrefMethods, PyWeakrefReference};
+use std::cell::RefCell;
+
+/// An open SQLite database connection.
+#[pyclass(module = "apsw")]
+pub struct Connection {
+    /// `None` once [`Connection::close`] has run.
+    inner: Option<arsw::Connection>,
+    row_factory: Option<PyObject>,
+    /// Weak references to every [`crate::cursor::Cursor`] created by
+    /// [`Connection::cursor`] that hasn't been garbage-collected yet --
+    /// weak so a cursor going out of scope doesn't leak here forever, and
+    /// so this registry doesn't itself keep cursors (and, transitively,
+    /// this connection) alive. [`Connection::close`] walks it to reject or
+    /// force-finalize cursors still holding statements against this
+    /// connection, since (unlike plain Rust) nothing else stops a Python
+    /// caller from closing a connection out from under a live cursor.
+    open_cursors: RefCell<Vec<Py<PyWeakrefReference>>>,
+    /// The same registry as `open_cursors`, but for every
+    /// [`crate::backup::Backup`] referencing this connection, as either its
+    /// source or its destination -- [`Connection::backup`] registers each
+    /// new backup in both connections' registries, since either one closing
+    /// out from under a live backup is equally unsafe.
+    open_backups: RefCell<Vec<Py<PyWeakrefReference>>>,
+    /// The same registry as `open_cursors`, but for every
+    /// [`crate::blob::Blob`] opened against this connection by
+    /// [`Connection::blob_open`].
+    open_blobs: RefCell<Vec<Py<PyWeakrefReference>>>,
+    /// The VFS name [`Connection::new`] recorded this connection under in
+    /// [`crate::vfs`]'s open-connection registry (`None` if the underlying
+    /// database has no real file yet to report one for, e.g. a fresh
+    /// `:memory:` connection) -- kept so [`Connection::close`] releases the
+    /// exact same entry, even if the default VFS has changed meanwhile.
+    used_vfs_name: Option<String>,
+}
+
+impl Connection {
+    /// Rust-only accessor for sibling modules (e.g. [`crate::cursor`]) that
+    /// need the underlying [`arsw::Connection`] to prepare statements.
+    /// Fails with [`crate::exceptions::ConnectionClosedError`] once
+    /// [`Connection::close`] has run.
+    pub(crate) fn inner(&self) -> PyResult<&arsw::Connection> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| crate::exceptions::ConnectionClosedError::new_err("connection is closed"))
+    }
+
+    /// Rust-only accessor for [`crate::cursor::Cursor`], which falls back to
+    /// this default when it has no `row_factory` of its own set.
+    pub(crate) fn default_row_factory(&self, py: Python<'_>) -> Option<PyObject> {
+        self.row_factory.as_ref().map(|factory| factory.clone_ref(py))
+    }
+
+    /// Every registered cursor that's still alive, upgraded from
+    /// `open_cursors`'s weak references; dead entries are dropped in the
+    /// same pass.
+    fn live_cursors(&self, py: Python<'_>) -> Vec<Py<crate::cursor::Cursor>> {
+        let mut cursors = self.open_cursors.borrow_mut();
+        let live: Vec<Py<crate::cursor::Cursor>> = cursors
+            .iter()
+            .filter_map(|weak| weak.bind(py).get_object().downcast::<crate::cursor::Cursor>().ok().map(|c| c.clone().unbind()))
+            .collect();
+        cursors.retain(|weak| !weak.bind(py).get_object().is_none());
+        live
+    }
+
+    /// Every registered backup that's still alive, the `open_backups`
+    /// analogue of [`Connection::live_cursors`].
+    fn live_backups(&self, py: Python<'_>) -> Vec<Py<crate::backup::Backup>> {
+        let mut backups = self.open_backups.borrow_mut();
+        let live: Vec<Py<crate::backup::Backup>> = backups
+            .iter()
+            .filter_map(|weak| weak.bind(py).get_object().downcast::<crate::backup::Backup>().ok().map(|b| b.clone().unbind()))
+            .collect();
+        backups.retain(|weak| !weak.bind(py).get_object().is_none());
+        live
+    }
+
+    /// Register `backup` in this connection's `open_backups`, called by
+    /// [`Connection::backup`] once for the destination connection and once
+    /// for the source.
+    pub(crate) fn register_backup(&self, backup: &Bound<'_, crate::backup::Backup>) -> PyResult<()> {
+        let weak = PyWeakrefReference::new_bound(backup.as_any())?;
+        self.open_backups.borrow_mut().push(weak.unbind());
+        Ok(())
+    }
+
+    /// Every registered blob that's still alive, the `open_blobs` analogue
+    /// of [`Connection::live_cursors`].
+    fn live_blobs(&self, py: Python<'_>) -> Vec<Py<crate::blob::Blob>> {
+        let mut blobs = self.open_blobs.borrow_mut();
+        let live: Vec<Py<crate::blob::Blob>> = blobs
+            .iter()
+            .filter_map(|weak| weak.bind(py).get_object().downcast::<crate::blob::Blob>().ok().map(|b| b.clone().unbind()))
+            .collect();
+        blobs.retain(|weak| !weak.bind(py).get_object().is_none());
+        live
+    }
+
+    /// Register `blob` in this connection's `open_blobs`, called by
+    /// [`Connection::blob_open`].
+    pub(crate) fn register_blob(&self, blob: &Bound<'_, crate::blob::Blob>) -> PyResult<()> {
+        let weak = PyWeakrefReference::new_bound(blob.as_any())?;
+        self.open_blobs.borrow_mut().push(weak.unbind());
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl Connection {
+    #[new]
+    #[pyo3(signature = (filename=":memory:"))]
+    fn new(filename: &str) -> PyResult<Self> {
+        let inner = arsw::Connection::open(filename).map_err(pyerr)?;
+        let used_vfs_name = inner.vfs_name("main").ok();
+        if let Some(name) = &used_vfs_name {
+            crate::vfs::note_vfs_in_use(name);
+        }
+        Ok(Connection {
+            inner: Some(inner),
+            row_factory: None,
+            open_cursors: RefCell::new(Vec::new()),
+            open_backups: RefCell::new(Vec::new()),
+            open_blobs: RefCell::new(Vec::new()),
+            used_vfs_name,
+        })
+    }
+
+    /// `Connection.vfsname(name="main") -> str` -- the name of the VFS (or
+    /// `/`-joined chain of VFS shim names) backing the `name`d database's
+    /// file, via [`arsw::Connection::vfs_name`]. Fails for a database with
+    /// no real underlying file yet (e.g. a fresh `:memory:` connection).
+    #[pyo3(signature = (name = "main"))]
+    fn vfsname(&self, name: &str) -> PyResult<String> {
+        self.inner()?.vfs_name(name).map_err(pyerr)
+    }
+
+    /// `Connection.close(force=False)` -- close the underlying database.
+    /// Already-closed connections tolerate a repeat `close()`, matching
+    /// `sqlite3_close_v2`'s own idempotency.
+    ///
+    /// If cursors created by [`Connection.cursor`](Self::cursor), backups
+    /// created by [`Connection.backup`](Self::backup), or blobs opened by
+    /// [`Connection.blob_open`](Self::blob_open) still reference this
+    /// connection, `force=False` (the default) raises `MisuseError` naming
+    /// how many of each; `force=True` finalizes every one of them first
+    /// (any later method call on one of them raises `CursorClosedError`/
+    /// `BackupClosedError`/`BlobClosedError`) and closes regardless.
+    #[pyo3(signature = (force = false))]
+    fn close(&mut self, py: Python<'_>, force: bool) -> PyResult<()> {
+        let Some(inner) = self.inner.take() else {
+            return Ok(());
+        };
+        let live_cursors = self.live_cursors(py);
+        let live_backups = self.live_backups(py);
+        let live_blobs = self.live_blobs(py);
+        if !live_cursors.is_empty() || !live_backups.is_empty() || !live_blobs.is_empty() {
+            if !force {
+                self.inner = Some(inner);
+                return Err(crate::exceptions::MisuseError::new_err(format!(
+                    "cannot close connection: {} cursor(s), {} backup(s), and {} blob(s) still open",
+                    live_cursors.len(),
+                    live_backups.len(),
+                    live_blobs.len()
+                )));
+            }
+            for cursor in live_cursors {
+                cursor.borrow_mut(py).force_close();
+            }
+            for backup in live_backups {
+                backup.borrow_mut(py).force_close();
+            }
+            for blob in live_blobs {
+                blob.borrow_mut(py).force_close();
+            }
+        }
+        inner.close().map_err(pyerr)?;
+        if let Some(name) = self.used_vfs_name.take() {
+            crate::vfs::note_vfs_released(&name);
+        }
+        Ok(())
+    }
+
+    /// `Connection.row_factory` -- the default row factory new cursors use
+    /// when they don't set one of their own. `None` (the default) means
+    /// rows are plain tuples.
+    #[getter]
+    fn row_factory(&self, py: Python<'_>) -> Option<PyObject> {
+        self.row_factory.as_ref().map(|factory| factory.clone_ref(py))
+    }
+
+    #[setter]
+    fn set_row_factory(&mut self, value: Option<PyObject>) {
+        self.row_factory = value;
+    }
+
+    /// `Connection.serialize(name="main")` -- copy out the current contents
+    /// of the named database as `bytes`, via `sqlite3_serialize`.
+    #[pyo3(signature = (name="main"))]
+    fn serialize<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = self.inner()?.serialize(Some(name)).map_err(pyerr)?;
+        Ok(PyBytes::new_bound(py, &bytes))
+    }
+
+    /// `Connection.deserialize(data, name="main")` -- replace the named
+    /// database's contents with `data`, via `sqlite3_deserialize`.
+    ///
+    /// `data` isn't validated eagerly (`sqlite3_deserialize` doesn't check
+    /// the header); malformed data instead raises `NotADBError` from the
+    /// first statement run against the database afterwards.
+    #[pyo3(signature = (data, name="main"))]
+    fn deserialize(&self, data: &[u8], name: &str) -> PyResult<()> {
+        self.inner()?.deserialize(Some(name), data).map_err(pyerr)
+    }
+
+    /// `Connection.read(schema, which, offset, amount) -> (bool, bytes)`
+    ///
+    /// Reads directly from the VFS file backing `schema`'s main database
+    /// (`which == 0`) or its rollback journal/WAL (`which != 0`), bypassing
+    /// the page cache. The returned `bool` is `True` if the full `amount`
+    /// was read, `False` if the read ran past end of file.
+    fn read<'py>(
+        &self,
+        py: Python<'py>,
+        schema: &str,
+        which: i32,
+        offset: i64,
+        amount: usize,
+    ) -> PyResult<(bool, Bound<'py, PyBytes>)> {
+        let (complete, bytes) = self.inner()?.read(schema, which, offset, amount).map_err(pyerr)?;
+        Ok((complete, PyBytes::new_bound(py, &bytes)))
+    }
+
+    /// `Connection.last_insert_rowid()` -- the rowid of the most recent
+    /// successful `INSERT`, via `sqlite3_last_insert_rowid`. Doesn't run a
+    /// statement, so it can't disturb the connection's statement cache.
+    fn last_insert_rowid(&self) -> PyResult<i64> {
+        Ok(self.inner()?.last_insert_rowid())
+    }
+
+    /// `Connection.set_last_insert_rowid(value)` -- override the value
+    /// `last_insert_rowid()` reports, via `sqlite3_set_last_insert_rowid`.
+    fn set_last_insert_rowid(&self, value: i64) -> PyResult<()> {
+        self.inner()?.set_last_insert_rowid(value);
+        Ok(())
+    }
+
+    /// `Connection.setunlocknotify(enabled)` -- when enabled, a cursor
+    /// blocking on `SQLITE_LOCKED`/`SQLITE_LOCKED_SHAREDCACHE` (typically
+    /// from another connection sharing this one's cache) waits for the
+    /// lock to release and retries instead of raising immediately, via
+    /// `sqlite3_unlock_notify`. Deadlocks between two waiting connections
+    /// still raise right away. See [`arsw::Connection::set_unlock_notify_blocking`].
+    #[cfg(feature = "unlock_notify")]
+    fn setunlocknotify(&self, enabled: bool) -> PyResult<()> {
+        self.inner()?.set_unlock_notify_blocking(enabled);
+        Ok(())
+    }
+
+    /// `Connection.changes()` -- rows inserted/updated/deleted by the most
+    /// recently completed statement, via `sqlite3_changes64`.
+    fn changes(&self) -> PyResult<i64> {
+        Ok(self.inner()?.changes())
+    }
+
+    /// `Connection.vacuum_into(path)` -- copy this connection's database
+    /// into a fresh file at `path`, via `VACUUM INTO`. See
+    /// [`arsw::Connection::vacuum_into`].
+    fn vacuum_into(&self, path: &str) -> PyResult<()> {
+        self.inner()?.vacuum_into(path).map_err(pyerr)
+    }
+
+    /// `Connection.integrity_check(schema=None, max_errors=100)` --
+    /// consistency check via `PRAGMA integrity_check`; an empty list means
+    /// the check passed. See [`arsw::Connection::integrity_check`].
+    #[pyo3(signature = (schema=None, max_errors=100))]
+    fn integrity_check(&self, schema: Option<&str>, max_errors: usize) -> PyResult<Vec<String>> {
+        self.inner()?.integrity_check(schema, max_errors).map_err(pyerr)
+    }
+
+    /// `Connection.quick_check(schema=None, max_errors=100)` -- faster, less
+    /// thorough consistency check via `PRAGMA quick_check`. See
+    /// [`arsw::Connection::quick_check`].
+    #[pyo3(signature = (schema=None, max_errors=100))]
+    fn quick_check(&self, schema: Option<&str>, max_errors: usize) -> PyResult<Vec<String>> {
+        self.inner()?.quick_check(schema, max_errors).map_err(pyerr)
+    }
+
+    /// `Connection.config(op, new=None)` -- get or set a boolean
+    /// `SQLITE_DBCONFIG_*` option, via `sqlite3_db_config`. With `new` left
+    /// as `None`, reads back the current value without changing it. See
+    /// [`arsw::Connection::db_config`].
+    #[pyo3(signature = (op, new=None))]
+    fn config(&self, op: i32, new: Option<bool>) -> PyResult<bool> {
+        let value = match new {
+            Some(new) => new as i32,
+            None => -1,
+        };
+        self.inner()?.db_config(op, value).map_err(pyerr)
+    }
+
+    /// `Connection.total_changes()` -- rows inserted/updated/deleted since
+    /// this connection was opened, via `sqlite3_total_changes64`.
+    fn total_changes(&self) -> PyResult<i64> {
+        Ok(self.inner()?.total_changes())
+    }
+
+    /// `Connection.db_names()` -- the names of every attached database, in
+    /// SQLite's own order, via `sqlite3_db_name`. See
+    /// [`arsw::Connection::db_names`].
+    fn db_names(&self) -> PyResult<Vec<String>> {
+        Ok(self.inner()?.db_names())
+    }
+
+    /// `Connection.set_slow_query_threshold(threshold, callback=None)` --
+    /// call `callback(sql, seconds)` whenever an execution's wall-clock time
+    /// meets or exceeds `threshold` seconds; `threshold=None` disables it.
+    /// `callback` is required whenever `threshold` isn't `None`. See
+    /// [`arsw::Connection::set_slow_query_threshold`].
+    #[pyo3(signature = (threshold, callback=None))]
+    fn set_slow_query_threshold(&self, threshold: Option<f64>, callback: Option<PyObject>) -> PyResult<()> {
+        let conn = self.inner()?;
+        let Some(threshold) = threshold else {
+            conn.set_slow_query_threshold(None, |_: &str, _: std::time::Duration| {});
+            return Ok(());
+        };
+        let Some(callback) = callback else {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "callback is required when threshold is not None",
+            ));
+        };
+        conn.set_slow_query_threshold(Some(std::time::Duration::from_secs_f64(threshold.max(0.0))), move |sql, elapsed| {
+            Python::with_gil(|py| {
+                let _ = callback.call1(py, (sql, elapsed.as_secs_f64()));
+            });
+        });
+        Ok(())
+    }
+
+    /// `Connection.overload_function(name, nargs)` -- let a virtual table's
+    /// `xFindFunction` claim the SQL function `name`/`nargs`, via
+    /// `sqlite3_overload_function`. This crate has no virtual table support,
+    /// so nothing claims the function: calling it afterwards fails with
+    /// "unable to use function" instead of "no such function".
+    fn overload_function(&self, name: &str, nargs: i32) -> PyResult<()> {
+        self.inner()?.overload_function(name, nargs).map_err(pyerr)
+    }
+
+    /// `Connection.cursor()` -- a new [`crate::cursor::Cursor`] over this
+    /// connection, registered in `open_cursors` so [`Connection::close`]
+    /// can find it later.
+    fn cursor(self_: Py<Self>, py: Python<'_>) -> PyResult<Py<crate::cursor::Cursor>> {
+        self_.borrow(py).inner()?;
+        let cursor = Py::new(py, crate::cursor::Cursor::new(self_.clone_ref(py)))?;
+        let weak = PyWeakrefReference::new_bound(cursor.bind(py).as_any())?;
+        self_.borrow(py).open_cursors.borrow_mut().push(weak.unbind());
+        Ok(cursor)
+    }
+
+    /// `Connection.backup(databasename, sourceconnection, sourcedatabasename)`
+    ///
+    /// Starts an online backup of `sourceconnection`'s `sourcedatabasename`
+    /// database into `self`'s `databasename` database, via
+    /// [`arsw::Connection::backup`]. Registered with both connections so
+    /// [`Connection::close`] on either one finishes the backup first rather
+    /// than leaving it referencing a closed connection.
+    #[pyo3(signature = (databasename, sourceconnection, sourcedatabasename))]
+    fn backup(
+        self_: Py<Self>,
+        py: Python<'_>,
+        databasename: &str,
+        sourceconnection: Py<Self>,
+        sourcedatabasename: &str,
+    ) -> PyResult<Py<crate::backup::Backup>> {
+        let backup = Py::new(
+            py,
+            crate::backup::Backup::new(py, self_.clone_ref(py), databasename, sourceconnection.clone_ref(py), sourcedatabasename)?,
+        )?;
+        self_.borrow(py).register_backup(backup.bind(py))?;
+        sourceconnection.borrow(py).register_backup(backup.bind(py))?;
+        Ok(backup)
+    }
+
+    /// `Connection.blob_open(database, table, column, rowid, writeable=False)`
+    ///
+    /// Opens a [`crate::blob::Blob`] for incremental I/O against a single
+    /// BLOB or TEXT value, via [`arsw::Connection::blob_open`]. Registered
+    /// with `self` so [`Connection::close`] finds it later.
+    #[pyo3(signature = (database, table, column, rowid, writeable=false))]
+    fn blob_open(
+        self_: Py<Self>,
+        py: Python<'_>,
+        database: &str,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        writeable: bool,
+    ) -> PyResult<Py<crate::blob::Blob>> {
+        let blob = Py::new(py, crate::blob::Blob::new(py, self_.clone_ref(py), database, table, column, rowid, writeable)?)?;
+        self_.borrow(py).register_blob(blob.bind(py))?;
+        Ok(blob)
+    }
+
+    /// `Connection.create_scalar_function(name, callable, numargs=-1, flags=0, deterministic=False)`
+    ///
+    /// Registers `callable` as the SQL function `name`, via
+    /// [`arsw::function::create_scalar_function`]. `numargs` is `-1` for
+    /// "any number of arguments", matching `sqlite3_create_function`.
+    /// `flags` may combine `SQLITE_DIRECTONLY`/`SQLITE_INNOCUOUS`/
+    /// `SQLITE_SUBTYPE`/`SQLITE_RESULT_SUBTYPE`; `deterministic=True` ORs in
+    /// `SQLITE_DETERMINISTIC` for you, so passing it both ways isn't a way
+    /// to get it wrong -- it's just the same bit set twice. See
+    /// [`crate::function`] for what is and isn't marshalled.
+    ///
+    /// `callable=None` removes the exact `(name, numargs)` overload instead
+    /// of registering one, via [`arsw::function::remove_function`];
+    /// `flags`/`deterministic` are ignored in that case, matching
+    /// `sqlite3_create_function`'s own "`NULL` function pointer means
+    /// unregister" semantics.
+    ///
+    /// This is the only spelling of scalar function registration this crate
+    /// exposes -- there's no separate `createscalarfunction` legacy alias to
+    /// keep in sync with it.
+    #[pyo3(signature = (name, callable, numargs=-1, flags=0, deterministic=false))]
+    fn create_scalar_function(
+        &self,
+        name: &str,
+        callable: Option<PyObject>,
+        numargs: i32,
+        flags: i32,
+        deterministic: bool,
+    ) -> PyResult<()> {
+        let Some(callable) = callable else {
+            return arsw::function::remove_function(self.inner()?, name, numargs).map_err(pyerr);
+        };
+        let flags = if deterministic { flags | arsw::ffi::SQLITE_DETERMINISTIC } else { flags };
+        let state = Box::new(crate::function::FunctionState { callable });
+        let state_ptr = Box::into_raw(state);
+        let result = unsafe {
+            self.inner()?.create_scalar_function(
+                name,
+                numargs,
+                flags,
+                state_ptr.cast(),
+                crate::function::function_trampoline,
+                Some(crate::function::destroy_trampoline),
+            )
+        };
+        if result.is_err() {
+            // SQLite never took ownership, so `destroy_trampoline` will
+            // never run for this one -- reclaim it ourselves.
+            drop(unsafe { Box::from_raw(state_ptr) });
+        }
+        result.map_err(pyerr)
+    }
+
+    /// `Connection.create_window_function(name, factory, numargs=-1, flags=0, deterministic=False)`
+    ///
+    /// Registers an aggregate window function `name`, via
+    /// [`arsw::window::create_window_function`]. `factory` is called once
+    /// per row group and must return either an object with `step`/
+    /// `inverse`/`value`/`final` methods, or a 4-tuple of those same four
+    /// callables in that order -- see [`crate::window`] for the full
+    /// contract and what is and isn't marshalled.
+    #[pyo3(signature = (name, factory, numargs=-1, flags=0, deterministic=false))]
+    fn create_window_function(
+        &self,
+        name: &str,
+        factory: PyObject,
+        numargs: i32,
+        flags: i32,
+        deterministic: bool,
+    ) -> PyResult<()> {
+        let flags = if deterministic { flags | arsw::ffi::SQLITE_DETERMINISTIC } else { flags };
+        let state = Box::new(crate::window::FunctionState { factory });
+        let state_ptr = Box::into_raw(state);
+        let result = unsafe {
+            self.inner()?.create_window_function(
+                name,
+                numargs,
+                flags,
+                state_ptr.cast(),
+                crate::window::step_trampoline,
+                crate::window::final_trampoline,
+                crate::window::value_trampoline,
+                crate::window::inverse_trampoline,
+                Some(crate::window::destroy_trampoline),
+            )
+        };
+        if result.is_err() {
+            drop(unsafe { Box::from_raw(state_ptr) });
+        }
+        result.map_err(pyerr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `close(force=True)` with a cursor mid-iteration must force-finalize
+    /// that cursor rather than crash, and every later method on it must
+    /// raise `CursorClosedError` instead of touching the freed statement.
+    #[test]
+    fn close_force_finalizes_a_cursor_mid_iteration() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            conn.borrow(py).inner().unwrap().execute_script("CREATE TABLE t(x); INSERT INTO t VALUES (1), (2), (3)").unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT x FROM t",)).unwrap();
+            // Step once so the cursor is genuinely mid-iteration, not idle.
+            assert!(!cursor.call_method0("fetchone").unwrap().is_none());
+
+            conn.bind(py).call_method1("close", (true,)).unwrap();
+
+            let err = cursor.call_method0("fetchone").unwrap_err();
+            assert!(err.is_instance_of::<crate::exceptions::CursorClosedError>(py));
+            // A repeat close (force or not) on an already-closed connection
+            // is idempotent, matching sqlite3_close_v2.
+            assert!(conn.bind(py).call_method0("close").is_ok());
+        });
+    }
+
+    /// Without `force`, `close()` refuses while a cursor is still open and
+    /// leaves the connection usable.
+    #[test]
+    fn close_without_force_rejects_live_cursor() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            let _cursor = conn.bind(py).call_method0("cursor").unwrap();
+
+            let err = conn.bind(py).call_method0("close").unwrap_err();
+            assert!(err.is_instance_of::<crate::exceptions::MisuseError>(py));
+            // Rejected close must not have torn down the connection.
+            assert!(conn.borrow(py).inner().is_ok());
+        });
+    }
+
+    /// `overload_function` doesn't create the function itself -- it just
+    /// tells SQLite a virtual table's `xFindFunction` may claim the
+    /// name/arity. With no virtual table registered to claim it (this crate
+    /// has no virtual table support yet), calling the function still fails,
+    /// but the error changes from "no such function" to "unable to use
+    /// function", proving SQLite now recognizes the name.
+    #[test]
+    fn overload_function_turns_no_such_function_into_unable_to_use() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+
+            cursor.call_method1("execute", ("SELECT myfunc(1, 2)",)).unwrap();
+            let err = cursor.call_method0("fetchone").unwrap_err();
+            assert!(err.to_string().contains("no such function: myfunc"));
+
+            conn.bind(py).call_method1("overload_function", ("myfunc", 2)).unwrap();
+
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT myfunc(1, 2)",)).unwrap();
+            let err = cursor.call_method0("fetchone").unwrap_err();
+            assert!(err.to_string().contains("unable to use function myfunc"));
+        });
+    }
+
+    /// `create_scalar_function(name, None, numargs)` removes only the exact
+    /// `(name, numargs)` overload, matching `sqlite3_create_function`'s own
+    /// per-arity registration -- a 1-arg overload of the same name must
+    /// keep working.
+    #[test]
+    fn removing_a_two_arg_overload_leaves_the_one_arg_overload_intact() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            let double = py.eval_bound("lambda x: x * 2", None, None).unwrap();
+            let add = py.eval_bound("lambda x, y: x + y", None, None).unwrap();
+            conn.bind(py).call_method1("create_scalar_function", ("f", &double, 1)).unwrap();
+            conn.bind(py).call_method1("create_scalar_function", ("f", &add, 2)).unwrap();
+
+            conn.bind(py).call_method1("create_scalar_function", ("f", py.None(), 2)).unwrap();
+
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT f(21)",)).unwrap();
+            let row = cursor.call_method0("fetchone").unwrap();
+            assert_eq!(row.get_item(0).unwrap().extract::<i64>().unwrap(), 42);
+
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT f(1, 2)",)).unwrap();
+            let err = cursor.call_method0("fetchone").unwrap_err();
+            assert!(err.to_string().contains("wrong number of arguments"));
+        });
+    }
+
+    /// Redefining a function on one thread while another thread repeatedly
+    /// queries it must never crash the process, regardless of which
+    /// definition (or transient "no such function", during the brief window
+    /// the old overload has been dropped but the new one not yet installed)
+    /// answers any given call -- `destroy_trampoline` reclaiming the old
+    /// `FunctionState` box is the only deallocation path, and every access
+    /// to it happens under the GIL.
+    #[test]
+    fn redefining_a_function_under_concurrent_queries_does_not_crash() {
+        let conn: Py<PyAny> = Python::with_gil(|py| Py::new(py, Connection::new(":memory:").unwrap()).unwrap().into_any());
+
+        let query_conn = Python::with_gil(|py| conn.clone_ref(py));
+        let querier = std::thread::spawn(move || {
+            for _ in 0..200 {
+                Python::with_gil(|py| {
+                    let cursor = query_conn.bind(py).call_method0("cursor").unwrap();
+                    if cursor.call_method1("execute", ("SELECT f(1)",)).is_ok() {
+                        let _ = cursor.call_method0("fetchone");
+                    }
+                });
+            }
+        });
+
+        Python::with_gil(|py| {
+            let one = py.eval_bound("lambda x: x + 1", None, None).unwrap();
+            let two = py.eval_bound("lambda x: x + 2", None, None).unwrap();
+            for i in 0..200 {
+                let callable = if i % 2 == 0 { &one } else { &two };
+                conn.bind(py).call_method1("create_scalar_function", ("f", callable, 1)).unwrap();
+            }
+        });
+
+        querier.join().unwrap();
+    }
+
+    /// `config` writes straight through to real `sqlite3_db_config` --
+    /// toggling `SQLITE_DBCONFIG_ENABLE_TRIGGER` actually gates whether
+    /// triggers fire, not just a flag some other code path ignores.
+    #[test]
+    fn config_enable_trigger_gates_whether_triggers_actually_fire() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            conn.borrow(py)
+                .inner()
+                .unwrap()
+                .execute_script(
+                    "CREATE TABLE t(a); CREATE TABLE log(msg); \
+                     CREATE TRIGGER trg AFTER INSERT ON t BEGIN INSERT INTO log VALUES ('fired'); END",
+                )
+                .unwrap();
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_ENABLE_TRIGGER, false)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("INSERT INTO t VALUES (1)",)).unwrap().call_method0("fetchall").unwrap();
+            cursor.call_method1("execute", ("SELECT COUNT(*) FROM log",)).unwrap();
+            assert_eq!(cursor.call_method0("fetchone").unwrap().get_item(0).unwrap().extract::<i64>().unwrap(), 0);
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_ENABLE_TRIGGER, true)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("INSERT INTO t VALUES (2)",)).unwrap().call_method0("fetchall").unwrap();
+            cursor.call_method1("execute", ("SELECT COUNT(*) FROM log",)).unwrap();
+            assert_eq!(cursor.call_method0("fetchone").unwrap().get_item(0).unwrap().extract::<i64>().unwrap(), 1);
+        });
+    }
+
+    /// Disabling `SQLITE_DBCONFIG_ENABLE_VIEW` makes a view genuinely
+    /// unreadable, not just a stored flag that nothing consults.
+    #[test]
+    fn config_enable_view_gates_whether_views_are_readable() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            conn.borrow(py)
+                .inner()
+                .unwrap()
+                .execute_script("CREATE TABLE t(a); INSERT INTO t VALUES (1); CREATE VIEW v AS SELECT * FROM t")
+                .unwrap();
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_ENABLE_VIEW, false)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            let err = cursor
+                .call_method1("execute", ("SELECT * FROM v",))
+                .and_then(|c| c.call_method0("fetchall"))
+                .unwrap_err();
+            assert!(err.to_string().contains("access to view \"v\" prohibited"));
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_ENABLE_VIEW, true)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT * FROM v",)).unwrap();
+            assert_eq!(cursor.call_method0("fetchone").unwrap().get_item(0).unwrap().extract::<i64>().unwrap(), 1);
+        });
+    }
+
+    /// `SQLITE_DBCONFIG_WRITABLE_SCHEMA` must make `sqlite_schema` itself
+    /// editable through ordinary SQL, and `config(op)` with no `new` must
+    /// read the real, current value back rather than a shadowed one.
+    #[test]
+    fn config_writable_schema_gates_direct_edits_to_sqlite_schema() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            conn.borrow(py).inner().unwrap().execute_script("CREATE TABLE t(a)").unwrap();
+
+            assert!(!conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_WRITABLE_SCHEMA,)).unwrap().extract::<bool>().unwrap());
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            let err = cursor
+                .call_method1("execute", ("UPDATE sqlite_schema SET sql = sql WHERE name = 't'",))
+                .and_then(|c| c.call_method0("fetchall"))
+                .unwrap_err();
+            assert!(err.to_string().contains("sqlite_master may not be modified"));
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_WRITABLE_SCHEMA, true)).unwrap();
+            assert!(conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_WRITABLE_SCHEMA,)).unwrap().extract::<bool>().unwrap());
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("UPDATE sqlite_schema SET sql = sql WHERE name = 't'",)).unwrap().call_method0("fetchall").unwrap();
+        });
+    }
+
+    /// `SQLITE_DBCONFIG_DEFENSIVE` blocks the same `sqlite_schema` edit even
+    /// with `writable_schema` enabled -- proving `defensive` isn't a no-op
+    /// flag some other layer ignores.
+    #[test]
+    fn config_defensive_blocks_writable_schema_edits_even_when_enabled() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            conn.borrow(py).inner().unwrap().execute_script("CREATE TABLE t(a)").unwrap();
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_WRITABLE_SCHEMA, true)).unwrap();
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_DEFENSIVE, true)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            let err = cursor
+                .call_method1("execute", ("UPDATE sqlite_schema SET sql = sql WHERE name = 't'",))
+                .and_then(|c| c.call_method0("fetchall"))
+                .unwrap_err();
+            assert!(err.to_string().contains("sqlite_master may not be modified"));
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_DEFENSIVE, false)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("UPDATE sqlite_schema SET sql = sql WHERE name = 't'",)).unwrap().call_method0("fetchall").unwrap();
+        });
+    }
+
+    /// `SQLITE_DBCONFIG_TRUSTED_SCHEMA` gates whether an ordinary (not
+    /// `SQLITE_INNOCUOUS`) function may be called from schema-stored SQL
+    /// (views, triggers, CHECK constraints) rather than only from top-level
+    /// statements -- disabling it must turn a working view into an error.
+    #[test]
+    fn config_trusted_schema_gates_untrusted_functions_in_views() {
+        Python::with_gil(|py| {
+            let conn = Py::new(py, Connection::new(":memory:").unwrap()).unwrap();
+            let f = py.eval_bound("lambda: 1", None, None).unwrap();
+            conn.bind(py).call_method1("create_scalar_function", ("untrusted", &f, 0)).unwrap();
+            conn.borrow(py).inner().unwrap().execute_script("CREATE VIEW v AS SELECT untrusted()").unwrap();
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_TRUSTED_SCHEMA, false)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            let err = cursor
+                .call_method1("execute", ("SELECT * FROM v",))
+                .and_then(|c| c.call_method0("fetchall"))
+                .unwrap_err();
+            assert!(err.to_string().contains("unsafe use of untrusted"));
+
+            conn.bind(py).call_method1("config", (arsw::ffi::SQLITE_DBCONFIG_TRUSTED_SCHEMA, true)).unwrap();
+            let cursor = conn.bind(py).call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT * FROM v",)).unwrap();
+            assert_eq!(cursor.call_method0("fetchone").unwrap().get_item(0).unwrap().extract::<i64>().unwrap(), 1);
+        });
+    }
+}
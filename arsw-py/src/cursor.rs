@@ -0,0 +1,484 @@
+//! `apsw.Cursor` -- executes SQL and iterates over the result rows.
+
+use crate::connection::Connection;
+use crate::error::pyerr;
+use arsw::statement::Statements;
+use arsw::value::Value;
+use arsw::Statement;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyTuple};
+
+pub(crate) fn value_to_py(py: Python<'_>, value: Value) -> PyObject {
+    match value {
+        Value::Null => py.None(),
+        Value::Integer(i) => i.into_py(py),
+        Value::Real(f) => f.into_py(py),
+        Value::Text(s) => s.into_py(py),
+        Value::Blob(b) => PyBytes::new_bound(py, &b).into_py(py),
+    }
+}
+
+/// The inverse of [`value_to_py`], for turning a Python scalar function's
+/// return value back into a [`Value`] SQLite can hold. `None` is `NULL`; a
+/// `bool` is accepted as an `int` since SQLite's own type system has no
+/// separate boolean type.
+pub(crate) fn py_to_value(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if obj.is_none() {
+        return Ok(Value::Null);
+    }
+    if let Ok(i) = obj.extract::<i64>() {
+        return Ok(Value::Integer(i));
+    }
+    if let Ok(f) = obj.extract::<f64>() {
+        return Ok(Value::Real(f));
+    }
+    if let Ok(s) = obj.extract::<String>() {
+        return Ok(Value::Text(s));
+    }
+    if let Ok(b) = obj.extract::<Vec<u8>>() {
+        return Ok(Value::Blob(b));
+    }
+    Err(PyValueError::new_err(format!(
+        "SQL functions must return None, bool, int, float, str, or bytes, got {}",
+        obj.get_type().name()?
+    )))
+}
+
+/// Runs the SQL passed to [`execute`](Cursor::execute) and iterates its
+/// result rows, one [`arsw::Statement`] at a time for multi-statement
+/// scripts -- rows from every statement in the script are visible through a
+/// single, continuous `fetch*` stream.
+///
+/// Like APSW, a cursor belongs to the thread that created it: every method
+/// that touches `statements`/`current` checks `owner_thread` first and
+/// raises `ThreadingViolationError` rather than stepping a statement from
+/// two threads at once (`force_close`, used by `Connection.close`, and
+/// `close(force=True)` are the deliberate exceptions -- see their own
+/// docs). This is an API-contract check, not a memory-safety one: the
+/// bundled SQLite is built `SQLITE_THREADSAFE=1` ("serialized"), so every
+/// FFI call this crate makes is internally mutex-guarded regardless of
+/// which OS thread issues it.
+///
+/// # Safety
+///
+/// `statements`/`current` borrow the [`arsw::Connection`] owned by `conn`;
+/// the `'static` here is a lie that only holds as long as `conn`'s
+/// underlying `arsw::Connection` is still open. Ordinarily a strong
+/// `Py<Connection>` reference (kept in `conn`) would guarantee that on its
+/// own, but [`Connection::close`](crate::connection::Connection::close) can
+/// tear down the `arsw::Connection` out from under a `Connection` object
+/// that's still alive -- see `weakref` on this `pyclass` and
+/// [`Connection`]'s `open_cursors` registry, which `force_close`s every
+/// live cursor first so `statements`/`current` are always dropped before
+/// that happens.
+#[pyclass(module = "apsw", weakref)]
+pub struct Cursor {
+    conn: Py<Connection>,
+    statements: Option<Statements<'static>>,
+    current: Option<Statement<'static>>,
+    arraysize: i64,
+    row_trace: Option<PyObject>,
+    row_factory: Option<PyObject>,
+    closed: bool,
+    owner_thread: std::thread::ThreadId,
+}
+
+// SAFETY: `statements`/`current` hold raw `sqlite3_stmt` pointers that are
+// `!Send` by default, but every access to them from a `#[pymethods]` fn
+// goes through `ensure_usable`, which rejects any thread but
+// `owner_thread` (the thread `new` ran on) -- except `force_close`, called
+// by `Connection.close`, and `close(force=True)`, both explicitly allowed
+// to finalize a cursor from elsewhere. `sqlite3_finalize` and every other
+// FFI call made along that path are themselves safe to call from any
+// thread under the bundled SQLite's `SQLITE_THREADSAFE=1` build, so this
+// doesn't rely on the affinity check for soundness -- only for matching
+// APSW's documented single-owner-thread cursor contract.
+unsafe impl Send for Cursor {}
+
+impl Cursor {
+    pub(crate) fn new(conn: Py<Connection>) -> Self {
+        Cursor {
+            conn,
+            statements: None,
+            current: None,
+            arraysize: 1,
+            row_trace: None,
+            row_factory: None,
+            closed: false,
+            owner_thread: std::thread::current().id(),
+        }
+    }
+
+    fn ensure_owner_thread(&self) -> PyResult<()> {
+        if std::thread::current().id() != self.owner_thread {
+            return Err(crate::exceptions::ThreadingViolationError::new_err(
+                "cursor was created on a different thread",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Both of [`Self::ensure_owner_thread`] and the not-yet-`closed` check,
+    /// run at the top of every method that touches `statements`/`current`.
+    fn ensure_usable(&self) -> PyResult<()> {
+        self.ensure_owner_thread()?;
+        if self.closed {
+            return Err(crate::exceptions::CursorClosedError::new_err("cursor is closed"));
+        }
+        Ok(())
+    }
+
+    /// Finalize any in-progress statement and mark this cursor closed,
+    /// without going through the `close` pymethod or its thread check --
+    /// called by [`Connection::close`](crate::connection::Connection::close)
+    /// on every still-live cursor before it tears down the underlying
+    /// `arsw::Connection`, so no `Statement` can outlive it regardless of
+    /// which thread is closing the connection.
+    pub(crate) fn force_close(&mut self) {
+        self.statements = None;
+        self.current = None;
+        self.closed = true;
+    }
+
+    /// Column names of the statement currently producing rows, for
+    /// [`crate::row::Row`]'s `row_factory` constructor to pick up.
+    pub(crate) fn column_names(&self) -> Vec<String> {
+        match &self.current {
+            None => Vec::new(),
+            Some(stmt) => (0..stmt.column_count())
+                .map(|i| stmt.column_name(i).unwrap_or_default())
+                .collect(),
+        }
+    }
+
+    /// This cursor's own `row_factory` if set, else its connection's.
+    fn resolved_row_factory(&self, py: Python<'_>) -> Option<PyObject> {
+        self.row_factory
+            .as_ref()
+            .map(|factory| factory.clone_ref(py))
+            .or_else(|| self.conn.borrow(py).default_row_factory(py))
+    }
+
+    /// Step through `self.current` (advancing through `self.statements` as
+    /// each one is exhausted) until a row is produced or the script runs
+    /// out, without applying `row_trace`/`row_factory`.
+    fn step_one_row(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        loop {
+            if self.current.is_none() {
+                let Some(statements) = self.statements.as_mut() else {
+                    return Ok(None);
+                };
+                match statements.next() {
+                    Some(stmt) => self.current = Some(stmt.map_err(pyerr)?),
+                    None => {
+                        self.statements = None;
+                        return Ok(None);
+                    }
+                }
+            }
+            let stmt = self.current.as_mut().unwrap();
+            if !stmt.step().map_err(pyerr)? {
+                self.current = None;
+                continue;
+            }
+            let row = PyTuple::new_bound(py, stmt.row().map_err(pyerr)?.into_iter().map(|v| value_to_py(py, v)));
+            return Ok(Some(row.into_py(py)));
+        }
+    }
+
+    /// Produce the next row as the object callers actually see: step to a
+    /// raw tuple, let `row_trace` transform or veto it, then let the
+    /// resolved `row_factory` build the final object from whatever
+    /// `row_trace` produced -- matching the DB-API ordering. Both callbacks
+    /// receive `(cursor, row)`, matching `sqlite3`'s interface.
+    fn fetch_next_row(self_: &Py<Cursor>, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        loop {
+            let Some(mut row) = self_.borrow_mut(py).step_one_row(py)? else {
+                return Ok(None);
+            };
+            let row_trace = self_.borrow(py).row_trace.as_ref().map(|t| t.clone_ref(py));
+            if let Some(tracer) = row_trace {
+                let traced = tracer.call1(py, (self_.clone_ref(py), row))?;
+                if traced.is_none(py) {
+                    // `row_trace` vetoed this row; keep stepping.
+                    continue;
+                }
+                row = traced;
+            }
+            if let Some(factory) = self_.borrow(py).resolved_row_factory(py) {
+                row = factory.call1(py, (self_.clone_ref(py), row))?;
+            }
+            return Ok(Some(row));
+        }
+    }
+}
+
+#[pymethods]
+impl Cursor {
+    #[getter]
+    fn arraysize(&self) -> i64 {
+        self.arraysize
+    }
+
+    #[setter]
+    fn set_arraysize(&mut self, value: i64) -> PyResult<()> {
+        if value < 1 {
+            return Err(PyValueError::new_err("arraysize must be a positive integer"));
+        }
+        self.arraysize = value;
+        Ok(())
+    }
+
+    /// Always `-1`: SQLite doesn't report affected-row counts the way the
+    /// DB-API's `rowcount` expects, so APSW (and we) report it as unknown.
+    #[getter]
+    fn rowcount(&self) -> i64 {
+        -1
+    }
+
+    #[getter]
+    fn row_trace(&self, py: Python<'_>) -> Option<PyObject> {
+        self.row_trace.as_ref().map(|tracer| tracer.clone_ref(py))
+    }
+
+    #[setter]
+    fn set_row_trace(&mut self, value: Option<PyObject>) {
+        self.row_trace = value;
+    }
+
+    /// `Cursor.row_factory` -- overrides the connection's default factory
+    /// for rows fetched through this cursor. `None` (the default) defers
+    /// to `Connection.row_factory`.
+    #[getter]
+    fn row_factory(&self, py: Python<'_>) -> Option<PyObject> {
+        self.row_factory.as_ref().map(|factory| factory.clone_ref(py))
+    }
+
+    #[setter]
+    fn set_row_factory(&mut self, value: Option<PyObject>) {
+        self.row_factory = value;
+    }
+
+    /// `(name, declared_type)` for each column of the statement currently
+    /// producing rows, or an empty list between/after executions.
+    #[getter]
+    fn description(&self) -> PyResult<Vec<(String, Option<String>)>> {
+        self.ensure_usable()?;
+        Ok(match &self.current {
+            None => Vec::new(),
+            Some(stmt) => (0..stmt.column_count())
+                .map(|i| (stmt.column_name(i).unwrap_or_default(), stmt.column_decltype(i)))
+                .collect(),
+        })
+    }
+
+    /// `Cursor.description_types` -- the SQLite storage class
+    /// (`"NULL"`/`"INTEGER"`/`"REAL"`/`"TEXT"`/`"BLOB"`) of each column of
+    /// the row currently sitting in the cursor, or an empty list
+    /// between/after executions. Unlike `description`'s `declared_type`
+    /// (the column's *declared* type from its `CREATE TABLE`, if any), this
+    /// reflects the actual value of the row that's ready to be fetched --
+    /// SQLite is dynamically typed, so the two can differ column by column
+    /// across rows.
+    #[getter]
+    fn description_types(&self) -> PyResult<Vec<&'static str>> {
+        self.ensure_usable()?;
+        Ok(match &self.current {
+            None => Vec::new(),
+            Some(stmt) => (0..stmt.column_count())
+                .map(|i| match stmt.column_type(i) {
+                    arsw::statement::ColumnType::Null => "NULL",
+                    arsw::statement::ColumnType::Integer => "INTEGER",
+                    arsw::statement::ColumnType::Float => "REAL",
+                    arsw::statement::ColumnType::Text => "TEXT",
+                    arsw::statement::ColumnType::Blob => "BLOB",
+                })
+                .collect(),
+        })
+    }
+
+    /// `Cursor.normalized_sql` -- the currently-executing statement's SQL
+    /// with literals/parameters/whitespace normalized away, for
+    /// fingerprinting equivalent queries, or `None` between/after
+    /// executions or when SQLite wasn't built with `SQLITE_ENABLE_NORMALIZE`.
+    #[getter]
+    fn normalized_sql(&self) -> PyResult<Option<String>> {
+        self.ensure_usable()?;
+        Ok(self.current.as_ref().and_then(|stmt| stmt.normalized_sql()))
+    }
+
+    /// `Cursor.execute(sql)` -- queue `sql` (which may hold multiple
+    /// `;`-separated statements) for this cursor. Each statement is only
+    /// prepared and stepped when a `fetch*` call or iteration reaches it, so
+    /// errors in `sql` surface there rather than here. Returns
+    /// `self`, matching APSW, so callers can chain straight into a `fetch*`
+    /// call or a `for row in cursor.execute(...)` loop.
+    fn execute<'py>(mut self_: PyRefMut<'py, Self>, py: Python<'py>, sql: &str) -> PyResult<PyRefMut<'py, Self>> {
+        self_.ensure_usable()?;
+        let conn_ref = self_.conn.borrow(py);
+        let statements = conn_ref.inner()?.prepare_all(sql).map_err(pyerr)?;
+        // SAFETY: see the struct-level safety comment -- `self_.conn` keeps
+        // the borrowed `arsw::Connection` alive for at least as long as
+        // `self_.statements`/`self_.current` do.
+        let statements: Statements<'static> = unsafe { std::mem::transmute(statements) };
+        drop(conn_ref);
+        self_.statements = Some(statements);
+        self_.current = None;
+        Ok(self_)
+    }
+
+    fn fetchone(self_: Py<Self>, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        self_.borrow(py).ensure_usable()?;
+        Self::fetch_next_row(&self_, py)
+    }
+
+    fn fetchall(self_: Py<Self>, py: Python<'_>) -> PyResult<Vec<PyObject>> {
+        self_.borrow(py).ensure_usable()?;
+        let mut rows = Vec::new();
+        while let Some(row) = Self::fetch_next_row(&self_, py)? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// `Cursor.close(force=False)` -- finalize any statement this cursor is
+    /// mid-iteration over and mark it closed; every later method except
+    /// `close` itself then raises `CursorClosedError` instead of touching
+    /// the (possibly already-finalized) statement.
+    ///
+    /// `force=False` (the default) still requires the caller to be on the
+    /// thread that created this cursor, like every other method --
+    /// `force=True` skips that check, for the same reason
+    /// [`Connection.close`](crate::connection::Connection::close) is allowed
+    /// to force-close cursors it didn't create: abandoning a cursor from
+    /// another thread is exactly the situation that needs a way out.
+    #[pyo3(signature = (force = false))]
+    fn close(&mut self, force: bool) -> PyResult<()> {
+        if !force {
+            self.ensure_owner_thread()?;
+        }
+        self.force_close();
+        Ok(())
+    }
+
+    /// `Cursor.fetchmany(size=None)` -- up to `size` rows (`arraysize` if
+    /// not given) as a list, possibly fewer if the result set runs out
+    /// first. Rows a `row_trace` callback vetoes (by returning `None`)
+    /// don't count against `size`.
+    #[pyo3(signature = (size=None))]
+    fn fetchmany(self_: Py<Self>, py: Python<'_>, size: Option<i64>) -> PyResult<Vec<PyObject>> {
+        self_.borrow(py).ensure_usable()?;
+        let size = size.unwrap_or(self_.borrow(py).arraysize).max(0) as usize;
+        let mut rows = Vec::with_capacity(size);
+        while rows.len() < size {
+            match Self::fetch_next_row(&self_, py)? {
+                Some(row) => rows.push(row),
+                None => break,
+            }
+        }
+        Ok(rows)
+    }
+
+    fn __iter__(self_: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        self_
+    }
+
+    fn __next__(self_: Py<Self>, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        self_.borrow(py).ensure_usable()?;
+        Self::fetch_next_row(&self_, py)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::Connection;
+    use pyo3::prelude::*;
+    use pyo3::types::PyTuple;
+
+    fn new_connection(py: Python<'_>) -> Bound<'_, PyAny> {
+        py.get_type_bound::<Connection>().call1((":memory:",)).unwrap()
+    }
+
+    /// `fetchmany` must keep returning rows across a multi-statement
+    /// `execute` script's statement boundaries, as one continuous stream.
+    #[test]
+    fn fetchmany_spans_statement_boundaries() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let cursor = conn.call_method0("cursor").unwrap();
+            cursor
+                .call_method1("execute", ("SELECT 1; SELECT 2 UNION ALL SELECT 3; SELECT 4",))
+                .unwrap();
+
+            let rows = cursor.call_method1("fetchmany", (10,)).unwrap();
+            let rows: Vec<Py<PyAny>> = rows.extract().unwrap();
+            let values: Vec<i64> = rows.iter().map(|row| row.bind(py).get_item(0).unwrap().extract().unwrap()).collect();
+            assert_eq!(values, vec![1, 2, 3, 4]);
+        });
+    }
+
+    /// Rows a `row_trace` callback vetoes (returns `None`) must not count
+    /// against `fetchmany`'s `size`.
+    #[test]
+    fn fetchmany_does_not_count_rows_row_trace_vetoes() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let cursor = conn.call_method0("cursor").unwrap();
+            // Veto every row whose sole column is even.
+            let veto = py
+                .eval_bound("lambda cursor, row: None if row[0] % 2 == 0 else row", None, None)
+                .unwrap();
+            cursor.setattr("row_trace", veto).unwrap();
+            cursor.call_method1("execute", ("SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3 UNION ALL SELECT 4 UNION ALL SELECT 5",)).unwrap();
+
+            let rows = cursor.call_method1("fetchmany", (2,)).unwrap();
+            let rows: Vec<Py<PyAny>> = rows.extract().unwrap();
+            let values: Vec<i64> = rows.iter().map(|row| row.bind(py).get_item(0).unwrap().extract().unwrap()).collect();
+            // The 2 surviving (odd) rows are 1 and 3, skipping vetoed 2.
+            assert_eq!(values, vec![1, 3]);
+        });
+    }
+
+    /// `arraysize` must reject non-positive values and otherwise become
+    /// `fetchmany`'s default `size`.
+    #[test]
+    fn arraysize_validates_and_defaults_fetchmany_size() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let cursor = conn.call_method0("cursor").unwrap();
+            let err = cursor.setattr("arraysize", 0).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+
+            cursor.setattr("arraysize", 2).unwrap();
+            cursor.call_method1("execute", ("SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3",)).unwrap();
+            let rows = cursor.call_method1("fetchmany", (py.None(),)).unwrap();
+            let rows: Vec<Py<PyTuple>> = rows.extract().unwrap();
+            assert_eq!(rows.len(), 2);
+        });
+    }
+
+    /// Every method touching `self.current`/`self.statements` must check
+    /// `owner_thread`, not just `execute` -- calling `fetchone` from a
+    /// thread other than the one that ran `execute` must raise
+    /// `ThreadingViolationError` rather than stepping the statement from
+    /// two threads at once.
+    #[test]
+    fn fetchone_from_another_thread_raises_threading_violation() {
+        let cursor: Py<PyAny> = Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let cursor = conn.call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT 1",)).unwrap();
+            cursor.unbind()
+        });
+
+        std::thread::spawn(move || {
+            Python::with_gil(|py| {
+                let err = cursor.bind(py).call_method0("fetchone").unwrap_err();
+                assert!(err.is_instance_of::<crate::exceptions::ThreadingViolationError>(py));
+            });
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+//! Conversion from [`arsw::Error`] to Python exceptions.
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::PyErr;
+
+/// Newtype so we can implement `From<arsw::Error> for PyErr` without
+/// running into the orphan rule (neither type lives in this crate).
+pub struct PyArswError(pub arsw::Error);
+
+impl From<arsw::Error> for PyArswError {
+    fn from(err: arsw::Error) -> Self {
+        PyArswError(err)
+    }
+}
+
+impl From<PyArswError> for PyErr {
+    fn from(err: PyArswError) -> Self {
+        match err.0 {
+            arsw::Error::EmbeddedNul { .. } => PyValueError::new_err(err.0.to_string()),
+            arsw::Error::Sqlite { code, .. } if code == arsw::ffi::SQLITE_NOTADB => {
+                crate::exceptions::NotADBError::new_err(err.0.to_string())
+            }
+            arsw::Error::Sqlite { code, .. } if code == arsw::ffi::SQLITE_BUSY => {
+                crate::exceptions::BusyError::new_err(err.0.to_string())
+            }
+            arsw::Error::Sqlite { code, .. } if code == arsw::ffi::SQLITE_READONLY => {
+                crate::exceptions::ReadOnlyError::new_err(err.0.to_string())
+            }
+            arsw::Error::Sqlite { code, .. } if code == arsw::ffi::SQLITE_ABORT => {
+                crate::exceptions::BlobExpiredError::new_err(err.0.to_string())
+            }
+            arsw::Error::Sqlite { .. } => PyRuntimeError::new_err(err.0.to_string()),
+            arsw::Error::Misuse(_) => crate::exceptions::MisuseError::new_err(err.0.to_string()),
+            arsw::Error::Jsonb { .. } => PyValueError::new_err(err.0.to_string()),
+            arsw::Error::TypeHook { .. } => PyRuntimeError::new_err(err.0.to_string()),
+            arsw::Error::Script { .. } => PyRuntimeError::new_err(err.0.to_string()),
+            arsw::Error::Pragma { .. } => PyRuntimeError::new_err(err.0.to_string()),
+            arsw::Error::PragmaRejected { .. } => PyRuntimeError::new_err(err.0.to_string()),
+            #[cfg(feature = "serde")]
+            arsw::Error::Serde(_) => PyValueError::new_err(err.0.to_string()),
+        }
+    }
+}
+
+/// Convenience alias: `some_arsw_call().map_err(pyerr)?`.
+pub fn pyerr(err: arsw::Error) -> PyErr {
+    PyArswError(err).into()
+}
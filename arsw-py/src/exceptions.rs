@@ -0,0 +1,53 @@
+//! The `apsw` exception hierarchy.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::types::PyModuleMethods;
+
+create_exception!(apsw, Error, PyException);
+create_exception!(apsw, MisuseError, Error);
+create_exception!(apsw, NotADBError, Error);
+// Raised for `SQLITE_BUSY`: another connection holds a lock this one
+// needed and didn't release it before `sqlite3_busy_timeout` (unset by
+// default) ran out.
+create_exception!(apsw, BusyError, Error);
+// Raised by a `Connection` method other than `close` once the connection
+// has been closed.
+create_exception!(apsw, ConnectionClosedError, Error);
+// Raised by a `Cursor` method other than `close` once the cursor has been
+// closed.
+create_exception!(apsw, CursorClosedError, Error);
+// Raised by a `Cursor`, `Backup` or `Blob` method (other than
+// `close(force=True)`, which is exempt) when called from a thread other
+// than the one that created the object.
+create_exception!(apsw, ThreadingViolationError, Error);
+// Raised by a `Backup` method other than `close`/`finish` (and the
+// `remaining`/`done` properties, which read `0`/`True`) once the backup
+// has finished (whether by running to completion or by an explicit
+// `close`/`finish` call).
+create_exception!(apsw, BackupClosedError, Error);
+// Raised for `SQLITE_READONLY` from `Blob.write`: the blob was opened
+// read-only.
+create_exception!(apsw, ReadOnlyError, Error);
+// Raised for `SQLITE_ABORT` from a `Blob` method: the row underlying the
+// blob was deleted or changed size since it was opened, invalidating the
+// handle.
+create_exception!(apsw, BlobExpiredError, Error);
+// Raised by a `Blob` method other than `close`, `tell` and
+// `readonly_write_attempted` once the blob has been closed.
+create_exception!(apsw, BlobClosedError, Error);
+
+pub fn register(m: &pyo3::Bound<'_, pyo3::types::PyModule>) -> pyo3::PyResult<()> {
+    m.add("Error", m.py().get_type_bound::<Error>())?;
+    m.add("MisuseError", m.py().get_type_bound::<MisuseError>())?;
+    m.add("NotADBError", m.py().get_type_bound::<NotADBError>())?;
+    m.add("BusyError", m.py().get_type_bound::<BusyError>())?;
+    m.add("ConnectionClosedError", m.py().get_type_bound::<ConnectionClosedError>())?;
+    m.add("CursorClosedError", m.py().get_type_bound::<CursorClosedError>())?;
+    m.add("ThreadingViolationError", m.py().get_type_bound::<ThreadingViolationError>())?;
+    m.add("BackupClosedError", m.py().get_type_bound::<BackupClosedError>())?;
+    m.add("ReadOnlyError", m.py().get_type_bound::<ReadOnlyError>())?;
+    m.add("BlobExpiredError", m.py().get_type_bound::<BlobExpiredError>())?;
+    m.add("BlobClosedError", m.py().get_type_bound::<BlobClosedError>())?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! `Connection.create_scalar_function` -- registers a Python callable as a
+//! SQL scalar function, via [`arsw::function::create_scalar_function`].
+//!
+//! Arguments are marshalled `sqlite3_value` -> [`arsw::Value`] -> Python
+//! object (`NULL`/`int`/`float`/`str`/`bytes`), and the return value back
+//! the same way; an exception raised by the callable aborts the statement
+//! via `sqlite3_result_error` with the exception's message, which loses
+//! the exact exception type (`error.rs` turns it back into a generic
+//! `RuntimeError` rather than re-raising what the callable
+//! actually raised). Subtype-aware marshalling (`apsw.with_subtype`, the
+//! small `.value`/`.subtype` wrapper object JSON-aware functions need) is
+//! not implemented yet -- see [`arsw::function::value_subtype`]/
+//! [`arsw::function::set_result_subtype`] for the Rust-level pieces it
+//! would build on.
+
+use crate::cursor::{py_to_value, value_to_py};
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use std::os::raw::{c_int, c_void};
+
+/// Owns the Python callable a registered function was created with.
+pub(crate) struct FunctionState {
+    pub(crate) callable: PyObject,
+}
+
+/// Report a Python exception raised while computing a function's result as
+/// a SQL error, via `sqlite3_result_error`. The exception's message
+/// survives; its type doesn't.
+fn report_py_error(ctx: *mut arsw::ffi::sqlite3_context, _py: Python<'_>, err: PyErr) {
+    let message = err.to_string();
+    unsafe { arsw::function::set_result_error(ctx, &message) };
+    // `err` (a GIL-bound traceback) is dropped here, while the GIL is still
+    // held by the caller's `Python::with_gil`.
+}
+
+pub(crate) unsafe extern "C" fn function_trampoline(
+    ctx: *mut arsw::ffi::sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut arsw::ffi::sqlite3_value,
+) {
+    let state = unsafe { &*arsw::ffi::sqlite3_user_data(ctx).cast::<FunctionState>() };
+    Python::with_gil(|py| {
+        let args: Vec<PyObject> = (0..argc as isize)
+            .map(|i| value_to_py(py, unsafe { arsw::function::value_to_value(*argv.offset(i)) }))
+            .collect();
+        let call_result = state.callable.call1(py, PyTuple::new_bound(py, args));
+        match call_result {
+            Ok(result) => match py_to_value(result.bind(py)) {
+                Ok(value) => unsafe { arsw::function::set_result(ctx, &value) },
+                Err(err) => report_py_error(ctx, py, err),
+            },
+            Err(err) => report_py_error(ctx, py, err),
+        }
+    });
+}
+
+pub(crate) unsafe extern "C" fn destroy_trampoline(data: *mut c_void) {
+    let state = unsafe { Box::from_raw(data.cast::<FunctionState>()) };
+    Python::with_gil(|_py| drop(state));
+}
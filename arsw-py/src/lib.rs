@@ -0,0 +1,99 @@
+//! PyO3 bindings exposing an APSW-compatible `apsw` module backed by `arsw`.
+//
+// pyo3's `#[pyfunction]`/`#[pymodule]` expansion triggers a clippy false
+// positive on every `PyResult<T>`-returning function (it sees the generated
+// `Err(e) => Err(e.into())` arm as a same-type conversion). See
+// https://github.com/PyO3/pyo3/issues/4062.
+#![allow(clippy::useless_conversion)]
+// `create_exception!`'s expansion references a `cfg(feature = "gil-refs")`
+// that this pyo3 version's `Cargo.toml` no longer declares.
+#![allow(unexpected_cfgs)]
+
+mod backup;
+mod blob;
+mod connection;
+mod cursor;
+mod error;
+mod exceptions;
+mod function;
+mod log;
+mod row;
+mod vfs;
+mod window;
+
+use error::pyerr;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::cmp::Ordering;
+
+/// `apsw.randomness(amount, seed=None)`
+///
+/// Returns `amount` bytes drawn from SQLite's own PRNG (`sqlite3_randomness`),
+/// rather than Python's `os.urandom`, so that tests relying on SQLite's PRNG
+/// seeding behave correctly. If `seed` is given, it first reseeds SQLite's
+/// PRNG via `SQLITE_TESTCTRL_PRNG_SEED` before drawing bytes.
+#[pyfunction]
+#[pyo3(signature = (amount, seed=None))]
+fn randomness<'py>(py: Python<'py>, amount: usize, seed: Option<&[u8]>) -> Bound<'py, PyBytes> {
+    if let Some(seed) = seed {
+        arsw::seed_randomness(seed);
+    }
+    PyBytes::new_bound(py, &arsw::randomness(amount))
+}
+
+/// `apsw.strglob(pattern, string)` -- GLOB matching via `sqlite3_strglob`.
+#[pyfunction]
+fn strglob(pattern: &str, string: &str) -> PyResult<bool> {
+    arsw::util::strglob(pattern, string).map_err(pyerr)
+}
+
+/// `apsw.strlike(pattern, string, escape=None)` -- LIKE matching via
+/// `sqlite3_strlike`.
+#[pyfunction]
+#[pyo3(signature = (pattern, string, escape=None))]
+fn strlike(pattern: &str, string: &str, escape: Option<char>) -> PyResult<bool> {
+    arsw::util::strlike(pattern, string, escape).map_err(pyerr)
+}
+
+/// `apsw.stricmp(a, b)` -- ASCII case-insensitive comparison via
+/// `sqlite3_stricmp`, returned as a Python-style `-1`/`0`/`1`.
+#[pyfunction]
+fn stricmp(a: &str, b: &str) -> PyResult<i32> {
+    let ordering = arsw::util::stricmp(a, b).map_err(pyerr)?;
+    Ok(ordering_to_int(ordering))
+}
+
+fn ordering_to_int(ordering: Ordering) -> i32 {
+    match ordering {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+#[pymodule]
+fn apsw(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(randomness, m)?)?;
+    m.add_function(wrap_pyfunction!(strglob, m)?)?;
+    m.add_function(wrap_pyfunction!(strlike, m)?)?;
+    m.add_function(wrap_pyfunction!(stricmp, m)?)?;
+    m.add_function(wrap_pyfunction!(vfs::vfs_names, m)?)?;
+    m.add_function(wrap_pyfunction!(vfs::vfs_details, m)?)?;
+    m.add_function(wrap_pyfunction!(vfs::set_default_vfs, m)?)?;
+    m.add_function(wrap_pyfunction!(vfs::unregister_vfs, m)?)?;
+    m.add_class::<backup::Backup>()?;
+    m.add_class::<blob::Blob>()?;
+    m.add_class::<connection::Connection>()?;
+    m.add_class::<cursor::Cursor>()?;
+    m.add_class::<row::Row>()?;
+    m.add_class::<vfs::VFS>()?;
+    m.add_class::<vfs::VFSFile>()?;
+    exceptions::register(m)?;
+    log::register(m)?;
+    m.add("SQLITE_DETERMINISTIC", arsw::ffi::SQLITE_DETERMINISTIC)?;
+    m.add("SQLITE_DIRECTONLY", arsw::ffi::SQLITE_DIRECTONLY)?;
+    m.add("SQLITE_INNOCUOUS", arsw::ffi::SQLITE_INNOCUOUS)?;
+    m.add("SQLITE_SUBTYPE", arsw::ffi::SQLITE_SUBTYPE)?;
+    m.add("SQLITE_RESULT_SUBTYPE", arsw::ffi::SQLITE_RESULT_SUBTYPE)?;
+    Ok(())
+}
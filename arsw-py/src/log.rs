@@ -0,0 +1,55 @@
+//! `apsw.config(SQLITE_CONFIG_LOG, handler)` / `apsw.log(errorcode, message)`.
+
+use crate::error::pyerr;
+use crate::exceptions::MisuseError;
+use pyo3::prelude::*;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::Mutex;
+
+/// The handler passed to the most recent successful `config(SQLITE_CONFIG_LOG, ...)`.
+static LOG_HANDLER: Mutex<Option<Py<PyAny>>> = Mutex::new(None);
+
+unsafe extern "C" fn log_trampoline(_ctx: *mut c_void, err_code: c_int, msg: *const c_char) {
+    Python::with_gil(|py| {
+        let Some(handler) = LOG_HANDLER.lock().unwrap().as_ref().map(|h| h.clone_ref(py)) else {
+            return;
+        };
+        let message = CStr::from_ptr(msg).to_string_lossy();
+        // Errors from the user's handler are not this call's to propagate:
+        // SQLite's logging path has no way to surface them either.
+        let _ = handler.call1(py, (err_code, message.into_owned()));
+    });
+}
+
+/// `apsw.config(SQLITE_CONFIG_LOG, handler)`.
+///
+/// `handler` is `None` to unregister, or a callable taking
+/// `(errorcode: int, message: str)`.
+#[pyfunction]
+#[pyo3(signature = (op, handler=None))]
+fn config(py: Python<'_>, op: i32, handler: Option<Py<PyAny>>) -> PyResult<()> {
+    if op != arsw::ffi::SQLITE_CONFIG_LOG {
+        return Err(MisuseError::new_err(format!(
+            "apsw.config() only supports SQLITE_CONFIG_LOG, got {op}"
+        )));
+    }
+    let callback = handler.as_ref().map(|_| log_trampoline as arsw::log::LogCallback);
+    unsafe { arsw::log::set_log_callback(callback, std::ptr::null_mut()) }.map_err(pyerr)?;
+    *LOG_HANDLER.lock().unwrap() = handler.map(|h| h.clone_ref(py));
+    Ok(())
+}
+
+/// `apsw.log(errorcode, message)` -- routes through `sqlite3_log` so it is
+/// indistinguishable from a message SQLite logged itself.
+#[pyfunction]
+fn log(errorcode: i32, message: &str) -> PyResult<()> {
+    arsw::log::log(errorcode, message).map_err(pyerr)
+}
+
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(config, m)?)?;
+    m.add_function(wrap_pyfunction!(log, m)?)?;
+    m.add("SQLITE_CONFIG_LOG", arsw::ffi::SQLITE_CONFIG_LOG)?;
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! `apsw.Row` -- a result row that supports index *and* name access.
+
+use crate::cursor::Cursor;
+use pyo3::exceptions::{PyAttributeError, PyIndexError, PyKeyError};
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyTuple};
+
+/// A row with both `row[0]`-style and `row.colname`-style access.
+///
+/// Built by [`crate::cursor::Cursor`] from a column name list (from
+/// `description`) and a value tuple; a name appearing in more than one
+/// column (e.g. a `JOIN` on same-named columns) resolves to the leftmost
+/// match, matching `sqlite3.Row`.
+///
+/// Set as a `row_factory`, `apsw.Row` is called as `Row(cursor, row)` like
+/// any other row factory; its columns come from `cursor`'s `description`.
+#[pyclass(module = "apsw")]
+pub struct Row {
+    columns: Vec<String>,
+    values: Vec<PyObject>,
+}
+
+impl Row {
+    pub(crate) fn new(columns: Vec<String>, values: Vec<PyObject>) -> Self {
+        Row { columns, values }
+    }
+}
+
+#[pymethods]
+impl Row {
+    #[new]
+    fn from_factory_call(cursor: PyRef<'_, Cursor>, row: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let values: Vec<PyObject> = row.extract()?;
+        Ok(Row::new(cursor.column_names(), values))
+    }
+
+    fn __len__(&self) -> usize {
+        self.values.len()
+    }
+
+    fn __getitem__(&self, py: Python<'_>, key: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        if let Ok(index) = key.extract::<isize>() {
+            let len = self.values.len() as isize;
+            let normalized = if index < 0 { index + len } else { index };
+            return self
+                .values
+                .get(normalized as usize)
+                .map(|v| v.clone_ref(py))
+                .ok_or_else(|| PyIndexError::new_err("row index out of range"));
+        }
+        if let Ok(name) = key.extract::<&str>() {
+            return self.column_value(py, name).ok_or_else(|| {
+                PyKeyError::new_err(format!("no such column: {name}"))
+            });
+        }
+        Err(PyIndexError::new_err("row indices must be integers or column names"))
+    }
+
+    fn __getattr__(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        self.column_value(py, name)
+            .ok_or_else(|| PyAttributeError::new_err(format!("no such column: {name}")))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.columns.clone()
+    }
+
+    fn __eq__(&self, py: Python<'_>, other: &Bound<'_, PyAny>) -> PyResult<bool> {
+        let mine = PyTuple::new_bound(py, self.values.iter().map(|v| v.clone_ref(py)));
+        mine.as_any().eq(other)
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let tuple = PyTuple::new_bound(py, self.values.iter().map(|v| v.clone_ref(py)));
+        Ok(format!("Row{tuple}"))
+    }
+}
+
+impl Row {
+    fn column_value(&self, py: Python<'_>, name: &str) -> Option<PyObject> {
+        self.columns
+            .iter()
+            .position(|c| c == name)
+            .map(|i| self.values[i].clone_ref(py))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::Connection;
+    use pyo3::prelude::*;
+
+    fn new_connection(py: Python<'_>) -> Bound<'_, PyAny> {
+        let conn = py.get_type_bound::<Connection>().call1((":memory:",)).unwrap();
+        conn.downcast::<Connection>()
+            .unwrap()
+            .borrow()
+            .inner()
+            .unwrap()
+            .execute_script("CREATE TABLE t(a, b); INSERT INTO t VALUES (1, 2)")
+            .unwrap();
+        conn
+    }
+
+    /// Set as `row_factory`, `apsw.Row` supports both `row[0]`-style and
+    /// `row.colname`-style access, plus `keys()` for the column names.
+    #[test]
+    fn row_supports_index_and_name_access() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            conn.setattr("row_factory", py.get_type_bound::<super::Row>()).unwrap();
+            let cursor = conn.call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT a, b FROM t",)).unwrap();
+            let row = cursor.call_method0("fetchone").unwrap();
+
+            assert_eq!(row.get_item(0).unwrap().extract::<i64>().unwrap(), 1);
+            assert_eq!(row.get_item(-1).unwrap().extract::<i64>().unwrap(), 2);
+            assert_eq!(row.get_item("b").unwrap().extract::<i64>().unwrap(), 2);
+            assert_eq!(row.getattr("a").unwrap().extract::<i64>().unwrap(), 1);
+            assert_eq!(row.call_method0("keys").unwrap().extract::<Vec<String>>().unwrap(), vec!["a", "b"]);
+
+            assert!(row.get_item("nope").is_err());
+            assert!(row.getattr("nope").is_err());
+        });
+    }
+
+    /// A custom callable `row_factory` replaces the default plain tuples,
+    /// and receives `(cursor, row)` just as `apsw.Row` does.
+    #[test]
+    fn custom_row_factory_overrides_the_default() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            let factory = py.eval_bound("lambda cursor, row: {'sum': row[0] + row[1]}", None, None).unwrap();
+            conn.setattr("row_factory", factory).unwrap();
+            let cursor = conn.call_method0("cursor").unwrap();
+            cursor.call_method1("execute", ("SELECT a, b FROM t",)).unwrap();
+            let row = cursor.call_method0("fetchone").unwrap();
+
+            assert_eq!(row.get_item("sum").unwrap().extract::<i64>().unwrap(), 3);
+        });
+    }
+
+    /// `row_trace` runs first and its output -- not the raw fetched tuple --
+    /// is what `row_factory` builds from, matching the DB-API ordering.
+    #[test]
+    fn row_trace_output_feeds_the_row_factory() {
+        Python::with_gil(|py| {
+            let conn = new_connection(py);
+            conn.setattr("row_factory", py.get_type_bound::<super::Row>()).unwrap();
+            let cursor = conn.call_method0("cursor").unwrap();
+            let tracer = py.eval_bound("lambda cursor, row: (row[0] * 10, row[1])", None, None).unwrap();
+            cursor.setattr("row_trace", tracer).unwrap();
+            cursor.call_method1("execute", ("SELECT a, b FROM t",)).unwrap();
+            let row = cursor.call_method0("fetchone").unwrap();
+
+            assert_eq!(row.get_item("a").unwrap().extract::<i64>().unwrap(), 10);
+            assert_eq!(row.get_item("b").unwrap().extract::<i64>().unwrap(), 2);
+        });
+    }
+}
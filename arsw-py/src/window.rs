@@ -0,0 +1,204 @@
+//! `Connection.create_window_function` -- registers a Python factory as a
+//! SQL aggregate window function, via
+//! [`arsw::window::create_window_function`].
+//!
+//! `factory` is called once per row group (once for a plain aggregate, once
+//! per partition for a windowed one) and must return either:
+//! - an *object* with `step(*args)`, `inverse(*args)`, `value()`, and
+//!   `final()` methods, or
+//! - a 4-`tuple` of callables `(step, inverse, value, final)` sharing
+//!   whatever closure state they like.
+//!
+//! Both shapes are driven identically: SQLite calls `step` for rows
+//! entering the frame, `inverse` for rows leaving it, and `value` whenever
+//! the current frame's result is needed, interleaved however the frame's
+//! movement requires, ending with exactly one `final` call. A plain
+//! (non-windowed) aggregate use never calls `inverse`/`value` at all -- just
+//! `step` per row, then one `final`. An exception raised by any of the four
+//! aborts the statement via `sqlite3_result_error`, same as
+//! [`crate::function`]: the message survives, the exception type doesn't.
+
+use crate::cursor::{py_to_value, value_to_py};
+use arsw::window::aggregate_context;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use std::os::raw::{c_int, c_void};
+
+/// Owns the Python factory a registered window function was created with.
+pub(crate) struct FunctionState {
+    pub(crate) factory: PyObject,
+}
+
+/// The live per-row-group instance a factory call produced, in whichever of
+/// the two accepted shapes it came back as.
+enum WindowInstance {
+    Object(PyObject),
+    Tuple { step: PyObject, inverse: PyObject, value: PyObject, final_: PyObject },
+}
+
+impl WindowInstance {
+    fn new(py: Python<'_>, factory: &PyObject) -> PyResult<Self> {
+        let result = factory.call0(py)?;
+        if let Ok(tuple) = result.bind(py).downcast::<PyTuple>() {
+            if tuple.len() == 4 {
+                return Ok(WindowInstance::Tuple {
+                    step: tuple.get_item(0)?.unbind(),
+                    inverse: tuple.get_item(1)?.unbind(),
+                    value: tuple.get_item(2)?.unbind(),
+                    final_: tuple.get_item(3)?.unbind(),
+                });
+            }
+        }
+        Ok(WindowInstance::Object(result))
+    }
+
+    fn step(&self, py: Python<'_>, args: &Bound<'_, PyTuple>) -> PyResult<()> {
+        match self {
+            WindowInstance::Object(obj) => obj.call_method1(py, "step", args),
+            WindowInstance::Tuple { step, .. } => step.call1(py, args),
+        }?;
+        Ok(())
+    }
+
+    fn inverse(&self, py: Python<'_>, args: &Bound<'_, PyTuple>) -> PyResult<()> {
+        match self {
+            WindowInstance::Object(obj) => obj.call_method1(py, "inverse", args),
+            WindowInstance::Tuple { inverse, .. } => inverse.call1(py, args),
+        }?;
+        Ok(())
+    }
+
+    fn value(&self, py: Python<'_>) -> PyResult<PyObject> {
+        match self {
+            WindowInstance::Object(obj) => obj.call_method0(py, "value"),
+            WindowInstance::Tuple { value, .. } => value.call0(py),
+        }
+    }
+
+    fn final_(&self, py: Python<'_>) -> PyResult<PyObject> {
+        match self {
+            WindowInstance::Object(obj) => obj.call_method0(py, "final"),
+            WindowInstance::Tuple { final_, .. } => final_.call0(py),
+        }
+    }
+}
+
+/// Report a Python exception raised from a window-function callback as a
+/// SQL error, via `sqlite3_result_error`.
+fn report_py_error(ctx: *mut arsw::ffi::sqlite3_context, err: PyErr) {
+    let message = err.to_string();
+    unsafe { arsw::function::set_result_error(ctx, &message) };
+}
+
+fn collect_args(py: Python<'_>, argc: c_int, argv: *mut *mut arsw::ffi::sqlite3_value) -> Bound<'_, PyTuple> {
+    let args: Vec<PyObject> = (0..argc as isize)
+        .map(|i| value_to_py(py, unsafe { arsw::function::value_to_value(*argv.offset(i)) }))
+        .collect();
+    PyTuple::new_bound(py, args)
+}
+
+/// The slot `sqlite3_aggregate_context` hands back for this row group, one
+/// raw pointer wide. Zero-initialized by SQLite, so a null pointer means
+/// "factory() hasn't run yet for this group".
+unsafe fn instance_slot(ctx: *mut arsw::ffi::sqlite3_context) -> *mut *mut WindowInstance {
+    unsafe { aggregate_context::<*mut WindowInstance>(ctx) }.expect("sqlite3_aggregate_context allocation failed")
+}
+
+unsafe fn get_or_init_instance(
+    ctx: *mut arsw::ffi::sqlite3_context,
+    py: Python<'_>,
+    factory: &PyObject,
+) -> PyResult<*mut WindowInstance> {
+    let slot = unsafe { instance_slot(ctx) };
+    if unsafe { (*slot).is_null() } {
+        let instance = Box::new(WindowInstance::new(py, factory)?);
+        unsafe { *slot = Box::into_raw(instance) };
+    }
+    Ok(unsafe { *slot })
+}
+
+pub(crate) unsafe extern "C" fn step_trampoline(
+    ctx: *mut arsw::ffi::sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut arsw::ffi::sqlite3_value,
+) {
+    let state = unsafe { &*arsw::ffi::sqlite3_user_data(ctx).cast::<FunctionState>() };
+    Python::with_gil(|py| {
+        let args = collect_args(py, argc, argv);
+        match unsafe { get_or_init_instance(ctx, py, &state.factory) } {
+            Ok(instance) => {
+                if let Err(err) = unsafe { &*instance }.step(py, &args) {
+                    report_py_error(ctx, err);
+                }
+            }
+            Err(err) => report_py_error(ctx, err),
+        }
+    });
+}
+
+pub(crate) unsafe extern "C" fn inverse_trampoline(
+    ctx: *mut arsw::ffi::sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut arsw::ffi::sqlite3_value,
+) {
+    let state = unsafe { &*arsw::ffi::sqlite3_user_data(ctx).cast::<FunctionState>() };
+    Python::with_gil(|py| {
+        let args = collect_args(py, argc, argv);
+        // `inverse` only ever runs for a row group `step` already saw, so
+        // the instance always exists already -- but fall back to creating
+        // one rather than panicking if SQLite ever surprises us here.
+        match unsafe { get_or_init_instance(ctx, py, &state.factory) } {
+            Ok(instance) => {
+                if let Err(err) = unsafe { &*instance }.inverse(py, &args) {
+                    report_py_error(ctx, err);
+                }
+            }
+            Err(err) => report_py_error(ctx, err),
+        }
+    });
+}
+
+pub(crate) unsafe extern "C" fn value_trampoline(ctx: *mut arsw::ffi::sqlite3_context) {
+    let state = unsafe { &*arsw::ffi::sqlite3_user_data(ctx).cast::<FunctionState>() };
+    Python::with_gil(|py| match unsafe { get_or_init_instance(ctx, py, &state.factory) } {
+        Ok(instance) => match unsafe { &*instance }.value(py) {
+            Ok(result) => match py_to_value(result.bind(py)) {
+                Ok(value) => unsafe { arsw::function::set_result(ctx, &value) },
+                Err(err) => report_py_error(ctx, err),
+            },
+            Err(err) => report_py_error(ctx, err),
+        },
+        Err(err) => report_py_error(ctx, err),
+    });
+}
+
+pub(crate) unsafe extern "C" fn final_trampoline(ctx: *mut arsw::ffi::sqlite3_context) {
+    let state = unsafe { &*arsw::ffi::sqlite3_user_data(ctx).cast::<FunctionState>() };
+    Python::with_gil(|py| {
+        // `final` runs exactly once even if `step` never did (e.g. an
+        // aggregate over zero rows), so it has to be prepared to create the
+        // instance itself.
+        let instance = match unsafe { get_or_init_instance(ctx, py, &state.factory) } {
+            Ok(instance) => unsafe { Box::from_raw(instance) },
+            Err(err) => {
+                report_py_error(ctx, err);
+                return;
+            }
+        };
+        match instance.final_(py) {
+            Ok(result) => match py_to_value(result.bind(py)) {
+                Ok(value) => unsafe { arsw::function::set_result(ctx, &value) },
+                Err(err) => report_py_error(ctx, err),
+            },
+            Err(err) => report_py_error(ctx, err),
+        }
+        // `instance` drops here, releasing its Python reference(s) while the
+        // GIL is still held. SQLite frees the raw aggregate-context bytes
+        // that held the pointer to it once this callback returns.
+    });
+}
+
+pub(crate) unsafe extern "C" fn destroy_trampoline(data: *mut c_void) {
+    let state = unsafe { Box::from_raw(data.cast::<FunctionState>()) };
+    Python::with_gil(|_py| drop(state));
+}
@@ -0,0 +1,140 @@
+//! SQLite type affinity: the "preferred storage class" a column's declared
+//! type maps to (see <https://sqlite.org/datatype3.html#type_affinity>).
+
+use crate::value::Value;
+
+/// A column's type affinity, as determined by [`affinity`] from its declared
+/// type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+/// Determine the type affinity of a declared column type, via SQLite's five
+/// documented rules applied in order (see
+/// <https://sqlite.org/datatype3.html#determination_of_column_affinity>):
+/// per rule 3, an empty declared type gets [`Affinity::Blob`], and a
+/// declared type matching none of the first four rules gets
+/// [`Affinity::Numeric`] (rule 5).
+pub fn affinity(decltype: &str) -> Affinity {
+    let decltype = decltype.to_ascii_uppercase();
+    if decltype.contains("INT") {
+        Affinity::Integer
+    } else if decltype.contains("CHAR") || decltype.contains("CLOB") || decltype.contains("TEXT") {
+        Affinity::Text
+    } else if decltype.contains("BLOB") || decltype.is_empty() {
+        Affinity::Blob
+    } else if decltype.contains("REAL") || decltype.contains("FLOA") || decltype.contains("DOUB") {
+        Affinity::Real
+    } else {
+        Affinity::Numeric
+    }
+}
+
+/// Parse `text` as SQLite would when coercing it to a numeric affinity: a
+/// well-formed integer literal becomes [`Value::Integer`], a well-formed
+/// (but non-integer) real literal becomes [`Value::Real`], and anything else
+/// is not a number at all.
+fn parse_numeric_text(text: &str) -> Option<Value> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if let Ok(i) = trimmed.parse::<i64>() {
+        return Some(Value::Integer(i));
+    }
+    trimmed.parse::<f64>().ok().map(Value::Real)
+}
+
+impl Value {
+    /// Coerce this value toward `affinity`, following SQLite's documented
+    /// storage-class conversions for a value being stored into a column of
+    /// that affinity (see
+    /// <https://sqlite.org/datatype3.html#type_affinity>). `NULL` and `BLOB`
+    /// values are never converted; text that doesn't look like a number is
+    /// left as text even under a numeric affinity. Under [`Affinity::Text`],
+    /// a `REAL` is rendered with Rust's `f64` formatting (`2.0` becomes
+    /// `"2"`), not SQLite's.
+    pub fn coerce_to(&self, affinity: Affinity) -> Value {
+        // INTEGER/NUMERIC affinity stores a real that is exactly an integer
+        // as that integer.
+        let exact_integer = |f: f64| {
+            if f.fract() == 0.0 && (-9.223_372_036_854_775_808e18..9.223_372_036_854_775_808e18).contains(&f) {
+                Value::Integer(f as i64)
+            } else {
+                Value::Real(f)
+            }
+        };
+        match affinity {
+            Affinity::Blob => self.clone(),
+            Affinity::Text => match self {
+                Value::Integer(i) => Value::Text(i.to_string()),
+                Value::Real(f) => Value::Text(f.to_string()),
+                other => other.clone(),
+            },
+            Affinity::Real => match self {
+                Value::Text(s) => match parse_numeric_text(s) {
+                    Some(Value::Integer(i)) => Value::Real(i as f64),
+                    Some(numeric) => numeric,
+                    None => self.clone(),
+                },
+                Value::Integer(i) => Value::Real(*i as f64),
+                other => other.clone(),
+            },
+            Affinity::Integer | Affinity::Numeric => match self {
+                Value::Text(s) => match parse_numeric_text(s) {
+                    Some(Value::Real(f)) => exact_integer(f),
+                    Some(numeric) => numeric,
+                    None => self.clone(),
+                },
+                Value::Real(f) => exact_integer(*f),
+                other => other.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn affinity_follows_the_sqlite_docs_rules_table() {
+        // From https://sqlite.org/datatype3.html#affname -- "FLOATING POINT"
+        // gets INTEGER affinity because it contains "INT", not REAL affinity.
+        assert_eq!(affinity("INT"), Affinity::Integer);
+        assert_eq!(affinity("FLOATING POINT"), Affinity::Integer);
+        assert_eq!(affinity("VARCHAR(255)"), Affinity::Text);
+        assert_eq!(affinity("NATIVE CHARACTER(70)"), Affinity::Text);
+        assert_eq!(affinity("BLOB"), Affinity::Blob);
+        assert_eq!(affinity(""), Affinity::Blob);
+        assert_eq!(affinity("REAL"), Affinity::Real);
+        assert_eq!(affinity("DOUBLE PRECISION"), Affinity::Real);
+        assert_eq!(affinity("NUMERIC(10,5)"), Affinity::Numeric);
+        assert_eq!(affinity("DATE"), Affinity::Numeric);
+    }
+
+    #[test]
+    fn coerce_to_numeric_parses_a_zero_padded_integer_literal() {
+        assert_eq!(Value::Text("0123".to_string()).coerce_to(Affinity::Numeric), Value::Integer(123));
+        assert_eq!(Value::Text("2.5".to_string()).coerce_to(Affinity::Numeric), Value::Real(2.5));
+        assert_eq!(
+            Value::Text("hello".to_string()).coerce_to(Affinity::Numeric),
+            Value::Text("hello".to_string())
+        );
+    }
+
+    #[test]
+    fn coerce_to_leaves_blobs_untouched_regardless_of_target_affinity() {
+        let blob = Value::Blob(vec![1, 2, 3]);
+        assert_eq!(blob.coerce_to(Affinity::Integer), blob);
+        assert_eq!(blob.coerce_to(Affinity::Text), blob);
+        assert_eq!(blob.coerce_to(Affinity::Numeric), blob);
+        assert_eq!(blob.coerce_to(Affinity::Real), blob);
+        assert_eq!(blob.coerce_to(Affinity::Blob), blob);
+    }
+}
@@ -0,0 +1,165 @@
+//! Process-wide hooks applied to every new connection, via
+//! `sqlite3_auto_extension`.
+//!
+//! `sqlite3_auto_extension` only accepts a plain C function pointer with no
+//! context argument, so registering more than one Rust callback can't mean
+//! registering more than one C entry point -- instead a single shared
+//! trampoline stays registered with SQLite as long as at least one
+//! [`AutoExtensionGuard`] is alive, and dispatches to every callback in
+//! [`REGISTRY`] in turn.
+
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// A hook installed by [`register_auto_extension`], run against every
+/// connection opened afterwards.
+type Callback = fn(&Connection) -> Result<()>;
+
+struct Entry {
+    id: u64,
+    callback: Callback,
+}
+
+static REGISTRY: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Cancels a [`register_auto_extension`] registration on drop. The shared
+/// trampoline stays registered with SQLite (via `sqlite3_auto_extension`)
+/// for as long as any [`AutoExtensionGuard`] is alive; dropping the last one
+/// calls `sqlite3_cancel_auto_extension` to remove it.
+pub struct AutoExtensionGuard {
+    id: u64,
+}
+
+impl Drop for AutoExtensionGuard {
+    fn drop(&mut self) {
+        let mut registry = REGISTRY.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        registry.retain(|entry| entry.id != self.id);
+        if registry.is_empty() {
+            unsafe { ffi::sqlite3_cancel_auto_extension(Some(trampoline)) };
+        }
+    }
+}
+
+/// Register `f` to run against every [`Connection`] opened from now on, via
+/// `sqlite3_auto_extension`. Multiple registrations all run, in registration
+/// order. Returns an [`AutoExtensionGuard`] -- see its docs for what
+/// dropping it does.
+///
+/// If `f` returns an error, the `open()` call that would have received the
+/// connection fails instead, with `f`'s error message.
+pub fn register_auto_extension(f: fn(&Connection) -> Result<()>) -> Result<AutoExtensionGuard> {
+    let rc = unsafe { ffi::sqlite3_auto_extension(Some(trampoline)) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_auto_extension failed"));
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(Entry { id, callback: f });
+    Ok(AutoExtensionGuard { id })
+}
+
+/// Forget every automatic extension registered with SQLite -- including ones
+/// registered outside this module -- via `sqlite3_reset_auto_extension`.
+/// Outstanding [`AutoExtensionGuard`]s become inert: dropping one afterwards
+/// finds nothing left to remove.
+pub fn reset_auto_extensions() {
+    unsafe { ffi::sqlite3_reset_auto_extension() };
+    REGISTRY.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+}
+
+unsafe extern "C" fn trampoline(
+    db: *mut ffi::sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    _api: *const ffi::sqlite3_api_routines,
+) -> c_int {
+    let callbacks: Vec<Callback> = REGISTRY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|entry| entry.callback)
+        .collect();
+    // Not ours to close -- `sqlite3_open_v2` still owns `db` at this point.
+    let conn = ManuallyDrop::new(unsafe { Connection::from_borrowed_raw(db) });
+    for callback in callbacks {
+        if let Err(err) = callback(&conn) {
+            if let Ok(message) = CString::new(err.to_string()) {
+                unsafe { *pz_err_msg = ffi::sqlite3_mprintf(c"%s".as_ptr(), message.as_ptr()) };
+            }
+            return ffi::SQLITE_ERROR;
+        }
+    }
+    ffi::SQLITE_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+
+    fn add_noop(conn: &Connection) -> Result<()> {
+        unsafe {
+            crate::function::create_scalar_function(
+                conn,
+                "auto_ext_noop",
+                0,
+                0,
+                std::ptr::null_mut(),
+                constant_one,
+                None,
+            )
+        }
+    }
+
+    fn always_fails(_conn: &Connection) -> Result<()> {
+        Err(Error::Misuse("auto extension deliberately failed"))
+    }
+
+    unsafe extern "C" fn constant_one(ctx: *mut ffi::sqlite3_context, _argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+        unsafe { ffi::sqlite3_result_int64(ctx, 1) };
+    }
+
+    fn column_value(conn: &Connection, sql: &str) -> crate::value::Value {
+        let mut stmt = conn.execute(sql).unwrap();
+        assert!(stmt.step().unwrap());
+        stmt.column_value(0).unwrap()
+    }
+
+    #[test]
+    fn registered_function_is_available_on_every_new_connection_until_cancelled() {
+        let _guard = lock_global_sqlite_state();
+        reset_auto_extensions();
+        let ext_guard = register_auto_extension(add_noop).unwrap();
+
+        let a = Connection::open(":memory:").unwrap();
+        let b = Connection::open(":memory:").unwrap();
+        assert_eq!(column_value(&a, "SELECT auto_ext_noop()"), crate::value::Value::Integer(1));
+        assert_eq!(column_value(&b, "SELECT auto_ext_noop()"), crate::value::Value::Integer(1));
+
+        drop(ext_guard);
+        let c = Connection::open(":memory:").unwrap();
+        let err = c.execute("SELECT auto_ext_noop()").err().unwrap();
+        assert!(matches!(err, Error::Sqlite { .. }));
+    }
+
+    #[test]
+    fn a_failing_callback_makes_open_fail() {
+        let _guard = lock_global_sqlite_state();
+        reset_auto_extensions();
+        let _ext_guard = register_auto_extension(always_fails).unwrap();
+
+        let err = Connection::open(":memory:").err().unwrap();
+        assert!(matches!(err, Error::Sqlite { .. }));
+        assert!(err.to_string().contains("auto extension deliberately failed"));
+
+        reset_auto_extensions();
+    }
+}
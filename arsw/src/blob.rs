@@ -0,0 +1,178 @@
+//! Incremental BLOB I/O, via `sqlite3_blob_open` and friends.
+
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use std::ffi::CString;
+use std::os::raw::c_int;
+
+/// A handle for incremental reads/writes against a single BLOB or TEXT
+/// value, without loading the whole column into memory first. Borrowed
+/// from the [`Connection`] that opened it, the same way
+/// [`Statement`](crate::statement::Statement) is.
+pub struct Blob<'conn> {
+    blob: *mut ffi::sqlite3_blob,
+    conn: &'conn Connection,
+}
+
+impl<'conn> Blob<'conn> {
+    pub(crate) fn open(
+        conn: &'conn Connection,
+        db: &str,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        writable: bool,
+    ) -> Result<Self> {
+        let cdb = CString::new(db)?;
+        let ctable = CString::new(table)?;
+        let ccolumn = CString::new(column)?;
+        let mut blob: *mut ffi::sqlite3_blob = std::ptr::null_mut();
+        let rc = unsafe {
+            ffi::sqlite3_blob_open(
+                conn.as_ptr(),
+                cdb.as_ptr(),
+                ctable.as_ptr(),
+                ccolumn.as_ptr(),
+                rowid,
+                writable as c_int,
+                &mut blob,
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(conn.last_error("sqlite3_blob_open failed"));
+        }
+        Ok(Blob { blob, conn })
+    }
+
+    /// The BLOB/TEXT value's length in bytes, via `sqlite3_blob_bytes`.
+    /// Fixed for the lifetime of this handle unless [`reopen`](Self::reopen)
+    /// points it at a different row.
+    pub fn length(&self) -> i32 {
+        unsafe { ffi::sqlite3_blob_bytes(self.blob) }
+    }
+
+    /// Read `buf.len()` bytes starting at `offset` into `buf`, via
+    /// `sqlite3_blob_read`. Fails (without partially filling `buf`) if
+    /// `offset..offset + buf.len()` runs past [`length`](Self::length), or
+    /// if the row underlying this blob has since been deleted or modified
+    /// in a way that changed its size (`SQLITE_ABORT`).
+    pub fn read_at(&self, offset: i32, buf: &mut [u8]) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_blob_read(self.blob, buf.as_mut_ptr().cast(), buf.len() as c_int, offset) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.conn.last_error("sqlite3_blob_read failed"));
+        }
+        Ok(())
+    }
+
+    /// Write `data` starting at `offset`, via `sqlite3_blob_write`. Fails if
+    /// `offset..offset + data.len()` runs past [`length`](Self::length) --
+    /// a blob's size is fixed at open time and writing cannot grow it -- if
+    /// this handle was opened read-only (`SQLITE_READONLY`), or if the
+    /// underlying row has since changed size (`SQLITE_ABORT`).
+    pub fn write_at(&self, offset: i32, data: &[u8]) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_blob_write(self.blob, data.as_ptr().cast(), data.len() as c_int, offset) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.conn.last_error("sqlite3_blob_write failed"));
+        }
+        Ok(())
+    }
+
+    /// Point this handle at a different row of the same table/column, via
+    /// `sqlite3_blob_reopen` -- cheaper than closing and reopening a new
+    /// [`Blob`] since it reuses the existing cursor into the table's B-tree.
+    pub fn reopen(&mut self, rowid: i64) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_blob_reopen(self.blob, rowid) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.conn.last_error("sqlite3_blob_reopen failed"));
+        }
+        Ok(())
+    }
+
+    /// Close the blob now, via `sqlite3_blob_close`, surfacing any error
+    /// that occurred on the last unreported [`write_at`](Self::write_at)
+    /// call (`sqlite3_blob_close`'s own documented behavior).
+    pub fn close(self) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_blob_close(self.blob) };
+        std::mem::forget(self);
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "sqlite3_blob_close failed"));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Blob<'_> {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_blob_close(self.blob) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+
+    fn table_with_one_row(conn: &Connection) -> i64 {
+        conn.execute("CREATE TABLE t(a BLOB)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (zeroblob(8))").unwrap().step().unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let rowid = table_with_one_row(&conn);
+        let blob = Blob::open(&conn, "main", "t", "a", rowid, true).unwrap();
+        assert_eq!(blob.length(), 8);
+        blob.write_at(0, b"abcdefgh").unwrap();
+        let mut buf = [0u8; 8];
+        blob.read_at(0, &mut buf).unwrap();
+        assert_eq!(&buf, b"abcdefgh");
+    }
+
+    #[test]
+    fn write_past_length_fails() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let rowid = table_with_one_row(&conn);
+        let blob = Blob::open(&conn, "main", "t", "a", rowid, true).unwrap();
+        assert!(blob.write_at(4, b"toolong!").is_err());
+    }
+
+    #[test]
+    fn read_only_open_rejects_writes() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let rowid = table_with_one_row(&conn);
+        let blob = Blob::open(&conn, "main", "t", "a", rowid, false).unwrap();
+        let err = blob.write_at(0, b"12345678").unwrap_err();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_READONLY));
+    }
+
+    #[test]
+    fn reopen_points_at_a_different_row() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let first = table_with_one_row(&conn);
+        conn.execute("INSERT INTO t VALUES (zeroblob(4))").unwrap().step().unwrap();
+        let second = conn.last_insert_rowid();
+        let mut blob = Blob::open(&conn, "main", "t", "a", first, true).unwrap();
+        assert_eq!(blob.length(), 8);
+        blob.reopen(second).unwrap();
+        assert_eq!(blob.length(), 4);
+    }
+
+    #[test]
+    fn deleting_the_row_makes_the_blob_abort_on_use() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let rowid = table_with_one_row(&conn);
+        let blob = Blob::open(&conn, "main", "t", "a", rowid, true).unwrap();
+        conn.execute(&format!("DELETE FROM t WHERE rowid = {rowid}")).unwrap().step().unwrap();
+        let mut buf = [0u8; 8];
+        let err = blob.read_at(0, &mut buf).unwrap_err();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_ABORT));
+    }
+}
@@ -0,0 +1,135 @@
+//! Introspection of which SQLite compile-time options the linked library
+//! actually has, via `sqlite3_compileoption_used`/`sqlite3_compileoption_get`.
+//!
+//! This crate's own `fts5`/`rtree`/`geopoly`/`math_functions`/`stat4`/
+//! `dbstat`/`scanstatus` Cargo features only gate which of `arsw`'s wrapper APIs get
+//! compiled; they don't by themselves control whether the bundled SQLite was
+//! built with the matching `SQLITE_ENABLE_*` option (that's the linked
+//! `libsqlite3-sys`/`.cargo/config.toml`'s `LIBSQLITE3_FLAGS` doing the
+//! work -- see `arsw::ffi`). The two usually agree, but callers who want to
+//! be sure rather than assume can check here at runtime.
+
+use crate::error::Result;
+use crate::ffi;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_int;
+
+/// `true` if the linked SQLite was compiled with `SQLITE_<name>` (`name`
+/// omits the `SQLITE_` prefix, e.g. `"ENABLE_FTS5"`), per
+/// `sqlite3_compileoption_used`.
+pub fn compile_option_used(name: &str) -> Result<bool> {
+    let name = CString::new(name)?;
+    let used = unsafe { ffi::sqlite3_compileoption_used(name.as_ptr()) };
+    Ok(used != 0)
+}
+
+/// Every `SQLITE_*` compile-time option baked into the linked library (the
+/// same list the `compile_options` pragma and `sqlite3_compileoption_get`
+/// enumerate), each with its `SQLITE_` prefix stripped.
+pub fn compile_options() -> Vec<String> {
+    let mut options = Vec::new();
+    for i in 0.. {
+        let ptr = unsafe { ffi::sqlite3_compileoption_get(i as c_int) };
+        if ptr.is_null() {
+            break;
+        }
+        options.push(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned());
+    }
+    options
+}
+
+/// Which of this crate's SQLite-compile-time-gated Cargo features (`fts5`,
+/// `rtree`, `geopoly`, `math_functions`, `stat4`, `dbstat`, `scanstatus`)
+/// the linked SQLite was actually built with, per [`compile_option_used`]
+/// rather than assumed from the Cargo feature alone -- see the module docs
+/// for why the two can disagree.
+pub fn features() -> Vec<&'static str> {
+    const KNOWN: &[(&str, &CStr)] = &[
+        ("fts5", c"ENABLE_FTS5"),
+        ("rtree", c"ENABLE_RTREE"),
+        ("geopoly", c"ENABLE_GEOPOLY"),
+        ("math_functions", c"ENABLE_MATH_FUNCTIONS"),
+        ("stat4", c"ENABLE_STAT4"),
+        ("dbstat", c"ENABLE_DBSTAT_VTAB"),
+        ("scanstatus", c"ENABLE_STMT_SCANSTATUS"),
+    ];
+    KNOWN
+        .iter()
+        .filter(|(_, option)| unsafe { ffi::sqlite3_compileoption_used(option.as_ptr()) } != 0)
+        .map(|(feature, _)| *feature)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Connection;
+
+    #[test]
+    fn compile_options_lists_known_flags() {
+        let options = compile_options();
+        assert!(!options.is_empty());
+        assert!(options.contains(&"ENABLE_FTS5".to_string()));
+        assert!(options.contains(&"ENABLE_NORMALIZE".to_string()));
+    }
+
+    #[test]
+    fn compile_option_used_matches_the_enumerated_list() {
+        assert!(compile_option_used("ENABLE_FTS5").unwrap());
+        assert!(!compile_option_used("NOT_A_REAL_SQLITE_OPTION").unwrap());
+    }
+
+    #[test]
+    fn embedded_nul_is_rejected() {
+        assert!(compile_option_used("a\0b").is_err());
+    }
+
+    #[test]
+    fn features_reflects_the_bundled_build() {
+        let built = features();
+        assert!(built.contains(&"fts5"));
+        assert!(built.contains(&"rtree"));
+        assert!(built.contains(&"geopoly"));
+        assert!(built.contains(&"math_functions"));
+        assert!(built.contains(&"stat4"));
+        assert!(built.contains(&"dbstat"));
+        assert!(built.contains(&"scanstatus"));
+    }
+
+    #[cfg(feature = "geopoly")]
+    #[test]
+    fn geopoly_virtual_table_is_usable() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE VIRTUAL TABLE shapes USING geopoly()").unwrap().step().unwrap();
+        let mut stmt = conn
+            .execute("INSERT INTO shapes(_shape) VALUES ('[[0,0],[0,1],[1,1],[1,0],[0,0]]')")
+            .unwrap();
+        stmt.step().unwrap();
+        let mut count = conn.execute("SELECT count(*) FROM shapes").unwrap();
+        count.step().unwrap();
+        assert_eq!(count.column_value(0).unwrap(), crate::value::Value::Integer(1));
+    }
+
+    #[cfg(feature = "math_functions")]
+    #[test]
+    fn math_functions_sqrt_is_usable() {
+        let conn = Connection::open(":memory:").unwrap();
+        let mut stmt = conn.execute("SELECT sqrt(9.0)").unwrap();
+        stmt.step().unwrap();
+        assert_eq!(stmt.column_value(0).unwrap(), crate::value::Value::Real(3.0));
+    }
+
+    #[cfg(feature = "rtree")]
+    #[test]
+    fn rtree_virtual_table_is_usable() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE VIRTUAL TABLE spots USING rtree(id, minX, maxX, minY, maxY)")
+            .unwrap()
+            .step()
+            .unwrap();
+        conn.execute("INSERT INTO spots VALUES (1, 0, 1, 0, 1)").unwrap().step().unwrap();
+        let mut stmt = conn.execute("SELECT count(*) FROM spots WHERE minX <= 0.5 AND maxX >= 0.5").unwrap();
+        stmt.step().unwrap();
+        assert_eq!(stmt.column_value(0).unwrap(), crate::value::Value::Integer(1));
+    }
+}
@@ -0,0 +1,3169 @@
+//! A SQLite database connection.
+
+use crate::error::{Error, Result};
+use crate::ffi;
+use crate::statement::{Statement, Statements};
+use crate::type_hooks::TypeHooks;
+use crate::value::Value;
+use std::cell::{Cell, RefCell};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr;
+use std::rc::Rc;
+
+/// An open SQLite database connection.
+///
+/// Mirrors `sqlite3*`: not `Sync` (SQLite handles must not be used from two
+/// threads at once without external synchronization), but `Send` since the
+/// bundled build is compiled with `SQLITE_THREADSAFE=1` ("serialized" mode),
+/// so handing a `Connection` to another thread and using it there alone is
+/// fine.
+pub struct Connection {
+    db: *mut ffi::sqlite3,
+    /// Set for the duration of a virtual table module's `xCreate`/
+    /// `xConnect` callback, so [`Connection::vtab_config`] can reject calls
+    /// made outside that window, matching `sqlite3_vtab_config`'s own
+    /// restriction. This crate has no virtual table module support yet, so
+    /// nothing sets it today except [`Connection::enter_vtab_construction`]
+    /// itself (used directly by tests); real module dispatch should hold
+    /// the guard for the callback's duration once it exists.
+    in_vtab_construction: Cell<bool>,
+    /// The boxed closure passed to the most recent [`Connection::trace`]
+    /// call, if any, as the opaque `sqlite3_trace_v2` context pointer. Owned
+    /// by this connection: freed by [`Connection::clear_trace`], called both
+    /// when replacing/clearing the trace and on drop.
+    trace_callback: Cell<*mut std::ffi::c_void>,
+    /// The boxed closure passed to the most recent [`Connection::set_wal_hook`]
+    /// call, if any, as the opaque `sqlite3_wal_hook` context pointer. Owned
+    /// by this connection, freed on drop.
+    wal_hook: Cell<*mut std::ffi::c_void>,
+    /// The [`TypeHooks`] installed by [`Connection::set_type_hooks`], if any.
+    /// `Rc` so [`Statement::row`](crate::statement::Statement::row) can hold
+    /// a cheap clone of it across a borrow of `self.conn` without also
+    /// borrowing this cell for the duration; `RefCell` (rather than this
+    /// struct's usual `Cell`) since `Rc<TypeHooks>` isn't `Copy`.
+    type_hooks: RefCell<Option<Rc<TypeHooks>>>,
+    /// The [`ProgressState`] installed as the opaque `sqlite3_progress_handler`
+    /// context pointer, if [`Connection::set_progress_handler`] or
+    /// [`Connection::set_query_timeout`] has been called at least once.
+    /// Owned by this connection, freed on drop; once allocated, later calls
+    /// to either method mutate it in place rather than reinstalling, so a
+    /// user handler and a query timeout coexist regardless of which was set
+    /// first.
+    progress_handler: Cell<*mut std::ffi::c_void>,
+    /// The duration configured by [`Connection::set_query_timeout`], if any.
+    /// [`Statement::step`](crate::statement::Statement::step) reads this to
+    /// arm a fresh per-execution deadline in [`ProgressState::deadline`].
+    query_timeout: Cell<Option<std::time::Duration>>,
+    /// The boxed closure passed to the most recent
+    /// [`Connection::set_preupdate_hook`] call, if any, as the opaque
+    /// `sqlite3_preupdate_hook` context pointer. Owned by this connection,
+    /// freed on drop.
+    preupdate_hook: Cell<*mut std::ffi::c_void>,
+    /// The boxed closure passed to the most recent
+    /// [`Connection::set_busy_handler`] call, if any, as the opaque
+    /// `sqlite3_busy_handler` context pointer. Owned by this connection,
+    /// freed on drop or when replaced by another call to
+    /// [`Connection::set_busy_handler`] or [`Connection::set_busy_timeout`]
+    /// (`sqlite3_busy_timeout` installs its own internal busy handler,
+    /// silently overwriting ours at the C level, so we drop our side of it
+    /// too rather than leak the box or leave a stale pointer around).
+    busy_handler: Cell<*mut std::ffi::c_void>,
+    /// The name most recently passed to [`Connection::set_main_db_name`], if
+    /// any. `SQLITE_DBCONFIG_MAINDBNAME` stores the pointer we pass it and
+    /// reads it back for the lifetime of the connection (or until renamed
+    /// again), so we own the backing `CString` here rather than hand SQLite
+    /// a dangling pointer once the call returns.
+    main_db_name: RefCell<Option<CString>>,
+    /// Set by [`Connection::set_unlock_notify_blocking`]; read by
+    /// [`Statement::step`](crate::statement::Statement::step) to decide
+    /// whether to block and retry via [`crate::unlock_notify`] instead of
+    /// returning `SQLITE_LOCKED`/`SQLITE_LOCKED_SHAREDCACHE` immediately.
+    #[cfg(feature = "unlock_notify")]
+    unlock_notify_blocking: Cell<bool>,
+    /// The threshold/callback pair installed by
+    /// [`Connection::set_slow_query_threshold`], if any. Unlike the other
+    /// hooks above, this isn't registered with SQLite at all --
+    /// [`Statement::step`](crate::statement::Statement::step) and
+    /// [`Statement::reset`](crate::statement::Statement::reset) time
+    /// executions themselves and call [`Self::report_slow_query`] -- so it
+    /// composes with a [`Self::trace`] callback instead of competing with it
+    /// for `sqlite3_trace_v2`'s single callback slot.
+    slow_query_hook: RefCell<Option<SlowQueryHook>>,
+    /// Controls how much a prepare/step failure's [`Error::Sqlite`] reveals
+    /// about the statement's bound parameters, via
+    /// [`Connection::set_error_verbosity`]. Plain `Cell` state consulted by
+    /// [`crate::statement::Statement`], not registered with SQLite.
+    error_verbosity: Cell<ErrorVerbosity>,
+}
+
+unsafe impl Send for Connection {}
+
+/// RAII marker produced by [`Connection::enter_vtab_construction`]; clears
+/// the "inside xCreate/xConnect" flag on drop.
+///
+/// Only tests construct this today (standing in for the real module
+/// dispatch this crate doesn't have yet), so it's dead code outside
+/// `#[cfg(test)]` builds.
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) struct VtabConstructionGuard<'conn> {
+    conn: &'conn Connection,
+}
+
+impl Drop for VtabConstructionGuard<'_> {
+    fn drop(&mut self) {
+        self.conn.in_vtab_construction.set(false);
+    }
+}
+
+impl Connection {
+    /// Open `path` (or `:memory:` / `""` for a private/temporary database),
+    /// creating it if it does not exist.
+    pub fn open(path: &str) -> Result<Self> {
+        Self::open_with_flags(path, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE)
+    }
+
+    /// Open an unnamed, private on-disk temporary database, per
+    /// `sqlite3_open_v2`'s empty-filename convention -- equivalent to
+    /// `Connection::open("")`, spelled out for callers who want a temp
+    /// database without reaching for a magic empty string.
+    pub fn open_temp() -> Result<Self> {
+        Self::open("")
+    }
+
+    /// Open `path` read-only, via `SQLITE_OPEN_READONLY`. Fails if `path`
+    /// does not already exist.
+    pub fn open_readonly(path: &str) -> Result<Self> {
+        Self::open_with_flags(path, ffi::SQLITE_OPEN_READONLY)
+    }
+
+    fn open_with_flags(path: &str, flags: c_int) -> Result<Self> {
+        Self::open_with_flags_and_vfs(path, flags, None)
+    }
+
+    /// Like [`Self::open_with_flags`], but also allows naming the VFS to
+    /// open through (`None` for `sqlite3_open_v2`'s default), for
+    /// [`crate::connection_builder::ConnectionBuilder::vfs`].
+    pub(crate) fn open_with_flags_and_vfs(path: &str, flags: c_int, vfs: Option<&str>) -> Result<Self> {
+        let cpath = CString::new(path)?;
+        let cvfs = vfs.map(CString::new).transpose()?;
+        let vfs_ptr = cvfs.as_ref().map_or(ptr::null(), |v| v.as_ptr());
+        let mut db: *mut ffi::sqlite3 = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_open_v2(cpath.as_ptr(), &mut db, flags, vfs_ptr) };
+        crate::log::mark_initialized();
+        if rc != ffi::SQLITE_OK {
+            let err = connection_error(db, rc, "sqlite3_open_v2 failed");
+            unsafe { ffi::sqlite3_close(db) };
+            return Err(err);
+        }
+        Ok(Connection {
+            db,
+            in_vtab_construction: Cell::new(false),
+            trace_callback: Cell::new(ptr::null_mut()),
+            wal_hook: Cell::new(ptr::null_mut()),
+            type_hooks: RefCell::new(None),
+            progress_handler: Cell::new(ptr::null_mut()),
+            query_timeout: Cell::new(None),
+            preupdate_hook: Cell::new(ptr::null_mut()),
+            busy_handler: Cell::new(ptr::null_mut()),
+            main_db_name: RefCell::new(None),
+            #[cfg(feature = "unlock_notify")]
+            unlock_notify_blocking: Cell::new(false),
+            slow_query_hook: RefCell::new(None),
+            error_verbosity: Cell::new(ErrorVerbosity::Summary),
+        })
+    }
+
+    /// Open an in-memory database preloaded with `data` (as produced by
+    /// [`serialize`](Self::serialize)), via `sqlite3_deserialize` on a fresh
+    /// `:memory:` connection.
+    ///
+    /// If `writable` is `false`, every write against the returned connection
+    /// fails with `SQLITE_READONLY`, per `SQLITE_DESERIALIZE_READONLY`.
+    pub fn open_from_memory(data: Vec<u8>, writable: bool) -> Result<Self> {
+        let conn = Self::open(":memory:")?;
+        let mut flags = ffi::SQLITE_DESERIALIZE_FREEONCLOSE;
+        if writable {
+            flags |= ffi::SQLITE_DESERIALIZE_RESIZEABLE;
+        } else {
+            flags |= ffi::SQLITE_DESERIALIZE_READONLY;
+        }
+        conn.deserialize_with_flags("main", &data, flags)?;
+        Ok(conn)
+    }
+
+    /// The raw `sqlite3*`, for use by sibling modules that need lower-level
+    /// access (statements, serialization, pragmas, ...).
+    pub(crate) fn as_ptr(&self) -> *mut ffi::sqlite3 {
+        self.db
+    }
+
+    /// Wrap an `sqlite3*` this crate doesn't own -- e.g. one handed to an
+    /// [`crate::auto_extension`] callback mid-`sqlite3_open_v2` -- as a
+    /// [`Connection`] with none of its own hooks installed yet. Callers must
+    /// keep the result from outliving `db` and must not let its `Drop` impl
+    /// run (wrap it in [`std::mem::ManuallyDrop`]), since `db` is owned by
+    /// whoever is really opening it.
+    ///
+    /// # Safety
+    ///
+    /// `db` must be a valid, currently-open `sqlite3*`.
+    pub(crate) unsafe fn from_borrowed_raw(db: *mut ffi::sqlite3) -> Self {
+        Connection {
+            db,
+            in_vtab_construction: Cell::new(false),
+            trace_callback: Cell::new(ptr::null_mut()),
+            wal_hook: Cell::new(ptr::null_mut()),
+            type_hooks: RefCell::new(None),
+            progress_handler: Cell::new(ptr::null_mut()),
+            query_timeout: Cell::new(None),
+            preupdate_hook: Cell::new(ptr::null_mut()),
+            busy_handler: Cell::new(ptr::null_mut()),
+            main_db_name: RefCell::new(None),
+            #[cfg(feature = "unlock_notify")]
+            unlock_notify_blocking: Cell::new(false),
+            slow_query_hook: RefCell::new(None),
+            error_verbosity: Cell::new(ErrorVerbosity::Summary),
+        }
+    }
+
+    /// Prepare `sql`, returning the prepared [`Statement`] without running
+    /// it, so the caller can bind parameters and step through result rows.
+    /// Only the first statement in `sql` is prepared.
+    pub fn execute(&self, sql: &str) -> Result<Statement<'_>> {
+        Statement::prepare(self, sql)
+    }
+
+    /// Prepare every `;`-separated statement in `sql`, one at a time as the
+    /// returned iterator is advanced (unlike [`execute`](Self::execute),
+    /// which only prepares the first).
+    pub fn prepare_all<'conn>(&'conn self, sql: &str) -> Result<Statements<'conn>> {
+        Statements::new(self, sql)
+    }
+
+    /// Run every `;`-separated statement in `sql` to completion in order,
+    /// discarding any result rows, via [`Self::prepare_all`] plus a
+    /// [`Statement::step`] loop per statement. Stops at (and returns) the
+    /// first failure, wrapped in [`Error::Script`] with the 0-based index of
+    /// the statement that failed.
+    pub fn execute_script(&self, sql: &str) -> Result<()> {
+        for (statement_index, stmt) in self.prepare_all(sql)?.enumerate() {
+            (|| -> Result<()> {
+                let mut stmt = stmt?;
+                while stmt.step()? {}
+                Ok(())
+            })()
+            .map_err(|source| Error::Script {
+                statement_index,
+                source: Box::new(source),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Copy this connection's database into a fresh file at `path`, via
+    /// `VACUUM INTO ?` -- `path` is passed as a bound parameter rather than
+    /// interpolated into the SQL text. Fails with SQLite's own error (e.g.
+    /// "output file already exists") if `path` already exists.
+    pub fn vacuum_into(&self, path: &str) -> Result<()> {
+        let mut stmt = self.execute("VACUUM INTO ?")?;
+        stmt.bind_text(1, path)?;
+        stmt.step()?;
+        Ok(())
+    }
+
+    /// Check `schema` (every attached database, if `None`) for consistency
+    /// errors, via `PRAGMA integrity_check(max_errors)`. Reports up to
+    /// `max_errors` problem descriptions; an empty result means the check
+    /// passed.
+    pub fn integrity_check(&self, schema: Option<&str>, max_errors: usize) -> Result<Vec<String>> {
+        self.run_consistency_check("integrity_check", schema, max_errors)
+    }
+
+    /// Like [`Self::integrity_check`], but via `PRAGMA quick_check`: skips
+    /// the slower UNIQUE/CHECK/foreign-key verification for a faster, if
+    /// less thorough, sanity check.
+    pub fn quick_check(&self, schema: Option<&str>, max_errors: usize) -> Result<Vec<String>> {
+        self.run_consistency_check("quick_check", schema, max_errors)
+    }
+
+    /// `PRAGMA [schema.]{pragma}(max_errors)`, collecting every reported row
+    /// except the single `"ok"` row SQLite reports in place of any problem
+    /// rows when the check passes. `schema`'s identifier is quoted (doubling
+    /// embedded `"`) rather than bound, since pragma statements don't accept
+    /// bound parameters for a schema name.
+    fn run_consistency_check(&self, pragma: &str, schema: Option<&str>, max_errors: usize) -> Result<Vec<String>> {
+        let sql = match schema {
+            Some(schema) => format!("PRAGMA \"{}\".{pragma}({max_errors})", schema.replace('"', "\"\"")),
+            None => format!("PRAGMA {pragma}({max_errors})"),
+        };
+        let mut stmt = self.execute(&sql)?;
+        let mut messages = Vec::new();
+        while stmt.step()? {
+            if let Value::Text(message) = stmt.column_value(0)? {
+                if message != "ok" {
+                    messages.push(message);
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// `true` if `self` is currently outside an explicit transaction (i.e.
+    /// the next statement it runs would open one implicitly), via
+    /// `sqlite3_get_autocommit`.
+    pub fn autocommit(&self) -> bool {
+        unsafe { ffi::sqlite3_get_autocommit(self.db) != 0 }
+    }
+
+    /// Read or set one of the boolean `SQLITE_DBCONFIG_*` options (e.g.
+    /// `SQLITE_DBCONFIG_ENABLE_FKEY`), via `sqlite3_db_config`'s `(int,
+    /// int*)` calling convention -- the form every `SQLITE_DBCONFIG_*` op
+    /// besides `MAINDBNAME` and `LOOKASIDE` uses. Pass `-1` for `value` to
+    /// leave the setting unchanged and just read back its current state.
+    /// Returns the setting's new (or, for `-1`, current) value.
+    ///
+    /// `sqlite3_db_config` is a C variadic function; Rust can call it
+    /// directly for this `(int, int*)` form, so no shim is needed.
+    pub fn db_config(&self, op: c_int, value: c_int) -> Result<bool> {
+        let mut out: c_int = 0;
+        let rc = unsafe { ffi::sqlite3_db_config(self.db, op, value, &mut out as *mut c_int) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "sqlite3_db_config failed"));
+        }
+        Ok(out != 0)
+    }
+
+    /// Give the connection's "main" database an additional schema name, via
+    /// `sqlite3_db_config(SQLITE_DBCONFIG_MAINDBNAME, ...)` -- lets SQL refer
+    /// to it as `name` (e.g. in `pragma_database_list` or a qualified
+    /// `name.table` reference) instead of always seeing `"main"`. The
+    /// original `"main"` name keeps working too: SQLite special-cases
+    /// database index 0 as always answering to `"main"`, so this does not
+    /// free `"main"` up for a subsequent `ATTACH ... AS main`. `self` keeps
+    /// `name` alive for as long as SQLite might read it back (the rest of
+    /// this connection's lifetime, or until the next call to this method).
+    pub fn set_main_db_name(&self, name: &str) -> Result<()> {
+        let cname = CString::new(name)?;
+        let rc = unsafe {
+            ffi::sqlite3_db_config(self.db, ffi::SQLITE_DBCONFIG_MAINDBNAME, cname.as_ptr())
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "sqlite3_db_config(SQLITE_DBCONFIG_MAINDBNAME) failed"));
+        }
+        *self.main_db_name.borrow_mut() = Some(cname);
+        Ok(())
+    }
+
+    /// Start an online backup of `src`'s `src_name` database into `self`'s
+    /// `dest_name` database, via `sqlite3_backup_init`. `self` is the
+    /// destination, matching `sqlite3_backup_init`'s own argument order.
+    pub fn backup<'dest, 'src>(
+        &'dest self,
+        dest_name: &str,
+        src: &'src Connection,
+        src_name: &str,
+    ) -> Result<crate::backup::Backup<'dest, 'src>> {
+        crate::backup::Backup::new(self, dest_name, src, src_name)
+    }
+
+    /// Open a [`Blob`](crate::blob::Blob) for incremental I/O against
+    /// `db.table.column` at `rowid`, via `sqlite3_blob_open`. `writable`
+    /// matches `sqlite3_blob_open`'s own `flags` argument: `false` opens
+    /// read-only.
+    pub fn blob_open<'conn>(
+        &'conn self,
+        db: &str,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        writable: bool,
+    ) -> Result<crate::blob::Blob<'conn>> {
+        crate::blob::Blob::open(self, db, table, column, rowid, writable)
+    }
+
+    /// Copy out the current contents of the `schema` database (`"main"` if
+    /// `None`) via `sqlite3_serialize`.
+    pub fn serialize(&self, schema: Option<&str>) -> Result<Vec<u8>> {
+        let schema = CString::new(schema.unwrap_or("main"))?;
+        let mut size: ffi::sqlite3_int64 = 0;
+        let ptr = unsafe { ffi::sqlite3_serialize(self.db, schema.as_ptr(), &mut size, 0) };
+        if ptr.is_null() {
+            if size == 0 {
+                return Ok(Vec::new());
+            }
+            return Err(self.last_error("sqlite3_serialize failed"));
+        }
+        let slice = unsafe { std::slice::from_raw_parts(ptr, size as usize) };
+        let copy = slice.to_vec();
+        unsafe { ffi::sqlite3_free(ptr.cast()) };
+        Ok(copy)
+    }
+
+    /// Replace the contents of the `schema` database (`"main"` if `None`)
+    /// with `data`, via `sqlite3_deserialize`. SQLite takes ownership of a
+    /// fresh `sqlite3_malloc64` copy of `data` and will free it itself.
+    pub fn deserialize(&self, schema: Option<&str>, data: &[u8]) -> Result<()> {
+        let flags = ffi::SQLITE_DESERIALIZE_FREEONCLOSE | ffi::SQLITE_DESERIALIZE_RESIZEABLE;
+        self.deserialize_with_flags(schema.unwrap_or("main"), data, flags)
+    }
+
+    /// Shared `sqlite3_deserialize` plumbing for [`deserialize`](Self::deserialize)
+    /// and [`open_from_memory`](Self::open_from_memory): copy `data` into a
+    /// fresh `sqlite3_malloc64` buffer (SQLite takes ownership of it) and
+    /// hand it to `schema` with the given deserialize flags.
+    fn deserialize_with_flags(&self, schema: &str, data: &[u8], flags: std::os::raw::c_uint) -> Result<()> {
+        let schema = CString::new(schema)?;
+        let len = data.len();
+        let buf = unsafe { ffi::sqlite3_malloc64(len as ffi::sqlite3_uint64) };
+        if buf.is_null() && len != 0 {
+            return Err(Error::sqlite_code(ffi::SQLITE_NOMEM, "sqlite3_malloc64 failed"));
+        }
+        if len != 0 {
+            unsafe { ptr::copy_nonoverlapping(data.as_ptr(), buf.cast(), len) };
+        }
+        let rc = unsafe {
+            ffi::sqlite3_deserialize(
+                self.db,
+                schema.as_ptr(),
+                buf.cast(),
+                len as ffi::sqlite3_int64,
+                len as ffi::sqlite3_int64,
+                flags,
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_deserialize failed"));
+        }
+        Ok(())
+    }
+
+    /// Register `callback` to observe SQL execution on this connection, via
+    /// `sqlite3_trace_v2`. `mask` selects which [`TraceEvent`] kinds fire;
+    /// an empty mask (e.g. [`TraceMask::NONE`]) unregisters any existing
+    /// trace instead of installing a new one. Replaces whatever callback
+    /// was previously registered, matching `sqlite3_trace_v2`'s own
+    /// single-callback-per-connection contract.
+    pub fn trace<F>(&self, mask: TraceMask, callback: F) -> Result<()>
+    where
+        F: FnMut(TraceEvent) + 'static,
+    {
+        self.clear_trace();
+        if mask.is_empty() {
+            let rc = unsafe { ffi::sqlite3_trace_v2(self.db, 0, None, ptr::null_mut()) };
+            if rc != ffi::SQLITE_OK {
+                return Err(self.last_error("sqlite3_trace_v2 failed"));
+            }
+            return Ok(());
+        }
+        let boxed: Box<TraceCallback> = Box::new(Box::new(callback));
+        let ctx = Box::into_raw(boxed).cast::<std::ffi::c_void>();
+        let rc = unsafe { ffi::sqlite3_trace_v2(self.db, mask.0, Some(trace_trampoline), ctx) };
+        if rc != ffi::SQLITE_OK {
+            unsafe { drop(Box::from_raw(ctx.cast::<TraceCallback>())) };
+            return Err(self.last_error("sqlite3_trace_v2 failed"));
+        }
+        self.trace_callback.set(ctx);
+        Ok(())
+    }
+
+    /// Drop whatever boxed trace closure is currently stashed on this
+    /// connection, if any. Does not itself talk to SQLite -- callers either
+    /// immediately re-register a new callback ([`trace`](Self::trace)) or
+    /// have already told SQLite to stop calling it (connection close).
+    fn clear_trace(&self) {
+        let ctx = self.trace_callback.replace(ptr::null_mut());
+        if !ctx.is_null() {
+            unsafe { drop(Box::from_raw(ctx.cast::<TraceCallback>())) };
+        }
+    }
+
+    /// Checkpoint the write-ahead log of `db` (`"main"` if `None`) into the
+    /// main database file, via `sqlite3_wal_checkpoint_v2`. Returns
+    /// `(log_frames, checkpointed_frames)`: the size of the WAL in frames
+    /// and how many of those were checkpointed, as reported by SQLite (both
+    /// `-1` if the database isn't in WAL mode).
+    ///
+    /// [`CheckpointMode::Restart`] and [`CheckpointMode::Truncate`] block
+    /// until other connections' read transactions finish, failing with
+    /// `SQLITE_BUSY` (surfaced as [`Error::Sqlite`] with that code) instead
+    /// of waiting forever if one doesn't.
+    pub fn wal_checkpoint(&self, db: Option<&str>, mode: CheckpointMode) -> Result<(i32, i32)> {
+        let cdb = db.map(CString::new).transpose()?;
+        let cdb_ptr = cdb.as_ref().map_or(ptr::null(), |c| c.as_ptr());
+        let mut log_frames: c_int = 0;
+        let mut checkpointed_frames: c_int = 0;
+        let rc = unsafe {
+            ffi::sqlite3_wal_checkpoint_v2(
+                self.db,
+                cdb_ptr,
+                mode.as_raw(),
+                &mut log_frames,
+                &mut checkpointed_frames,
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_wal_checkpoint_v2 failed"));
+        }
+        Ok((log_frames, checkpointed_frames))
+    }
+
+    /// Set how many WAL frames accumulate before SQLite automatically runs a
+    /// passive checkpoint, via `sqlite3_wal_autocheckpoint`. `frames <= 0`
+    /// disables automatic checkpointing entirely.
+    pub fn wal_autocheckpoint(&self, frames: i32) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_wal_autocheckpoint(self.db, frames as c_int) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_wal_autocheckpoint failed"));
+        }
+        Ok(())
+    }
+
+    /// Register `hook` to run every time a transaction commits to a
+    /// database in WAL mode, via `sqlite3_wal_hook`. `hook` receives the
+    /// schema name and the WAL's new size in frames; returning `Err`
+    /// propagates its [`Error::Sqlite`] code (or `SQLITE_ERROR` for any
+    /// other variant) back to SQLite as the commit's result code, aborting
+    /// it. Replaces whatever hook was previously registered, matching
+    /// `sqlite3_wal_hook`'s own single-callback-per-connection contract.
+    pub fn set_wal_hook<F>(&self, hook: F) -> Result<()>
+    where
+        F: FnMut(&str, i32) -> Result<()> + 'static,
+    {
+        self.clear_wal_hook();
+        let boxed: Box<WalHookCallback> = Box::new(Box::new(hook));
+        let ctx = Box::into_raw(boxed).cast::<std::ffi::c_void>();
+        unsafe { ffi::sqlite3_wal_hook(self.db, Some(wal_hook_trampoline), ctx) };
+        self.wal_hook.set(ctx);
+        Ok(())
+    }
+
+    /// Drop whatever boxed WAL hook closure is currently stashed on this
+    /// connection, if any. Mirrors [`clear_trace`](Self::clear_trace).
+    fn clear_wal_hook(&self) {
+        let ctx = self.wal_hook.replace(ptr::null_mut());
+        if !ctx.is_null() {
+            unsafe { drop(Box::from_raw(ctx.cast::<WalHookCallback>())) };
+        }
+    }
+
+    /// Register `handler` to run periodically during long-running
+    /// statements, via `sqlite3_progress_handler`, checked roughly every
+    /// `n_ops` internal SQLite VM instructions. Returning `true` from
+    /// `handler` interrupts the running statement (surfaced as
+    /// [`Error::Sqlite`] with `SQLITE_INTERRUPT`); returning `false` lets it
+    /// continue. Coexists with [`Connection::set_query_timeout`]: if a
+    /// timeout is also configured, its deadline check runs first on every
+    /// invocation and `handler` only runs if that deadline hasn't passed.
+    pub fn set_progress_handler<F>(&self, n_ops: i32, handler: F)
+    where
+        F: FnMut() -> bool + 'static,
+    {
+        let state = self.progress_state();
+        state.user = Some(Box::new(handler));
+        state.n_ops = n_ops as c_int;
+        self.reconcile_progress_handler();
+    }
+
+    /// Remove whatever handler [`Connection::set_progress_handler`]
+    /// installed, without disturbing a [`Connection::set_query_timeout`]
+    /// deadline check that may also be active.
+    pub fn clear_progress_handler(&self) {
+        if !self.progress_handler.get().is_null() {
+            self.progress_state().user = None;
+        }
+        self.reconcile_progress_handler();
+    }
+
+    /// Interrupt statements on this connection that run longer than
+    /// `timeout`, via a `sqlite3_progress_handler` callback that checks a
+    /// deadline armed fresh by [`Statement::step`](crate::statement::Statement::step)
+    /// at the start of each execution -- so the timeout bounds one
+    /// `step`-to-completion run, not the connection's whole lifetime, and a
+    /// query that finishes quickly is never affected. `None` disables it.
+    ///
+    /// If [`Connection::set_progress_handler`] has also been called (in
+    /// either order), both run: the timeout's deadline check always runs
+    /// first, and only calls into the user handler if it hasn't expired.
+    pub fn set_query_timeout(&self, timeout: Option<std::time::Duration>) {
+        self.query_timeout.set(timeout);
+        if timeout.is_none() {
+            if !self.progress_handler.get().is_null() {
+                self.progress_state().deadline.set(None);
+            }
+        } else {
+            self.progress_state(); // ensure allocated
+        }
+        self.reconcile_progress_handler();
+    }
+
+    /// Called by [`Statement::step`](crate::statement::Statement::step) at
+    /// the start of a fresh execution (i.e. when the statement isn't
+    /// currently mid-execution) to arm this run's timeout deadline, if one
+    /// is configured.
+    pub(crate) fn arm_query_timeout(&self) {
+        let Some(timeout) = self.query_timeout.get() else {
+            return;
+        };
+        self.progress_state().deadline.set(Some(std::time::Instant::now() + timeout));
+    }
+
+    /// The configured query timeout, if any -- used by [`Statement::step`]
+    /// to word a timeout error usefully.
+    pub(crate) fn query_timeout(&self) -> Option<std::time::Duration> {
+        self.query_timeout.get()
+    }
+
+    /// Call `callback` with a statement's expanded SQL and elapsed
+    /// wall-clock time whenever an execution -- from its first
+    /// [`Statement::step`](crate::statement::Statement::step) call to
+    /// either exhaustion, an error, or an early
+    /// [`Statement::reset`](crate::statement::Statement::reset) -- takes at
+    /// least `threshold`, including any time spent blocked in a busy
+    /// handler along the way. `None` disables it and drops `callback`.
+    /// Replaces whatever callback was previously registered.
+    ///
+    /// This is independent of [`Self::trace`]'s `sqlite3_trace_v2`
+    /// mechanism (which only ever has room for one callback across the
+    /// whole connection), so it composes with a caller's own trace.
+    ///
+    /// A panic inside `callback` is caught and discarded rather than
+    /// unwinding through the `step`/`reset` call that triggered it.
+    pub fn set_slow_query_threshold<F>(&self, threshold: Option<std::time::Duration>, callback: F)
+    where
+        F: Fn(&str, std::time::Duration) + 'static,
+    {
+        *self.slow_query_hook.borrow_mut() = threshold.map(|threshold| SlowQueryHook {
+            threshold,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Called by [`Statement::step`](crate::statement::Statement::step) and
+    /// [`Statement::reset`](crate::statement::Statement::reset) when an
+    /// execution ends, to report it to
+    /// [`Self::set_slow_query_threshold`]'s callback if one is installed and
+    /// `elapsed` meets its threshold.
+    pub(crate) fn report_slow_query(&self, sql: &str, elapsed: std::time::Duration) {
+        let hook = self.slow_query_hook.borrow();
+        if let Some(hook) = hook.as_ref() {
+            if elapsed >= hook.threshold {
+                let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| (hook.callback)(sql, elapsed)));
+            }
+        }
+    }
+
+    /// The [`ProgressState`] behind `self.progress_handler`, allocating one
+    /// (without yet installing the `sqlite3_progress_handler` C callback --
+    /// callers that need it installed call [`Self::reconcile_progress_handler`]
+    /// or [`Self::set_progress_handler`]'s own explicit install) if this is
+    /// the first call to touch it.
+    // The returned reference points at heap state reached through a raw
+    // pointer stashed in a `Cell`, not at anything borrowed from `self`.
+    #[allow(clippy::mut_from_ref)]
+    fn progress_state(&self) -> &mut ProgressState {
+        let mut ctx = self.progress_handler.get();
+        if ctx.is_null() {
+            let boxed = Box::new(ProgressState {
+                deadline: Cell::new(None),
+                user: None,
+                n_ops: DEFAULT_TIMEOUT_POLL_OPS,
+            });
+            ctx = Box::into_raw(boxed).cast::<std::ffi::c_void>();
+            self.progress_handler.set(ctx);
+        }
+        unsafe { &mut *ctx.cast::<ProgressState>() }
+    }
+
+    /// (Re)install or remove the `sqlite3_progress_handler` C callback to
+    /// match whether a user handler and/or a query timeout are currently
+    /// configured. The polling interval is whatever [`Self::set_progress_handler`]
+    /// last requested, or [`DEFAULT_TIMEOUT_POLL_OPS`] when only a timeout
+    /// is active.
+    fn reconcile_progress_handler(&self) {
+        let ctx = self.progress_handler.get();
+        if ctx.is_null() {
+            return;
+        }
+        let state = unsafe { &*ctx.cast::<ProgressState>() };
+        if state.user.is_none() && self.query_timeout.get().is_none() {
+            unsafe { ffi::sqlite3_progress_handler(self.db, 0, None, ptr::null_mut()) };
+            self.clear_progress_state();
+        } else {
+            unsafe { ffi::sqlite3_progress_handler(self.db, state.n_ops, Some(progress_trampoline), ctx) };
+        }
+    }
+
+    /// Free the boxed [`ProgressState`], if any. Only safe to call once the
+    /// `sqlite3_progress_handler` C callback pointing at it has been
+    /// cleared or replaced.
+    fn clear_progress_state(&self) {
+        let ctx = self.progress_handler.replace(ptr::null_mut());
+        if !ctx.is_null() {
+            unsafe { drop(Box::from_raw(ctx.cast::<ProgressState>())) };
+        }
+    }
+
+    /// Register `hook` to run just before each INSERT/UPDATE/DELETE against
+    /// a rowid table modifies a row, via `sqlite3_preupdate_hook` (this
+    /// crate's bundled SQLite is built with `SQLITE_ENABLE_PREUPDATE_HOOK`).
+    /// `hook` receives a [`PreUpdateEvent`] describing the change; its old
+    /// and new column values can only be fetched for the duration of the
+    /// call, matching `sqlite3_preupdate_old`/`new`'s own restriction.
+    /// Replaces whatever hook was previously registered, matching
+    /// `sqlite3_preupdate_hook`'s own single-callback-per-connection
+    /// contract.
+    pub fn set_preupdate_hook<F>(&self, hook: F)
+    where
+        F: FnMut(PreUpdateEvent) + 'static,
+    {
+        self.clear_preupdate_hook();
+        let boxed: Box<PreUpdateHookCallback> = Box::new(Box::new(hook));
+        let ctx = Box::into_raw(boxed).cast::<std::ffi::c_void>();
+        unsafe { ffi::sqlite3_preupdate_hook(self.db, Some(preupdate_trampoline), ctx) };
+        self.preupdate_hook.set(ctx);
+    }
+
+    /// Remove whatever hook [`Connection::set_preupdate_hook`] installed, if
+    /// any.
+    pub fn clear_preupdate_hook(&self) {
+        let ctx = self.preupdate_hook.replace(ptr::null_mut());
+        if !ctx.is_null() {
+            unsafe { ffi::sqlite3_preupdate_hook(self.db, None, ptr::null_mut()) };
+            unsafe { drop(Box::from_raw(ctx.cast::<PreUpdateHookCallback>())) };
+        }
+    }
+
+    /// Register `handler` to be called by SQLite whenever a call would
+    /// otherwise fail with `SQLITE_BUSY`, via `sqlite3_busy_handler`.
+    /// `handler` receives the number of times it's been invoked (starting
+    /// at `0`) for the current locked operation; returning `true` tells
+    /// SQLite to retry, `false` lets the `SQLITE_BUSY` error through.
+    /// Replaces whatever handler or [`Connection::set_busy_timeout`] was
+    /// previously registered, matching `sqlite3_busy_handler`'s own
+    /// single-callback-per-connection contract.
+    pub fn set_busy_handler<F>(&self, handler: F)
+    where
+        F: FnMut(i32) -> bool + 'static,
+    {
+        self.clear_busy_handler();
+        let boxed: Box<BusyHandlerCallback> = Box::new(Box::new(handler));
+        let ctx = Box::into_raw(boxed).cast::<std::ffi::c_void>();
+        unsafe { ffi::sqlite3_busy_handler(self.db, Some(busy_handler_trampoline), ctx) };
+        self.busy_handler.set(ctx);
+    }
+
+    /// Remove whatever handler [`Connection::set_busy_handler`] installed,
+    /// if any, so `SQLITE_BUSY` is reported immediately instead.
+    pub fn clear_busy_handler(&self) {
+        let ctx = self.busy_handler.replace(ptr::null_mut());
+        if !ctx.is_null() {
+            unsafe { ffi::sqlite3_busy_handler(self.db, None, ptr::null_mut()) };
+            unsafe { drop(Box::from_raw(ctx.cast::<BusyHandlerCallback>())) };
+        }
+    }
+
+    /// Retry a locked operation for up to `ms` milliseconds before letting
+    /// `SQLITE_BUSY` through, via `sqlite3_busy_timeout`. `sqlite3_busy_timeout`
+    /// installs its own internal busy handler, replacing whatever
+    /// [`Connection::set_busy_handler`] registered -- this also drops our
+    /// side of that handler so it isn't leaked. `ms <= 0` disables the
+    /// timeout (and any handler).
+    pub fn set_busy_timeout(&self, ms: i32) -> Result<()> {
+        let ctx = self.busy_handler.replace(ptr::null_mut());
+        if !ctx.is_null() {
+            unsafe { drop(Box::from_raw(ctx.cast::<BusyHandlerCallback>())) };
+        }
+        let rc = unsafe { ffi::sqlite3_busy_timeout(self.db, ms as c_int) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_busy_timeout failed"));
+        }
+        Ok(())
+    }
+
+    /// When enabled, [`Statement::step`](crate::statement::Statement::step)
+    /// on this connection transparently blocks and retries on
+    /// `SQLITE_LOCKED`/`SQLITE_LOCKED_SHAREDCACHE` instead of returning it
+    /// as an error, waiting via [`crate::unlock_notify`] for whatever
+    /// connection holds the conflicting lock to release it. Requires the
+    /// linked SQLite to have been built with `SQLITE_ENABLE_UNLOCK_NOTIFY`
+    /// (the workspace's bundled build turns this on; see the top-level
+    /// `.cargo/config.toml`) -- this toggle alone doesn't check for that,
+    /// so enabling it against a SQLite that lacks the symbol is a link
+    /// error, not a runtime one. Disabled by default.
+    #[cfg(feature = "unlock_notify")]
+    pub fn set_unlock_notify_blocking(&self, enabled: bool) {
+        self.unlock_notify_blocking.set(enabled);
+    }
+
+    /// Whether [`Connection::set_unlock_notify_blocking`] is currently
+    /// enabled -- read by [`Statement::step`](crate::statement::Statement::step).
+    #[cfg(feature = "unlock_notify")]
+    pub(crate) fn unlock_notify_blocking(&self) -> bool {
+        self.unlock_notify_blocking.get()
+    }
+
+    /// Close the connection, finalizing any statements the caller has
+    /// already dropped. Fails with `SQLITE_BUSY` if statements or backups
+    /// from this connection are still outstanding.
+    pub fn close(self) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_close(self.db) };
+        if rc != ffi::SQLITE_OK {
+            let err = connection_error(self.db, rc, "sqlite3_close failed");
+            std::mem::forget(self); // the handle is still alive; don't double-close in Drop
+            return Err(err);
+        }
+        self.clear_trace();
+        self.clear_wal_hook();
+        self.clear_progress_state();
+        self.clear_preupdate_hook();
+        self.clear_busy_handler();
+        std::mem::forget(self); // already closed successfully above
+        Ok(())
+    }
+
+    pub(crate) fn last_error(&self, context: &str) -> Error {
+        let rc = unsafe { ffi::sqlite3_errcode(self.db) };
+        connection_error(self.db, rc, context)
+    }
+
+    /// Like [`Self::last_error`], but folding `sql`/`param_summary` into the
+    /// result for a prepare or step failure that has that context
+    /// available.
+    pub(crate) fn last_error_with_statement_context(
+        &self,
+        context: &str,
+        sql: Option<String>,
+        param_summary: Option<String>,
+    ) -> Error {
+        let rc = unsafe { ffi::sqlite3_errcode(self.db) };
+        connection_error_with_statement_context(self.db, rc, context, sql, param_summary)
+    }
+
+    /// Controls whether [`Error::Sqlite`]'s `param_summary` (attached to
+    /// prepare/step failures) includes bound parameters' actual values, via
+    /// [`ErrorVerbosity::WithValues`], or only their types and lengths, via
+    /// the default [`ErrorVerbosity::Summary`]. Values are opt-in since
+    /// they can be sensitive (or simply large) and would otherwise end up
+    /// in error logs unconditionally.
+    pub fn set_error_verbosity(&self, verbosity: ErrorVerbosity) {
+        self.error_verbosity.set(verbosity);
+    }
+
+    pub(crate) fn error_verbosity(&self) -> ErrorVerbosity {
+        self.error_verbosity.get()
+    }
+
+    /// The rowid of the most recent successful `INSERT` on this connection,
+    /// via `sqlite3_last_insert_rowid`.
+    pub fn last_insert_rowid(&self) -> i64 {
+        unsafe { ffi::sqlite3_last_insert_rowid(self.db) }
+    }
+
+    /// Override the value [`last_insert_rowid`](Self::last_insert_rowid)
+    /// reports, without actually performing an insert, via
+    /// `sqlite3_set_last_insert_rowid`.
+    pub fn set_last_insert_rowid(&self, rowid: i64) {
+        unsafe { ffi::sqlite3_set_last_insert_rowid(self.db, rowid) };
+    }
+
+    /// Number of rows inserted/updated/deleted by the most recently
+    /// completed statement, via `sqlite3_changes64`.
+    pub fn changes(&self) -> i64 {
+        unsafe { ffi::sqlite3_changes64(self.db) }
+    }
+
+    /// Total number of rows inserted/updated/deleted since this connection
+    /// was opened, via `sqlite3_total_changes64`.
+    pub fn total_changes(&self) -> i64 {
+        unsafe { ffi::sqlite3_total_changes64(self.db) }
+    }
+
+    /// Read one of this connection's runtime status counters, via
+    /// `sqlite3_db_status`. Returns `(current, highwater)`; if `reset` is
+    /// `true`, the highwater mark is reset back down to the current value
+    /// afterward.
+    pub fn db_status(&self, op: DbStatusOp, reset: bool) -> Result<(i64, i64)> {
+        let mut current: c_int = 0;
+        let mut highwater: c_int = 0;
+        let rc = unsafe {
+            ffi::sqlite3_db_status(self.db, op.as_raw(), &mut current, &mut highwater, reset as c_int)
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_db_status failed"));
+        }
+        Ok((current as i64, highwater as i64))
+    }
+
+    /// Force any dirty pages this connection holds out to disk mid-transaction,
+    /// via `sqlite3_db_cacheflush`, without committing. Useful for
+    /// applications doing their own checkpoint-like coordination that need
+    /// writes durable before a transaction ends. Fails with `SQLITE_BUSY`
+    /// (surfaced as [`Error::Sqlite`] with that code) if another statement
+    /// on this connection holds a page that needs flushing open.
+    pub fn cache_flush(&self) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_db_cacheflush(self.db) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_db_cacheflush failed"));
+        }
+        Ok(())
+    }
+
+    /// Free as much heap memory as possible by discarding unused pages from
+    /// this connection's caches, via `sqlite3_db_release_memory`.
+    pub fn release_memory(&self) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_db_release_memory(self.db) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_db_release_memory failed"));
+        }
+        Ok(())
+    }
+
+    /// Install `hooks` so that [`Statement::row`](crate::statement::Statement::row)
+    /// runs a matching column's value through its registered conversion
+    /// before handing it back, per the column's `column_decltype`. Replaces
+    /// any hooks installed by an earlier call.
+    pub fn set_type_hooks(&self, hooks: TypeHooks) {
+        *self.type_hooks.borrow_mut() = Some(Rc::new(hooks));
+    }
+
+    /// The [`TypeHooks`] most recently installed by [`Connection::set_type_hooks`],
+    /// if any.
+    pub(crate) fn type_hooks(&self) -> Option<Rc<TypeHooks>> {
+        self.type_hooks.borrow().clone()
+    }
+
+    /// Read `amount` bytes at `offset` from the `schema` database's
+    /// underlying file (`which == 0`) or its journal/WAL (`which != 0`),
+    /// bypassing the page cache entirely via the VFS's `xRead` method.
+    ///
+    /// Mirrors APSW's `Connection.read`: the returned `bool` is `true` if
+    /// the full `amount` was read, `false` if the read ran past end of file
+    /// (in which case the unread tail of the buffer is zero-padded, per the
+    /// `xRead` contract).
+    pub fn read(&self, schema: &str, which: i32, offset: i64, amount: usize) -> Result<(bool, Vec<u8>)> {
+        let file = self.file_pointer(schema, which)?;
+        let methods = unsafe { &*(*file).pMethods };
+        let xread = methods
+            .xRead
+            .ok_or(Error::Misuse("VFS file has no xRead method"))?;
+        let c_amount =
+            c_int::try_from(amount).map_err(|_| Error::Misuse("read amount exceeds i32::MAX"))?;
+        let mut buf = vec![0u8; amount];
+        let rc = unsafe { xread(file, buf.as_mut_ptr().cast(), c_amount, offset) };
+        match rc {
+            ffi::SQLITE_OK => Ok((true, buf)),
+            ffi::SQLITE_IOERR_SHORT_READ => Ok((false, buf)),
+            _ => Err(self.last_error("xRead failed")),
+        }
+    }
+
+    /// The name of the VFS (or, for a VFS shim stacked over another, a
+    /// `/`-joined chain of names, outermost first) backing `schema`'s
+    /// database file, via `SQLITE_FCNTL_VFSNAME`.
+    pub fn vfs_name(&self, schema: &str) -> Result<String> {
+        let cschema = CString::new(schema)?;
+        let mut out: *mut c_char = ptr::null_mut();
+        let rc = unsafe {
+            ffi::sqlite3_file_control(
+                self.db,
+                cschema.as_ptr(),
+                ffi::SQLITE_FCNTL_VFSNAME,
+                (&mut out as *mut *mut c_char).cast(),
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "sqlite3_file_control(SQLITE_FCNTL_VFSNAME) failed"));
+        }
+        if out.is_null() {
+            return Err(Error::Misuse("VFS did not report a name"));
+        }
+        let name = unsafe { CStr::from_ptr(out) }.to_string_lossy().into_owned();
+        unsafe { ffi::sqlite3_free(out.cast()) };
+        Ok(name)
+    }
+
+    /// The filename associated with `schema`'s database file, via
+    /// `sqlite3_db_filename`. `None` for a temporary or in-memory database
+    /// (including `:memory:`), which have no filename.
+    pub fn db_filename(&self, schema: &str) -> Result<Option<String>> {
+        let cschema = CString::new(schema)?;
+        let name = unsafe { ffi::sqlite3_db_filename(self.db, cschema.as_ptr()) };
+        Ok(c_str_to_string(name).filter(|s| !s.is_empty()))
+    }
+
+    /// Whether `schema`'s database was opened read-only, via
+    /// `sqlite3_db_readonly`. [`Error::Misuse`] if `schema` doesn't name an
+    /// attached database.
+    pub fn is_readonly(&self, schema: &str) -> Result<bool> {
+        let cschema = CString::new(schema)?;
+        match unsafe { ffi::sqlite3_db_readonly(self.db, cschema.as_ptr()) } {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Misuse("no such attached database")),
+        }
+    }
+
+    /// The names of every attached database, in SQLite's own order (`main`
+    /// and `temp` first, then any `ATTACH`ed databases in attach order), via
+    /// `sqlite3_db_name(db, i)` for `i` starting at 0 until it returns
+    /// `NULL`.
+    pub fn db_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut i: c_int = 0;
+        loop {
+            let name = unsafe { ffi::sqlite3_db_name(self.db, i) };
+            match c_str_to_string(name) {
+                Some(name) => names.push(name),
+                None => break,
+            }
+            i += 1;
+        }
+        names
+    }
+
+    /// Capture a snapshot of `schema`'s current state. See
+    /// [`crate::snapshot::snapshot_get`].
+    pub fn snapshot_get(&self, schema: &str) -> Result<crate::snapshot::Snapshot> {
+        crate::snapshot::snapshot_get(self, schema)
+    }
+
+    /// Start a read transaction on `schema` that sees exactly `snapshot`'s
+    /// state. See [`crate::snapshot::snapshot_open`].
+    pub fn snapshot_open(&self, schema: &str, snapshot: &crate::snapshot::Snapshot) -> Result<()> {
+        crate::snapshot::snapshot_open(self, schema, snapshot)
+    }
+
+    /// Reconstruct the set of snapshots still recoverable from `schema`'s
+    /// WAL after reopening it. See [`crate::snapshot::snapshot_recover`].
+    pub fn snapshot_recover(&self, schema: &str) -> Result<()> {
+        crate::snapshot::snapshot_recover(self, schema)
+    }
+
+    /// Obtain the `sqlite3_file*` backing `schema`'s main database file or
+    /// its journal, via `SQLITE_FCNTL_FILE_POINTER` / `SQLITE_FCNTL_JOURNAL_POINTER`.
+    fn file_pointer(&self, schema: &str, which: i32) -> Result<*mut ffi::sqlite3_file> {
+        let cschema = CString::new(schema)?;
+        let op = if which == 0 {
+            ffi::SQLITE_FCNTL_FILE_POINTER
+        } else {
+            ffi::SQLITE_FCNTL_JOURNAL_POINTER
+        };
+        let mut file: *mut ffi::sqlite3_file = ptr::null_mut();
+        let rc = unsafe {
+            ffi::sqlite3_file_control(
+                self.db,
+                cschema.as_ptr(),
+                op,
+                (&mut file as *mut *mut ffi::sqlite3_file).cast(),
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_file_control failed"));
+        }
+        if file.is_null() {
+            return Err(Error::Misuse(
+                "no file pointer available for this database/journal",
+            ));
+        }
+        Ok(file)
+    }
+
+    /// Declare that the application (or a virtual table's `xFindFunction`)
+    /// may supply an overloaded implementation of the SQL function `name`
+    /// taking `nargs` arguments, via `sqlite3_overload_function`.
+    ///
+    /// Without this, SQLite rejects the function outright as "no such
+    /// function" before a virtual table ever gets a chance to claim it
+    /// through `xFindFunction`; calling it on an ordinary table still fails,
+    /// but with SQLite's "unable to use function in the requested context"
+    /// error instead.
+    pub fn overload_function(&self, name: &str, nargs: i32) -> Result<()> {
+        let cname = CString::new(name)?;
+        let rc = unsafe { ffi::sqlite3_overload_function(self.db, cname.as_ptr(), nargs as c_int) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_overload_function failed"));
+        }
+        Ok(())
+    }
+
+    /// Toggle `SQLITE_DBCONFIG_TRUSTED_SCHEMA`: whether SQL stored in the
+    /// schema itself (views, triggers, CHECK constraints, generated
+    /// columns) may call functions that haven't been registered
+    /// `SQLITE_INNOCUOUS`. On by default; turning it off is how to
+    /// exercise the "untrusted schema" code path deliberately.
+    pub fn set_trusted_schema(&self, enabled: bool) -> Result<()> {
+        let rc = unsafe {
+            ffi::sqlite3_db_config_int(self.db, ffi::SQLITE_DBCONFIG_TRUSTED_SCHEMA, enabled as c_int, ptr::null_mut())
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_db_config failed"));
+        }
+        Ok(())
+    }
+
+    /// Register a custom FTS5 tokenizer through the real `fts5_api`. See
+    /// [`crate::fts5::create_tokenizer`] for the raw callback contract.
+    ///
+    /// # Safety
+    ///
+    /// See [`crate::fts5::create_tokenizer`].
+    pub unsafe fn create_fts5_tokenizer(
+        &self,
+        name: &str,
+        user_data: *mut std::ffi::c_void,
+        tokenizer: ffi::fts5_tokenizer,
+        destroy: Option<unsafe extern "C" fn(*mut std::ffi::c_void)>,
+    ) -> Result<()> {
+        unsafe { crate::fts5::create_tokenizer(self, name, user_data, tokenizer, destroy) }
+    }
+
+    /// `true` if `name` is registered as an FTS5 tokenizer, via
+    /// `fts5_api::xFindTokenizer`. See [`crate::fts5::tokenizer_available`].
+    pub fn fts5_tokenizer_available(&self, name: &str) -> Result<bool> {
+        crate::fts5::tokenizer_available(self, name)
+    }
+
+    /// Register a custom FTS5 auxiliary function, via the real
+    /// `fts5_api::xCreateFunction`. See [`crate::fts5::create_function`].
+    ///
+    /// # Safety
+    ///
+    /// See [`crate::fts5::create_function`].
+    pub unsafe fn create_fts5_function(
+        &self,
+        name: &str,
+        user_data: *mut std::ffi::c_void,
+        function: ffi::fts5_extension_function,
+        destroy: Option<unsafe extern "C" fn(*mut std::ffi::c_void)>,
+    ) -> Result<()> {
+        unsafe { crate::fts5::create_function(self, name, user_data, function, destroy) }
+    }
+
+    /// Register a scalar SQL function, via `sqlite3_create_function_v2`.
+    /// See [`crate::function::create_scalar_function`].
+    ///
+    /// # Safety
+    ///
+    /// See [`crate::function::create_scalar_function`].
+    pub unsafe fn create_scalar_function(
+        &self,
+        name: &str,
+        nargs: i32,
+        flags: i32,
+        user_data: *mut std::ffi::c_void,
+        function: unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value),
+        destroy: Option<unsafe extern "C" fn(*mut std::ffi::c_void)>,
+    ) -> Result<()> {
+        unsafe { crate::function::create_scalar_function(self, name, nargs, flags, user_data, function, destroy) }
+    }
+
+    /// Register `uuid()`/`uuid_blob()`/`uuid_str()`. See
+    /// [`crate::extra_functions::register_uuid`].
+    #[cfg(feature = "extra-functions")]
+    pub fn register_uuid(&self) -> Result<()> {
+        crate::extra_functions::register_uuid(self)
+    }
+
+    /// Register the `REGEXP` operator. See
+    /// [`crate::extra_functions::register_regexp`].
+    #[cfg(feature = "extra-functions")]
+    pub fn register_regexp(&self) -> Result<std::sync::Arc<crate::extra_functions::RegexpCache>> {
+        crate::extra_functions::register_regexp(self)
+    }
+
+    /// Register `sha1()`/`sha256()`/`md5()`. See
+    /// [`crate::extra_functions::register_digest`].
+    #[cfg(feature = "extra-functions")]
+    pub fn register_digest(&self) -> Result<()> {
+        crate::extra_functions::register_digest(self)
+    }
+
+    /// Register an aggregate window function, via
+    /// `sqlite3_create_window_function`. See
+    /// [`crate::window::create_window_function`].
+    ///
+    /// # Safety
+    ///
+    /// See [`crate::window::create_window_function`].
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn create_window_function(
+        &self,
+        name: &str,
+        nargs: i32,
+        flags: i32,
+        user_data: *mut std::ffi::c_void,
+        step: unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value),
+        final_: unsafe extern "C" fn(*mut ffi::sqlite3_context),
+        value: unsafe extern "C" fn(*mut ffi::sqlite3_context),
+        inverse: unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value),
+        destroy: Option<unsafe extern "C" fn(*mut std::ffi::c_void)>,
+    ) -> Result<()> {
+        unsafe {
+            crate::window::create_window_function(self, name, nargs, flags, user_data, step, final_, value, inverse, destroy)
+        }
+    }
+
+    /// Enter the "currently constructing a virtual table" window
+    /// [`vtab_config`](Self::vtab_config) requires. Real module dispatch
+    /// for `xCreate`/`xConnect` should hold the returned guard for the
+    /// duration of the callback, once this crate has virtual table module
+    /// support; for now only tests call this directly.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) fn enter_vtab_construction(&self) -> VtabConstructionGuard<'_> {
+        self.in_vtab_construction.set(true);
+        VtabConstructionGuard { conn: self }
+    }
+
+    /// Configure the virtual table currently under construction, via
+    /// `sqlite3_vtab_config`. Only valid from inside a module's `xCreate`
+    /// or `xConnect` callback; [`Error::Misuse`] otherwise.
+    pub fn vtab_config(&self, option: VtabConfigOption) -> Result<()> {
+        if !self.in_vtab_construction.get() {
+            return Err(Error::Misuse("vtab_config is only valid inside xCreate/xConnect"));
+        }
+        let rc = match option {
+            VtabConfigOption::ConstraintSupport(supported) => unsafe {
+                ffi::sqlite3_vtab_config_int(self.db, ffi::SQLITE_VTAB_CONSTRAINT_SUPPORT, supported as c_int)
+            },
+            VtabConfigOption::Innocuous => unsafe {
+                ffi::sqlite3_vtab_config_noarg(self.db, ffi::SQLITE_VTAB_INNOCUOUS)
+            },
+            VtabConfigOption::DirectOnly => unsafe {
+                ffi::sqlite3_vtab_config_noarg(self.db, ffi::SQLITE_VTAB_DIRECTONLY)
+            },
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_vtab_config failed"));
+        }
+        Ok(())
+    }
+
+    /// The `ON CONFLICT` resolution a virtual table's `xUpdate` should
+    /// honor for the write it's currently handling, via
+    /// `sqlite3_vtab_on_conflict`. Only meaningful when called from inside
+    /// `xUpdate`; SQLite documents the result as otherwise unspecified.
+    pub fn vtab_on_conflict(&self) -> ConflictResolution {
+        match unsafe { ffi::sqlite3_vtab_on_conflict(self.db) } {
+            ffi::SQLITE_ROLLBACK => ConflictResolution::Rollback,
+            ffi::SQLITE_IGNORE => ConflictResolution::Ignore,
+            ffi::SQLITE_FAIL => ConflictResolution::Fail,
+            ffi::SQLITE_REPLACE => ConflictResolution::Replace,
+            _ => ConflictResolution::Abort,
+        }
+    }
+
+    /// Unregister every virtual table module except those named in `keep`,
+    /// via `sqlite3_drop_modules`. Each dropped module's `xDestroy`
+    /// (registered client data destructor, if any) runs as part of the
+    /// call; any virtual table instance still open on a dropped module
+    /// keeps working until it's closed, matching the underlying API.
+    pub fn drop_modules(&self, keep: &[&str]) -> Result<()> {
+        let ckeep = keep.iter().map(|name| CString::new(*name)).collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut pointers: Vec<*const std::os::raw::c_char> = ckeep.iter().map(|name| name.as_ptr()).collect();
+        pointers.push(std::ptr::null());
+        let rc = unsafe { ffi::sqlite3_drop_modules(self.db, pointers.as_mut_ptr()) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "sqlite3_drop_modules failed"));
+        }
+        Ok(())
+    }
+
+    /// Look up a column's declared type, collation sequence, and
+    /// `NOT NULL`/`PRIMARY KEY`/`AUTOINCREMENT` flags, via
+    /// `sqlite3_table_column_metadata`. `schema` defaults to searching every
+    /// attached database (main first) when `None`, matching the underlying
+    /// function's own "`NULL` means search all" behavior.
+    pub fn table_column_metadata(
+        &self,
+        schema: Option<&str>,
+        table: &str,
+        column: &str,
+    ) -> Result<ColumnMetadata> {
+        let cschema = schema.map(CString::new).transpose()?;
+        let ctable = CString::new(table)?;
+        let ccolumn = CString::new(column)?;
+        let mut data_type: *const c_char = ptr::null();
+        let mut coll_seq: *const c_char = ptr::null();
+        let mut not_null: c_int = 0;
+        let mut primary_key: c_int = 0;
+        let mut autoincrement: c_int = 0;
+        let rc = unsafe {
+            ffi::sqlite3_table_column_metadata(
+                self.db,
+                cschema.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
+                ctable.as_ptr(),
+                ccolumn.as_ptr(),
+                &mut data_type,
+                &mut coll_seq,
+                &mut not_null,
+                &mut primary_key,
+                &mut autoincrement,
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.last_error("sqlite3_table_column_metadata failed"));
+        }
+        Ok(ColumnMetadata {
+            data_type: c_str_to_string(data_type),
+            collation: c_str_to_string(coll_seq),
+            not_null: not_null != 0,
+            primary_key: primary_key != 0,
+            autoincrement: autoincrement != 0,
+        })
+    }
+}
+
+/// What [`Connection::vtab_config`] configures for the virtual table
+/// currently under construction -- see the `SQLITE_VTAB_*` constants in
+/// `sqlite3.h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtabConfigOption {
+    /// The module's `xBestIndex`/`xUpdate` understand `SQLITE_INDEX_CONSTRAINT_*`
+    /// operators well enough to report constraint usage back, via
+    /// `SQLITE_VTAB_CONSTRAINT_SUPPORT`.
+    ConstraintSupport(bool),
+    /// The module is safe to use from triggers/views created by untrusted
+    /// input, via `SQLITE_VTAB_INNOCUOUS`.
+    Innocuous,
+    /// The module may only be used in top-level SQL, never from a trigger
+    /// or view, via `SQLITE_VTAB_DIRECTONLY`.
+    DirectOnly,
+}
+
+/// The `ON CONFLICT` resolution in effect for a virtual table's `xUpdate`,
+/// as reported by `sqlite3_vtab_on_conflict`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    Rollback,
+    Ignore,
+    Fail,
+    Abort,
+    Replace,
+}
+
+/// How much detail [`Connection::set_error_verbosity`] includes about a
+/// prepared statement's bound parameters in a prepare/step failure's
+/// `param_summary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorVerbosity {
+    /// Each bound parameter's type and length, but never its value (the
+    /// default).
+    #[default]
+    Summary,
+    /// Each bound parameter's type and its actual value.
+    WithValues,
+}
+
+/// A column's schema metadata, as returned by
+/// [`Connection::table_column_metadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMetadata {
+    /// The column's declared type, e.g. `"INTEGER"`. `None` for a column
+    /// with no declared type (including the rowid alias columns of a
+    /// `WITHOUT ROWID` table).
+    pub data_type: Option<String>,
+    /// The column's collation sequence, e.g. `"BINARY"`. Always `Some` for
+    /// an ordinary column -- every column has at least the default `BINARY`
+    /// collation.
+    pub collation: Option<String>,
+    /// Whether the column has a `NOT NULL` constraint.
+    pub not_null: bool,
+    /// Whether the column is (part of) the table's `PRIMARY KEY`.
+    pub primary_key: bool,
+    /// Whether the column is `INTEGER PRIMARY KEY AUTOINCREMENT`.
+    pub autoincrement: bool,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // `sqlite3_close_v2` tolerates unfinalized statements (it defers
+        // the actual close), unlike `sqlite3_close`. A registered trace
+        // callback (see `trace`) may still fire -- e.g. `TraceEvent::Close`
+        // -- during this call, so the boxed closure is only freed after.
+        unsafe { ffi::sqlite3_close_v2(self.db) };
+        self.clear_trace();
+        self.clear_wal_hook();
+        self.clear_progress_state();
+        self.clear_preupdate_hook();
+        self.clear_busy_handler();
+    }
+}
+
+/// Bitmask of [`TraceEvent`] kinds to observe, for [`Connection::trace`].
+/// Mirrors `sqlite3_trace_v2`'s `uMask` argument (`SQLITE_TRACE_STMT` etc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TraceMask(c_uint);
+
+impl TraceMask {
+    /// An empty mask: passing this to [`Connection::trace`] unregisters
+    /// any existing trace callback instead of installing a new one.
+    pub const NONE: TraceMask = TraceMask(0);
+    pub const STMT: TraceMask = TraceMask(ffi::SQLITE_TRACE_STMT as c_uint);
+    pub const PROFILE: TraceMask = TraceMask(ffi::SQLITE_TRACE_PROFILE as c_uint);
+    pub const ROW: TraceMask = TraceMask(ffi::SQLITE_TRACE_ROW as c_uint);
+    pub const CLOSE: TraceMask = TraceMask(ffi::SQLITE_TRACE_CLOSE as c_uint);
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl std::ops::BitOr for TraceMask {
+    type Output = TraceMask;
+
+    fn bitor(self, rhs: TraceMask) -> TraceMask {
+        TraceMask(self.0 | rhs.0)
+    }
+}
+
+/// An event delivered to a [`Connection::trace`] callback.
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+    /// A statement is about to run. `sql` is its expanded text (bound
+    /// parameters substituted in, via `sqlite3_expanded_sql`); `unexpanded`
+    /// is the raw SQL text SQLite prepared it from (or a `-- trigger ...`
+    /// comment, for statements run on behalf of a trigger).
+    Stmt { sql: String, unexpanded: String },
+    /// A statement has finished running. `sql` is its expanded text;
+    /// `nanos` is the wall-clock time it took, in nanoseconds.
+    Profile { sql: String, nanos: u64 },
+    /// A statement has produced a result row.
+    Row,
+    /// The connection is closing.
+    Close,
+}
+
+/// The concrete boxed closure type stashed behind the `sqlite3_trace_v2`
+/// context pointer. Double-boxed so the `dyn` fat pointer fits in the
+/// single thin `*mut c_void` SQLite hands back to [`trace_trampoline`].
+type TraceCallback = Box<dyn FnMut(TraceEvent)>;
+
+unsafe extern "C" fn trace_trampoline(
+    mask: c_uint,
+    ctx: *mut std::ffi::c_void,
+    p: *mut std::ffi::c_void,
+    x: *mut std::ffi::c_void,
+) -> c_int {
+    let callback = unsafe { &mut *ctx.cast::<TraceCallback>() };
+    let event = match mask as i32 {
+        ffi::SQLITE_TRACE_STMT => {
+            let unexpanded = c_str_to_string(x.cast()).unwrap_or_default();
+            let sql = expanded_sql_of(p.cast()).unwrap_or_else(|| unexpanded.clone());
+            TraceEvent::Stmt { sql, unexpanded }
+        }
+        ffi::SQLITE_TRACE_PROFILE => {
+            let nanos = unsafe { *x.cast::<i64>() }.max(0) as u64;
+            let sql = expanded_sql_of(p.cast()).unwrap_or_default();
+            TraceEvent::Profile { sql, nanos }
+        }
+        ffi::SQLITE_TRACE_ROW => TraceEvent::Row,
+        ffi::SQLITE_TRACE_CLOSE => TraceEvent::Close,
+        _ => return 0,
+    };
+    callback(event);
+    0
+}
+
+/// `sqlite3_expanded_sql`, freeing the result itself since the caller only
+/// wants an owned `String`.
+fn expanded_sql_of(stmt: *mut ffi::sqlite3_stmt) -> Option<String> {
+    let ptr = unsafe { ffi::sqlite3_expanded_sql(stmt) };
+    let sql = c_str_to_string(ptr);
+    unsafe { ffi::sqlite3_free(ptr.cast()) };
+    sql
+}
+
+fn c_str_to_string(ptr: *const std::os::raw::c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+    }
+}
+
+/// Which `sqlite3_db_status` counter to read, for [`Connection::db_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbStatusOp {
+    LookasideUsed,
+    CacheUsed,
+    SchemaUsed,
+    StmtUsed,
+    LookasideHit,
+    LookasideMissSize,
+    LookasideMissFull,
+    CacheHit,
+    CacheMiss,
+    CacheWrite,
+    DeferredFks,
+    CacheUsedShared,
+    CacheSpill,
+}
+
+impl DbStatusOp {
+    fn as_raw(self) -> c_int {
+        match self {
+            DbStatusOp::LookasideUsed => ffi::SQLITE_DBSTATUS_LOOKASIDE_USED,
+            DbStatusOp::CacheUsed => ffi::SQLITE_DBSTATUS_CACHE_USED,
+            DbStatusOp::SchemaUsed => ffi::SQLITE_DBSTATUS_SCHEMA_USED,
+            DbStatusOp::StmtUsed => ffi::SQLITE_DBSTATUS_STMT_USED,
+            DbStatusOp::LookasideHit => ffi::SQLITE_DBSTATUS_LOOKASIDE_HIT,
+            DbStatusOp::LookasideMissSize => ffi::SQLITE_DBSTATUS_LOOKASIDE_MISS_SIZE,
+            DbStatusOp::LookasideMissFull => ffi::SQLITE_DBSTATUS_LOOKASIDE_MISS_FULL,
+            DbStatusOp::CacheHit => ffi::SQLITE_DBSTATUS_CACHE_HIT,
+            DbStatusOp::CacheMiss => ffi::SQLITE_DBSTATUS_CACHE_MISS,
+            DbStatusOp::CacheWrite => ffi::SQLITE_DBSTATUS_CACHE_WRITE,
+            DbStatusOp::DeferredFks => ffi::SQLITE_DBSTATUS_DEFERRED_FKS,
+            DbStatusOp::CacheUsedShared => ffi::SQLITE_DBSTATUS_CACHE_USED_SHARED,
+            DbStatusOp::CacheSpill => ffi::SQLITE_DBSTATUS_CACHE_SPILL,
+        }
+    }
+}
+
+/// Which `sqlite3_wal_checkpoint_v2` mode to run, for
+/// [`Connection::wal_checkpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointMode {
+    /// Checkpoint as many frames as possible without blocking.
+    Passive,
+    /// Block until every WAL frame is checkpointed.
+    Full,
+    /// Like `Full`, and also block until every other connection's read
+    /// transaction on this database finishes, so the WAL can be reset back
+    /// to the start of the file.
+    Restart,
+    /// Like `Restart`, and additionally truncate the WAL file to zero bytes
+    /// once it's been reset.
+    Truncate,
+}
+
+impl CheckpointMode {
+    fn as_raw(self) -> c_int {
+        match self {
+            CheckpointMode::Passive => ffi::SQLITE_CHECKPOINT_PASSIVE,
+            CheckpointMode::Full => ffi::SQLITE_CHECKPOINT_FULL,
+            CheckpointMode::Restart => ffi::SQLITE_CHECKPOINT_RESTART,
+            CheckpointMode::Truncate => ffi::SQLITE_CHECKPOINT_TRUNCATE,
+        }
+    }
+}
+
+/// The kind of row change a [`PreUpdateEvent`] reports, per
+/// `sqlite3_preupdate_hook`'s `op` argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreUpdateOp {
+    Insert,
+    Update,
+    Delete,
+    /// A code `sqlite3_preupdate_hook` isn't documented to send, kept
+    /// around so a future SQLite operation code doesn't need an API break.
+    Other(c_int),
+}
+
+impl PreUpdateOp {
+    fn from_raw(op: c_int) -> Self {
+        match op {
+            ffi::SQLITE_INSERT => PreUpdateOp::Insert,
+            ffi::SQLITE_UPDATE => PreUpdateOp::Update,
+            ffi::SQLITE_DELETE => PreUpdateOp::Delete,
+            other => PreUpdateOp::Other(other),
+        }
+    }
+}
+
+/// A row change about to happen, delivered to a
+/// [`Connection::set_preupdate_hook`] callback.
+///
+/// `old_value`/`new_value`/`column_count`/`depth`/`blobwrite_column` all read through
+/// `sqlite3_preupdate_*`, which SQLite only allows for the duration of the
+/// preupdate callback; calling any of them after the callback that produced
+/// this event has returned fails with [`Error::Misuse`] instead of
+/// forwarding to SQLite, which is undefined behavior once the callback has
+/// returned.
+pub struct PreUpdateEvent {
+    db: *mut ffi::sqlite3,
+    op: PreUpdateOp,
+    database: String,
+    table: String,
+    old_rowid: i64,
+    new_rowid: i64,
+    armed: Rc<Cell<bool>>,
+}
+
+impl PreUpdateEvent {
+    /// Whether this is an INSERT, UPDATE, or DELETE.
+    pub fn op(&self) -> PreUpdateOp {
+        self.op
+    }
+
+    /// The name of the schema the change is against, e.g. `"main"`.
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// The name of the table being changed.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// The rowid the change reads from -- meaningful for `Update`/`Delete`,
+    /// equal to `new_rowid` for `Insert`.
+    pub fn old_rowid(&self) -> i64 {
+        self.old_rowid
+    }
+
+    /// The rowid the change writes to -- meaningful for `Update`/`Insert`,
+    /// equal to `old_rowid` for `Delete`.
+    pub fn new_rowid(&self) -> i64 {
+        self.new_rowid
+    }
+
+    /// The number of columns in the row being changed, via
+    /// `sqlite3_preupdate_count`.
+    pub fn column_count(&self) -> Result<usize> {
+        self.ensure_armed()?;
+        Ok(unsafe { ffi::sqlite3_preupdate_count(self.db) } as usize)
+    }
+
+    /// How deeply nested this change is inside trigger programs, via
+    /// `sqlite3_preupdate_depth`: `0` for a change made directly by the
+    /// top-level statement, `> 0` for one made by a trigger it fired.
+    pub fn depth(&self) -> Result<i32> {
+        self.ensure_armed()?;
+        Ok(unsafe { ffi::sqlite3_preupdate_depth(self.db) })
+    }
+
+    /// The column index a `sqlite3_blob_write` is about to write through,
+    /// if this change is one of those rather than an ordinary
+    /// INSERT/UPDATE/DELETE, via `sqlite3_preupdate_blobwrite`.
+    pub fn blobwrite_column(&self) -> Result<Option<usize>> {
+        self.ensure_armed()?;
+        match unsafe { ffi::sqlite3_preupdate_blobwrite(self.db) } {
+            -1 => Ok(None),
+            i => Ok(Some(i as usize)),
+        }
+    }
+
+    /// The pre-change value of column `i`, via `sqlite3_preupdate_old`.
+    /// Not meaningful (and not called) for `Insert`.
+    pub fn old_value(&self, i: usize) -> Result<Value> {
+        self.ensure_armed()?;
+        let mut value: *mut ffi::sqlite3_value = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_preupdate_old(self.db, i as c_int, &mut value) };
+        if rc != ffi::SQLITE_OK {
+            return Err(connection_error(self.db, rc, "sqlite3_preupdate_old failed"));
+        }
+        Ok(unsafe { crate::function::value_to_value(value) })
+    }
+
+    /// The post-change value of column `i`, via `sqlite3_preupdate_new`.
+    /// Not meaningful (and not called) for `Delete`.
+    pub fn new_value(&self, i: usize) -> Result<Value> {
+        self.ensure_armed()?;
+        let mut value: *mut ffi::sqlite3_value = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_preupdate_new(self.db, i as c_int, &mut value) };
+        if rc != ffi::SQLITE_OK {
+            return Err(connection_error(self.db, rc, "sqlite3_preupdate_new failed"));
+        }
+        Ok(unsafe { crate::function::value_to_value(value) })
+    }
+
+    fn ensure_armed(&self) -> Result<()> {
+        if self.armed.get() {
+            Ok(())
+        } else {
+            Err(Error::Misuse(
+                "PreUpdateEvent accessed after its preupdate callback returned",
+            ))
+        }
+    }
+}
+
+/// The concrete boxed closure type stashed behind the
+/// `sqlite3_preupdate_hook` context pointer, mirroring [`WalHookCallback`].
+type PreUpdateHookCallback = Box<dyn FnMut(PreUpdateEvent)>;
+
+unsafe extern "C" fn preupdate_trampoline(
+    ctx: *mut std::ffi::c_void,
+    db: *mut ffi::sqlite3,
+    op: c_int,
+    z_db: *const std::os::raw::c_char,
+    z_table: *const std::os::raw::c_char,
+    old_rowid: ffi::sqlite3_int64,
+    new_rowid: ffi::sqlite3_int64,
+) {
+    let hook = unsafe { &mut *ctx.cast::<PreUpdateHookCallback>() };
+    let armed = Rc::new(Cell::new(true));
+    let event = PreUpdateEvent {
+        db,
+        op: PreUpdateOp::from_raw(op),
+        database: c_str_to_string(z_db).unwrap_or_default(),
+        table: c_str_to_string(z_table).unwrap_or_default(),
+        old_rowid,
+        new_rowid,
+        armed: armed.clone(),
+    };
+    hook(event);
+    armed.set(false);
+}
+
+/// The concrete boxed closure type stashed behind the `sqlite3_busy_handler`
+/// context pointer, mirroring [`WalHookCallback`].
+type BusyHandlerCallback = Box<dyn FnMut(i32) -> bool>;
+
+unsafe extern "C" fn busy_handler_trampoline(ctx: *mut std::ffi::c_void, count: c_int) -> c_int {
+    let handler = unsafe { &mut *ctx.cast::<BusyHandlerCallback>() };
+    c_int::from(handler(count))
+}
+
+/// The threshold/callback pair behind [`Connection::set_slow_query_threshold`].
+/// Unlike the other hooks in this file, never handed to SQLite itself as an
+/// opaque context pointer -- just plain Rust state this crate consults on
+/// its own.
+struct SlowQueryHook {
+    threshold: std::time::Duration,
+    callback: SlowQueryCallback,
+}
+
+/// The concrete boxed closure type behind [`SlowQueryHook::callback`].
+type SlowQueryCallback = Box<dyn Fn(&str, std::time::Duration)>;
+
+/// The concrete boxed closure type stashed behind the `sqlite3_wal_hook`
+/// context pointer, mirroring [`TraceCallback`].
+type WalHookCallback = Box<dyn FnMut(&str, i32) -> Result<()>>;
+
+unsafe extern "C" fn wal_hook_trampoline(
+    ctx: *mut std::ffi::c_void,
+    _db: *mut ffi::sqlite3,
+    name: *const std::os::raw::c_char,
+    frames: c_int,
+) -> c_int {
+    let hook = unsafe { &mut *ctx.cast::<WalHookCallback>() };
+    let name = c_str_to_string(name).unwrap_or_default();
+    match hook(&name, frames) {
+        Ok(()) => ffi::SQLITE_OK,
+        Err(Error::Sqlite { code, .. }) => code,
+        Err(_) => ffi::SQLITE_ERROR,
+    }
+}
+
+/// How often (in internal SQLite VM instructions) `sqlite3_progress_handler`
+/// polls when only [`Connection::set_query_timeout`] is active -- no user
+/// [`Connection::set_progress_handler`] callback to take a preference from.
+const DEFAULT_TIMEOUT_POLL_OPS: c_int = 1000;
+
+/// The state stashed behind the `sqlite3_progress_handler` context pointer,
+/// shared by [`Connection::set_progress_handler`] and
+/// [`Connection::set_query_timeout`] so the two coexist through one C-level
+/// registration.
+struct ProgressState {
+    /// The deadline armed by [`Connection::arm_query_timeout`], if a query
+    /// timeout is configured and a statement execution is in progress.
+    deadline: Cell<Option<std::time::Instant>>,
+    /// The closure passed to [`Connection::set_progress_handler`], if any.
+    user: Option<Box<dyn FnMut() -> bool>>,
+    /// The polling interval [`Connection::set_progress_handler`] requested,
+    /// or [`DEFAULT_TIMEOUT_POLL_OPS`] if it's never been called.
+    n_ops: c_int,
+}
+
+/// Checks `ctx`'s deadline first (interrupting if it's passed), then falls
+/// through to its user handler if one is set and the deadline hasn't fired.
+unsafe extern "C" fn progress_trampoline(ctx: *mut std::ffi::c_void) -> c_int {
+    let state = unsafe { &mut *ctx.cast::<ProgressState>() };
+    if let Some(deadline) = state.deadline.get() {
+        if std::time::Instant::now() >= deadline {
+            return 1;
+        }
+    }
+    match state.user.as_mut() {
+        Some(handler) => c_int::from(handler()),
+        None => 0,
+    }
+}
+
+fn connection_error(db: *mut ffi::sqlite3, rc: c_int, context: &str) -> Error {
+    connection_error_with_statement_context(db, rc, context, None, None)
+}
+
+fn connection_error_with_statement_context(
+    db: *mut ffi::sqlite3,
+    rc: c_int,
+    context: &str,
+    sql: Option<String>,
+    param_summary: Option<String>,
+) -> Error {
+    let message = unsafe {
+        let ptr = ffi::sqlite3_errmsg(db);
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        }
+    };
+    Error::sqlite_with_statement_context(rc, format!("{context}: {message}"), sql, param_summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::value::Value;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// A path in the system temp dir that's unique to this test run.
+    fn temp_db_path(name: &str) -> std::path::PathBuf {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        std::env::temp_dir().join(format!("arsw_test_{name}_{}_{nanos}.db", std::process::id()))
+    }
+
+    #[test]
+    fn vfs_name_reports_the_vfs_backing_the_main_database() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("vfs_name");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        let name = conn.vfs_name("main").unwrap();
+        assert!(!name.is_empty());
+        assert!(crate::vfs::vfs_names().contains(&name));
+    }
+
+    #[test]
+    fn db_filename_reports_the_absolute_path_of_a_file_database() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("db_filename");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        let filename = conn.db_filename("main").unwrap().unwrap();
+        assert_eq!(filename, path.to_str().unwrap());
+    }
+
+    #[test]
+    fn db_filename_is_none_for_an_in_memory_database() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        assert_eq!(conn.db_filename("main").unwrap(), None);
+    }
+
+    #[test]
+    fn db_names_lists_main_and_temp_on_a_fresh_connection() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        assert_eq!(conn.db_names(), vec!["main".to_string(), "temp".to_string()]);
+    }
+
+    #[test]
+    fn db_names_appends_attached_databases_in_attach_order_and_drops_them_on_detach() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("ATTACH ':memory:' AS a").unwrap().step().unwrap();
+        conn.execute("ATTACH ':memory:' AS b").unwrap().step().unwrap();
+        assert_eq!(
+            conn.db_names(),
+            vec!["main".to_string(), "temp".to_string(), "a".to_string(), "b".to_string()]
+        );
+
+        conn.execute("DETACH a").unwrap().step().unwrap();
+        assert_eq!(conn.db_names(), vec!["main".to_string(), "temp".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn is_readonly_flips_between_writable_and_readonly_opens() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("is_readonly");
+        let writable = Connection::open(path.to_str().unwrap()).unwrap();
+        writable.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        assert!(!writable.is_readonly("main").unwrap());
+
+        let readonly = Connection::open_readonly(path.to_str().unwrap()).unwrap();
+        assert!(readonly.is_readonly("main").unwrap());
+    }
+
+    #[test]
+    fn is_readonly_rejects_an_unknown_schema() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        assert!(conn.is_readonly("nope").is_err());
+    }
+
+    #[test]
+    fn serialize_deserialize_round_trips() {
+        let _guard = lock_global_sqlite_state();
+        let src = Connection::open(":memory:").unwrap();
+        src.execute("CREATE TABLE t(a, b)").unwrap().step().unwrap();
+        src.execute("INSERT INTO t VALUES (1, 'one'), (2, 'two')")
+            .unwrap()
+            .step()
+            .unwrap();
+        let bytes = src.serialize(None).unwrap();
+        assert!(!bytes.is_empty());
+
+        let dst = Connection::open(":memory:").unwrap();
+        dst.deserialize(None, &bytes).unwrap();
+
+        let mut stmt = dst.execute("SELECT a, b FROM t ORDER BY a").unwrap();
+        let mut rows = Vec::new();
+        while stmt.step().unwrap() {
+            rows.push(stmt.row().unwrap());
+        }
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Integer(1), Value::Text("one".into())],
+                vec![Value::Integer(2), Value::Text("two".into())],
+            ]
+        );
+    }
+
+    #[test]
+    fn serialize_fresh_database_is_a_valid_sqlite_header() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let bytes = conn.serialize(None).unwrap();
+        assert_eq!(&bytes[..16], b"SQLite format 3\0");
+    }
+
+    #[test]
+    fn open_from_memory_loads_a_serialized_database() {
+        let _guard = lock_global_sqlite_state();
+        let src = Connection::open(":memory:").unwrap();
+        src.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        src.execute("INSERT INTO t VALUES (1), (2)").unwrap().step().unwrap();
+        let bytes = src.serialize(None).unwrap();
+
+        let dst = Connection::open_from_memory(bytes, true).unwrap();
+        let mut stmt = dst.execute("SELECT a FROM t ORDER BY a").unwrap();
+        let mut rows = Vec::new();
+        while stmt.step().unwrap() {
+            rows.push(stmt.row().unwrap());
+        }
+        assert_eq!(rows, vec![vec![Value::Integer(1)], vec![Value::Integer(2)]]);
+    }
+
+    #[test]
+    fn open_from_memory_writable_round_trips_back_out_via_serialize() {
+        let _guard = lock_global_sqlite_state();
+        let src = Connection::open(":memory:").unwrap();
+        src.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        let bytes = src.serialize(None).unwrap();
+
+        let dst = Connection::open_from_memory(bytes, true).unwrap();
+        dst.execute("INSERT INTO t VALUES (42)").unwrap().step().unwrap();
+        let round_tripped = dst.serialize(None).unwrap();
+
+        let check = Connection::open_from_memory(round_tripped, false).unwrap();
+        let mut stmt = check.execute("SELECT a FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(42));
+    }
+
+    #[test]
+    fn open_from_memory_readonly_rejects_writes() {
+        let _guard = lock_global_sqlite_state();
+        let src = Connection::open(":memory:").unwrap();
+        src.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        let bytes = src.serialize(None).unwrap();
+
+        let dst = Connection::open_from_memory(bytes, false).unwrap();
+        let err = match dst.execute("INSERT INTO t VALUES (1)").unwrap().step() {
+            Err(err) => err,
+            Ok(_) => panic!("expected SQLITE_READONLY"),
+        };
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_READONLY));
+    }
+
+    #[test]
+    fn open_temp_behaves_like_a_private_database() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open_temp().unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+        let mut stmt = conn.execute("SELECT a FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(1));
+    }
+
+    #[test]
+    fn deserialize_invalid_bytes_errors() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let garbage = vec![0xffu8; 64];
+        conn.deserialize(None, &garbage).unwrap();
+        // The bogus header isn't rejected by deserialize() itself but by the
+        // first real read against it.
+        let err = match conn.execute("SELECT * FROM sqlite_master") {
+            Err(err) => err,
+            Ok(_) => panic!("expected a NOTADB error"),
+        };
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_NOTADB));
+    }
+
+    #[test]
+    fn trace_stmt_captures_expanded_sql_for_a_bound_statement() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = events.clone();
+        conn.trace(TraceMask::STMT, move |event| recorded.borrow_mut().push(event))
+            .unwrap();
+
+        conn.execute("SELECT 42").unwrap().step().unwrap();
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            TraceEvent::Stmt { sql, unexpanded } => {
+                assert_eq!(sql, "SELECT 42");
+                assert_eq!(unexpanded, "SELECT 42");
+            }
+            other => panic!("expected Stmt, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn trace_profile_reports_a_positive_duration() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = events.clone();
+        conn.trace(TraceMask::PROFILE, move |event| recorded.borrow_mut().push(event))
+            .unwrap();
+
+        // A trivial `SELECT 1` can complete inside a single clock tick on a
+        // fast machine, making a positive-duration assertion flaky; give the
+        // timer something to actually measure.
+        let heavy = "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 2000000) \
+                     SELECT count(*) FROM c";
+        conn.execute(heavy).unwrap().step().unwrap();
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            TraceEvent::Profile { nanos, .. } => assert!(*nanos > 0),
+            other => panic!("expected Profile, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn trace_none_clears_a_previously_installed_callback() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let count = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counted = count.clone();
+        conn.trace(TraceMask::STMT, move |_event| counted.set(counted.get() + 1))
+            .unwrap();
+        conn.execute("SELECT 1").unwrap().step().unwrap();
+        assert_eq!(count.get(), 1);
+
+        conn.trace(TraceMask::NONE, |_event| {}).unwrap();
+        conn.execute("SELECT 2").unwrap().step().unwrap();
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn wal_hook_fires_with_a_growing_frame_count_on_commits() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("wal_hook");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        conn.execute("PRAGMA journal_mode=WAL").unwrap().step().unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+
+        let frame_counts = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = frame_counts.clone();
+        conn.set_wal_hook(move |_schema, frames| {
+            recorded.borrow_mut().push(frames);
+            Ok(())
+        })
+        .unwrap();
+
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (2), (3)").unwrap().step().unwrap();
+
+        let frame_counts = frame_counts.borrow();
+        assert_eq!(frame_counts.len(), 2);
+        assert!(frame_counts[1] > frame_counts[0]);
+
+        conn.close().unwrap();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db-wal"));
+        let _ = std::fs::remove_file(path.with_extension("db-shm"));
+    }
+
+    #[test]
+    fn wal_checkpoint_passive_reports_frame_counts() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("wal_checkpoint_passive");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        conn.execute("PRAGMA journal_mode=WAL").unwrap().step().unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        let (log_frames, checkpointed_frames) = conn.wal_checkpoint(None, CheckpointMode::Passive).unwrap();
+        assert!(log_frames > 0);
+        assert_eq!(checkpointed_frames, log_frames);
+
+        conn.close().unwrap();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db-wal"));
+        let _ = std::fs::remove_file(path.with_extension("db-shm"));
+    }
+
+    #[test]
+    fn wal_checkpoint_truncate_empties_the_wal_file() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("wal_checkpoint_truncate");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        conn.execute("PRAGMA journal_mode=WAL").unwrap().step().unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        let wal_path = path.with_extension("db-wal");
+        assert!(std::fs::metadata(&wal_path).unwrap().len() > 0);
+
+        conn.wal_checkpoint(None, CheckpointMode::Truncate).unwrap();
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+
+        conn.close().unwrap();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let _ = std::fs::remove_file(path.with_extension("db-shm"));
+    }
+
+    #[test]
+    fn wal_autocheckpoint_accepts_a_frame_threshold() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.wal_autocheckpoint(1000).unwrap();
+        conn.wal_autocheckpoint(0).unwrap();
+    }
+
+    #[test]
+    fn db_status_cache_used_grows_with_data() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let (before, _) = conn.db_status(DbStatusOp::CacheUsed, false).unwrap();
+
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        for i in 0..500 {
+            conn.execute(&format!("INSERT INTO t VALUES ({i})")).unwrap().step().unwrap();
+        }
+
+        let (after, _) = conn.db_status(DbStatusOp::CacheUsed, false).unwrap();
+        assert!(after > before);
+    }
+
+    #[test]
+    fn cache_flush_inside_a_large_write_transaction_succeeds() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("BEGIN").unwrap().step().unwrap();
+        for i in 0..500 {
+            conn.execute(&format!("INSERT INTO t VALUES ({i})")).unwrap().step().unwrap();
+        }
+        conn.cache_flush().unwrap();
+        conn.execute("COMMIT").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("SELECT count(*) FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.row().unwrap(), vec![Value::Integer(500)]);
+    }
+
+    #[test]
+    fn release_memory_on_an_empty_connection_succeeds() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.release_memory().unwrap();
+    }
+
+    #[test]
+    fn read_main_file_sees_the_sqlite_header() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("read_main");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+
+        let (complete, bytes) = conn.read("main", 0, 0, 16).unwrap();
+        assert!(complete);
+        assert_eq!(&bytes, b"SQLite format 3\0");
+
+        conn.close().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn read_past_eof_is_incomplete_and_zero_padded() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("read_eof");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        let file_len = std::fs::metadata(&path).unwrap().len();
+
+        let (complete, bytes) = conn.read("main", 0, file_len as i64, 32).unwrap();
+        assert!(!complete);
+        assert_eq!(bytes, vec![0u8; 32]);
+
+        conn.close().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn read_journal_while_transaction_is_open() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("read_journal");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("BEGIN").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        // A rollback journal exists for the duration of this write
+        // transaction (SQLite writes its header's magic number lazily, at
+        // sync time, so its content here isn't asserted -- just that the
+        // VFS file is reachable and the read completes).
+        let (complete, bytes) = conn.read("main", 1, 0, 8).unwrap();
+        assert!(complete);
+        assert_eq!(bytes.len(), 8);
+
+        conn.execute("COMMIT").unwrap().step().unwrap();
+        conn.close().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn read_from_attached_schema() {
+        let _guard = lock_global_sqlite_state();
+        let main_path = temp_db_path("read_attach_main");
+        let aux_path = temp_db_path("read_attach_aux");
+        let conn = Connection::open(main_path.to_str().unwrap()).unwrap();
+        conn.execute(&format!("ATTACH DATABASE '{}' AS aux", aux_path.display()))
+            .unwrap()
+            .step()
+            .unwrap();
+        conn.execute("CREATE TABLE aux.t(a)").unwrap().step().unwrap();
+
+        let (complete, bytes) = conn.read("aux", 0, 0, 16).unwrap();
+        assert!(complete);
+        assert_eq!(&bytes, b"SQLite format 3\0");
+
+        conn.close().unwrap();
+        let _ = std::fs::remove_file(&main_path);
+        let _ = std::fs::remove_file(&aux_path);
+    }
+
+    #[test]
+    fn last_insert_rowid_reflects_recent_insert() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES ('x')").unwrap().step().unwrap();
+        assert_eq!(conn.last_insert_rowid(), 1);
+        conn.execute("INSERT INTO t VALUES ('y')").unwrap().step().unwrap();
+        assert_eq!(conn.last_insert_rowid(), 2);
+    }
+
+    #[test]
+    fn set_last_insert_rowid_overrides_value() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.set_last_insert_rowid(42);
+        assert_eq!(conn.last_insert_rowid(), 42);
+    }
+
+    #[test]
+    fn changes_reflects_multi_row_update() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1), (2), (3)")
+            .unwrap()
+            .step()
+            .unwrap();
+        assert_eq!(conn.total_changes(), 3);
+
+        conn.execute("UPDATE t SET a = a + 1").unwrap().step().unwrap();
+        assert_eq!(conn.changes(), 3);
+        assert_eq!(conn.total_changes(), 6);
+    }
+
+    #[test]
+    fn prepare_all_iterates_every_statement_in_a_script() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let mut count = 0;
+        for stmt in conn
+            .prepare_all("CREATE TABLE t(a); INSERT INTO t VALUES (1); SELECT a FROM t")
+            .unwrap()
+        {
+            let mut stmt = stmt.unwrap();
+            stmt.step().unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn prepare_all_ignores_trailing_whitespace_and_comments() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let statements: Vec<_> = conn
+            .prepare_all("SELECT 1; -- trailing comment\n  ")
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(statements.len(), 1);
+    }
+
+    #[test]
+    fn execute_script_runs_every_statement_and_ignores_result_rows() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute_script(
+            "CREATE TABLE t(a); \
+             INSERT INTO t VALUES (1), (2), (3); \
+             SELECT a FROM t; \
+             DELETE FROM t WHERE a = 2",
+        )
+        .unwrap();
+        assert_eq!(conn.total_changes(), 4);
+        let mut stmt = conn.execute("SELECT count(*) FROM t").unwrap();
+        stmt.step().unwrap();
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn execute_script_reports_the_index_of_the_failing_statement() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let err = conn
+            .execute_script("CREATE TABLE t(a); INSERT INTO t VALUES (1); SELECT * FROM missing")
+            .unwrap_err();
+        match err {
+            Error::Script { statement_index, .. } => assert_eq!(statement_index, 2),
+            other => panic!("expected Error::Script, got {other:?}"),
+        }
+        // Statements before the failure still ran.
+        let mut stmt = conn.execute("SELECT count(*) FROM t").unwrap();
+        stmt.step().unwrap();
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(1));
+    }
+
+    #[test]
+    fn autocommit_reflects_whether_a_transaction_is_open() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        assert!(conn.autocommit());
+        conn.execute("BEGIN").unwrap().step().unwrap();
+        assert!(!conn.autocommit());
+        conn.execute("COMMIT").unwrap().step().unwrap();
+        assert!(conn.autocommit());
+    }
+
+    #[test]
+    fn db_config_toggles_foreign_key_enforcement() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute_script(
+            "CREATE TABLE parent(id INTEGER PRIMARY KEY); \
+             CREATE TABLE child(parent_id REFERENCES parent(id))",
+        )
+        .unwrap();
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_FKEY, 0).unwrap();
+        conn.execute("INSERT INTO child VALUES (99)").unwrap().step().unwrap();
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_FKEY, 1).unwrap();
+        let err = conn.execute("INSERT INTO child VALUES (100)").unwrap().step().unwrap_err();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_CONSTRAINT));
+    }
+
+    #[test]
+    fn db_config_with_negative_one_reads_back_the_current_value_unchanged() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_FKEY, 1).unwrap();
+        assert!(conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_FKEY, -1).unwrap());
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_FKEY, 0).unwrap();
+        assert!(!conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_FKEY, -1).unwrap());
+    }
+
+    #[test]
+    fn db_config_enable_trigger_gates_whether_triggers_actually_fire() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute_script(
+            "CREATE TABLE t(a); CREATE TABLE log(msg); \
+             CREATE TRIGGER trg AFTER INSERT ON t BEGIN INSERT INTO log VALUES ('fired'); END",
+        )
+        .unwrap();
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_TRIGGER, 0).unwrap();
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+        let mut stmt = conn.execute("SELECT COUNT(*) FROM log").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(0));
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_TRIGGER, 1).unwrap();
+        conn.execute("INSERT INTO t VALUES (2)").unwrap().step().unwrap();
+        let mut stmt = conn.execute("SELECT COUNT(*) FROM log").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(1));
+    }
+
+    #[test]
+    fn db_config_enable_view_gates_whether_views_are_readable() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute_script("CREATE TABLE t(a); INSERT INTO t VALUES (1); CREATE VIEW v AS SELECT * FROM t")
+            .unwrap();
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_VIEW, 0).unwrap();
+        let err = conn.execute("SELECT * FROM v").err().unwrap();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_ERROR));
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_ENABLE_VIEW, 1).unwrap();
+        let mut stmt = conn.execute("SELECT * FROM v").unwrap();
+        assert!(stmt.step().unwrap());
+    }
+
+    #[test]
+    fn db_config_writable_schema_gates_direct_edits_to_sqlite_schema() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+
+        let err = conn.execute("UPDATE sqlite_schema SET sql = sql WHERE name = 't'").err().unwrap();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_ERROR));
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_WRITABLE_SCHEMA, 1).unwrap();
+        let mut stmt = conn.execute("UPDATE sqlite_schema SET sql = sql WHERE name = 't'").unwrap();
+        assert!(!stmt.step().unwrap());
+    }
+
+    #[test]
+    fn db_config_defensive_blocks_writable_schema_edits_even_when_enabled() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.db_config(ffi::SQLITE_DBCONFIG_WRITABLE_SCHEMA, 1).unwrap();
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_DEFENSIVE, 1).unwrap();
+        let err = conn.execute("UPDATE sqlite_schema SET sql = sql WHERE name = 't'").err().unwrap();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_ERROR));
+
+        conn.db_config(ffi::SQLITE_DBCONFIG_DEFENSIVE, 0).unwrap();
+        let mut stmt = conn.execute("UPDATE sqlite_schema SET sql = sql WHERE name = 't'").unwrap();
+        assert!(!stmt.step().unwrap());
+    }
+
+    unsafe extern "C" fn constant_one(ctx: *mut ffi::sqlite3_context, _argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+        unsafe { ffi::sqlite3_result_int64(ctx, 1) };
+    }
+
+    #[test]
+    fn set_trusted_schema_gates_a_non_innocuous_function_called_from_a_view() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe {
+            crate::function::create_scalar_function(&conn, "plain_one", 0, 0, std::ptr::null_mut(), constant_one, None)
+                .unwrap();
+        }
+        conn.execute("CREATE VIEW v AS SELECT plain_one()").unwrap().step().unwrap();
+
+        conn.set_trusted_schema(false).unwrap();
+        let err = conn.execute("SELECT * FROM v").err().unwrap();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_ERROR));
+
+        conn.set_trusted_schema(true).unwrap();
+        conn.execute("SELECT * FROM v").unwrap().step().unwrap();
+    }
+
+    #[test]
+    fn set_main_db_name_gives_main_an_additional_name_without_losing_the_original() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.set_main_db_name("renamed").unwrap();
+        conn.execute("CREATE TABLE renamed.t(a)").unwrap().step().unwrap();
+
+        // Still reachable under the original name too.
+        conn.execute("INSERT INTO main.t VALUES (1)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("SELECT name FROM pragma_database_list WHERE seq = 0").unwrap();
+        stmt.step().unwrap();
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Text("renamed".to_string()));
+    }
+
+    #[test]
+    fn overload_function_turns_no_such_function_into_unable_to_use() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        let err = match conn.execute("SELECT myfunc(1, 2)") {
+            Err(err) => err,
+            Ok(_) => panic!("expected \"no such function\" before overload_function"),
+        };
+        assert!(err.to_string().contains("no such function: myfunc"));
+
+        conn.overload_function("myfunc", 2).unwrap();
+
+        // Still fails: no virtual table's `xFindFunction` exists yet to
+        // claim the call, but SQLite now recognizes the name, so the error
+        // changes from "no such function" to "unable to use function".
+        let mut stmt = conn.execute("SELECT myfunc(1, 2)").unwrap();
+        let err = match stmt.step() {
+            Err(err) => err,
+            Ok(_) => panic!("expected \"unable to use function\" after overload_function"),
+        };
+        assert!(err.to_string().contains("unable to use function myfunc"));
+    }
+
+    #[test]
+    fn vtab_config_rejects_calls_outside_construction() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let result = conn.vtab_config(VtabConfigOption::Innocuous);
+        assert!(matches!(result, Err(Error::Misuse(_))));
+    }
+
+    /// A minimal real virtual table, registered through the actual
+    /// `sqlite3_create_module_v2`: just enough `sqlite3_module` callbacks
+    /// for `CREATE VIRTUAL TABLE`/`INSERT` to reach `xCreate`/`xUpdate` for
+    /// real, so [`Connection::vtab_config`] and
+    /// [`Connection::vtab_on_conflict`] can be exercised from inside
+    /// SQLite's own call into them rather than a simulated window. This
+    /// crate has no general virtual table module trait yet (registering
+    /// one for real SQL use is future work); this is test-only scaffolding
+    /// for that one purpose.
+    mod toy_vtab {
+        use super::*;
+        use std::ffi::c_void;
+        use std::os::raw::c_char;
+
+        #[repr(C)]
+        struct ToyVtab {
+            base: ffi::sqlite3_vtab,
+            conn: *const Connection,
+        }
+
+        #[repr(C)]
+        struct ToyCursor {
+            base: ffi::sqlite3_vtab_cursor,
+            eof: bool,
+        }
+
+        unsafe extern "C" fn create(
+            db: *mut ffi::sqlite3,
+            p_aux: *mut c_void,
+            _argc: c_int,
+            _argv: *const *const c_char,
+            pp_vtab: *mut *mut ffi::sqlite3_vtab,
+            _pz_err: *mut *mut c_char,
+        ) -> c_int {
+            let conn = p_aux.cast::<Connection>();
+            {
+                let _construction = unsafe { &*conn }.enter_vtab_construction();
+                if unsafe { &*conn }
+                    .vtab_config(VtabConfigOption::ConstraintSupport(true))
+                    .is_err()
+                {
+                    return ffi::SQLITE_ERROR;
+                }
+            }
+            let declare = CString::new("CREATE TABLE x(a)").unwrap();
+            if unsafe { ffi::sqlite3_declare_vtab(db, declare.as_ptr()) } != ffi::SQLITE_OK {
+                return ffi::SQLITE_ERROR;
+            }
+            let vtab = Box::new(ToyVtab { base: unsafe { std::mem::zeroed() }, conn });
+            unsafe { *pp_vtab = Box::into_raw(vtab).cast() };
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn disconnect(p_vtab: *mut ffi::sqlite3_vtab) -> c_int {
+            unsafe { drop(Box::from_raw(p_vtab.cast::<ToyVtab>())) };
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn best_index(_p_vtab: *mut ffi::sqlite3_vtab, info: *mut ffi::sqlite3_index_info) -> c_int {
+            unsafe { (*info).estimatedCost = 1.0 };
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn open(p_vtab: *mut ffi::sqlite3_vtab, pp_cursor: *mut *mut ffi::sqlite3_vtab_cursor) -> c_int {
+            let cursor = Box::new(ToyCursor { base: ffi::sqlite3_vtab_cursor { pVtab: p_vtab }, eof: true });
+            unsafe { *pp_cursor = Box::into_raw(cursor).cast() };
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn close(cursor: *mut ffi::sqlite3_vtab_cursor) -> c_int {
+            unsafe { drop(Box::from_raw(cursor.cast::<ToyCursor>())) };
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn filter(
+            cursor: *mut ffi::sqlite3_vtab_cursor,
+            _idx_num: c_int,
+            _idx_str: *const c_char,
+            _argc: c_int,
+            _argv: *mut *mut ffi::sqlite3_value,
+        ) -> c_int {
+            unsafe { (*cursor.cast::<ToyCursor>()).eof = true };
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn next(_cursor: *mut ffi::sqlite3_vtab_cursor) -> c_int {
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn eof(cursor: *mut ffi::sqlite3_vtab_cursor) -> c_int {
+            unsafe { (*cursor.cast::<ToyCursor>()).eof as c_int }
+        }
+
+        unsafe extern "C" fn column(_cursor: *mut ffi::sqlite3_vtab_cursor, ctx: *mut ffi::sqlite3_context, _i: c_int) -> c_int {
+            unsafe { ffi::sqlite3_result_null(ctx) };
+            ffi::SQLITE_OK
+        }
+
+        unsafe extern "C" fn rowid(_cursor: *mut ffi::sqlite3_vtab_cursor, p_rowid: *mut ffi::sqlite3_int64) -> c_int {
+            unsafe { *p_rowid = 0 };
+            ffi::SQLITE_OK
+        }
+
+        /// Rejects every write unless it's running under `OR REPLACE`,
+        /// purely to make `vtab_on_conflict()` observable from SQL: plain
+        /// `INSERT` fails, `INSERT OR REPLACE` succeeds.
+        unsafe extern "C" fn update(
+            p_vtab: *mut ffi::sqlite3_vtab,
+            _argc: c_int,
+            _argv: *mut *mut ffi::sqlite3_value,
+            _p_rowid: *mut ffi::sqlite3_int64,
+        ) -> c_int {
+            let vtab = unsafe { &*p_vtab.cast::<ToyVtab>() };
+            let conn = unsafe { &*vtab.conn };
+            match conn.vtab_on_conflict() {
+                ConflictResolution::Replace => ffi::SQLITE_OK,
+                _ => ffi::SQLITE_CONSTRAINT,
+            }
+        }
+
+        pub(super) static MODULE: ffi::sqlite3_module = ffi::sqlite3_module {
+            iVersion: 0,
+            xCreate: Some(create),
+            xConnect: Some(create),
+            xBestIndex: Some(best_index),
+            xDisconnect: Some(disconnect),
+            xDestroy: Some(disconnect),
+            xOpen: Some(open),
+            xClose: Some(close),
+            xFilter: Some(filter),
+            xNext: Some(next),
+            xEof: Some(eof),
+            xColumn: Some(column),
+            xRowid: Some(rowid),
+            xUpdate: Some(update),
+            xBegin: None,
+            xSync: None,
+            xCommit: None,
+            xRollback: None,
+            xFindFunction: None,
+            xRename: None,
+            xSavepoint: None,
+            xRelease: None,
+            xRollbackTo: None,
+            xShadowName: None,
+            xIntegrity: None,
+        };
+
+        pub(super) fn register(conn: &Connection) {
+            register_named(conn, "toy_vtab");
+        }
+
+        pub(super) fn register_named(conn: &Connection, name: &str) {
+            let cname = CString::new(name).unwrap();
+            let rc = unsafe {
+                ffi::sqlite3_create_module_v2(
+                    conn.as_ptr(),
+                    cname.as_ptr(),
+                    &MODULE,
+                    (conn as *const Connection).cast_mut().cast(),
+                    None,
+                )
+            };
+            assert_eq!(rc, ffi::SQLITE_OK);
+        }
+    }
+
+    #[test]
+    fn vtab_config_succeeds_from_a_real_xcreate_callback() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        toy_vtab::register(&conn);
+
+        // Reaching xCreate at all proves vtab_config ran inside the real
+        // "currently constructing a virtual table" window, not a simulated
+        // one -- xCreate returned SQLITE_ERROR (failing this statement) if
+        // vtab_config had been rejected.
+        conn.execute("CREATE VIRTUAL TABLE t USING toy_vtab()")
+            .unwrap()
+            .step()
+            .unwrap();
+    }
+
+    #[test]
+    fn vtab_on_conflict_distinguishes_plain_insert_from_or_replace() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        toy_vtab::register(&conn);
+        conn.execute("CREATE VIRTUAL TABLE t USING toy_vtab()")
+            .unwrap()
+            .step()
+            .unwrap();
+
+        let mut stmt = conn.execute("INSERT INTO t VALUES (1)").unwrap();
+        let err = match stmt.step() {
+            Err(err) => err,
+            Ok(_) => panic!("expected a plain INSERT to be rejected"),
+        };
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_CONSTRAINT));
+
+        conn.execute("INSERT OR REPLACE INTO t VALUES (1)")
+            .unwrap()
+            .step()
+            .unwrap();
+    }
+
+    #[test]
+    fn vtab_config_rejects_calls_outside_a_real_xcreate_callback() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        // Not inside xCreate/xConnect (toy_vtab::register just calls
+        // sqlite3_create_module_v2, which doesn't invoke the module yet).
+        toy_vtab::register(&conn);
+        let result = conn.vtab_config(VtabConfigOption::Innocuous);
+        assert!(matches!(result, Err(Error::Misuse(_))));
+    }
+
+    #[test]
+    fn vtab_on_conflict_reports_a_known_resolution_outside_xupdate() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        // Not inside xUpdate, so the result is whatever SQLite's
+        // documented-as-unspecified default is outside that context; just
+        // check it decodes to a known enum value rather than panicking on
+        // an unrecognized code.
+        let resolution = conn.vtab_on_conflict();
+        assert!(matches!(
+            resolution,
+            ConflictResolution::Rollback
+                | ConflictResolution::Ignore
+                | ConflictResolution::Fail
+                | ConflictResolution::Abort
+                | ConflictResolution::Replace
+        ));
+    }
+
+    #[test]
+    fn drop_modules_unregisters_every_module_except_those_kept() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        toy_vtab::register_named(&conn, "keepme");
+        toy_vtab::register_named(&conn, "dropme");
+
+        conn.drop_modules(&["keepme"]).unwrap();
+
+        conn.execute("CREATE VIRTUAL TABLE t USING keepme()").unwrap().step().unwrap();
+
+        let err = conn.execute("CREATE VIRTUAL TABLE u USING dropme()").unwrap().step().unwrap_err();
+        assert!(err.to_string().contains("no such module"));
+    }
+
+    #[test]
+    fn query_timeout_interrupts_a_long_running_statement() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.set_query_timeout(Some(std::time::Duration::from_millis(50)));
+
+        let mut stmt = conn
+            .execute("SELECT count(*) FROM (SELECT 1 FROM (WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x+1 FROM c LIMIT 3000000) SELECT x FROM c) a, (WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x+1 FROM c LIMIT 3000000) SELECT x FROM c) b)")
+            .unwrap();
+        let err = match stmt.step() {
+            Err(err) => err,
+            Ok(_) => panic!("expected the cross join to time out"),
+        };
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_INTERRUPT));
+    }
+
+    #[test]
+    fn query_timeout_does_not_fire_for_a_fast_query() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.set_query_timeout(Some(std::time::Duration::from_secs(30)));
+
+        let mut stmt = conn.execute("SELECT 1").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.row().unwrap(), vec![Value::Integer(1)]);
+    }
+
+    #[test]
+    fn query_timeout_still_runs_a_coexisting_user_progress_handler() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.set_query_timeout(Some(std::time::Duration::from_secs(30)));
+
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let calls_handle = calls.clone();
+        conn.set_progress_handler(1, move || {
+            calls_handle.set(calls_handle.get() + 1);
+            false
+        });
+
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        for i in 0..2000 {
+            conn.execute(&format!("INSERT INTO t VALUES ({i})")).unwrap().step().unwrap();
+        }
+        let mut stmt = conn.execute("SELECT count(*) FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+
+        assert!(calls.get() > 0);
+    }
+
+    #[test]
+    fn slow_query_threshold_fires_for_a_deliberately_slow_query_but_not_a_fast_one() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let reports_handle = reports.clone();
+        conn.set_slow_query_threshold(Some(std::time::Duration::from_millis(20)), move |sql, elapsed| {
+            reports_handle.borrow_mut().push((sql.to_string(), elapsed));
+        });
+
+        let mut stmt = conn
+            .execute("SELECT count(*) FROM (WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x+1 FROM c LIMIT 400000) SELECT x FROM c)")
+            .unwrap();
+        assert!(stmt.step().unwrap());
+        drop(stmt);
+
+        {
+            let recorded = reports.borrow();
+            assert_eq!(recorded.len(), 1);
+            assert!(recorded[0].0.contains("RECURSIVE"));
+            assert!(recorded[0].1 >= std::time::Duration::from_millis(20));
+        }
+
+        reports.borrow_mut().clear();
+        let mut stmt = conn.execute("SELECT 1").unwrap();
+        assert!(stmt.step().unwrap());
+        drop(stmt);
+        assert!(reports.borrow().is_empty());
+    }
+
+    #[test]
+    fn slow_query_threshold_of_none_disables_reporting() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let calls_handle = calls.clone();
+        conn.set_slow_query_threshold(Some(std::time::Duration::from_nanos(1)), move |_, _| {
+            calls_handle.set(calls_handle.get() + 1);
+        });
+        conn.set_slow_query_threshold(None, |_: &str, _: std::time::Duration| {});
+
+        let mut stmt = conn.execute("SELECT 1").unwrap();
+        assert!(stmt.step().unwrap());
+        drop(stmt);
+
+        assert_eq!(calls.get(), 0);
+    }
+
+    #[test]
+    fn a_panicking_slow_query_callback_does_not_poison_the_connection() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.set_slow_query_threshold(Some(std::time::Duration::from_nanos(1)), |_, _| {
+            panic!("deliberate panic from a slow-query callback");
+        });
+
+        let mut stmt = conn.execute("SELECT 1").unwrap();
+        assert!(stmt.step().unwrap());
+        drop(stmt);
+
+        // The connection is still usable after the callback panicked.
+        let mut stmt = conn.execute("SELECT 2").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.row().unwrap(), vec![Value::Integer(2)]);
+    }
+
+    #[test]
+    fn step_timeout_overrides_the_connection_timeout_for_one_call() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        let mut stmt = conn
+            .execute("SELECT count(*) FROM (WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x+1 FROM c LIMIT 3000000) SELECT x FROM c)")
+            .unwrap();
+        let err = match stmt.step_timeout(std::time::Duration::from_millis(50)) {
+            Err(err) => err,
+            Ok(_) => panic!("expected step_timeout to time out"),
+        };
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_INTERRUPT));
+        // The one-off override shouldn't leak into the connection's own setting.
+        assert!(conn.query_timeout().is_none());
+    }
+
+    #[test]
+    fn preupdate_hook_reports_both_old_and_new_on_update() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a, b)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1, 'x')").unwrap().step().unwrap();
+
+        let old_values: Rc<RefCell<Vec<Value>>> = Rc::new(RefCell::new(Vec::new()));
+        let new_values: Rc<RefCell<Vec<Value>>> = Rc::new(RefCell::new(Vec::new()));
+        let (old_handle, new_handle) = (old_values.clone(), new_values.clone());
+        conn.set_preupdate_hook(move |event| {
+            assert_eq!(event.op(), PreUpdateOp::Update);
+            assert_eq!(event.table(), "t");
+            *old_handle.borrow_mut() = vec![event.old_value(0).unwrap(), event.old_value(1).unwrap()];
+            *new_handle.borrow_mut() = vec![event.new_value(0).unwrap(), event.new_value(1).unwrap()];
+        });
+
+        conn.execute("UPDATE t SET a = 2, b = 'y' WHERE a = 1").unwrap().step().unwrap();
+
+        assert_eq!(*old_values.borrow(), vec![Value::Integer(1), Value::Text("x".to_string())]);
+        assert_eq!(*new_values.borrow(), vec![Value::Integer(2), Value::Text("y".to_string())]);
+    }
+
+    #[test]
+    fn preupdate_hook_on_delete_reports_only_old_values() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (7)").unwrap().step().unwrap();
+
+        let seen = Rc::new(Cell::new(false));
+        let seen_handle = seen.clone();
+        conn.set_preupdate_hook(move |event| {
+            assert_eq!(event.op(), PreUpdateOp::Delete);
+            assert_eq!(event.old_value(0).unwrap(), Value::Integer(7));
+            assert!(event.new_value(0).is_err());
+            seen_handle.set(true);
+        });
+
+        conn.execute("DELETE FROM t WHERE a = 7").unwrap().step().unwrap();
+        assert!(seen.get());
+    }
+
+    #[test]
+    fn preupdate_hook_reports_nonzero_depth_inside_a_trigger() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("CREATE TABLE log(a)").unwrap().step().unwrap();
+        conn.execute("CREATE TRIGGER trg AFTER INSERT ON t BEGIN INSERT INTO log VALUES (NEW.a); END")
+            .unwrap()
+            .step()
+            .unwrap();
+
+        let depths: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(Vec::new()));
+        let depths_handle = depths.clone();
+        conn.set_preupdate_hook(move |event| {
+            depths_handle.borrow_mut().push(event.depth().unwrap());
+        });
+
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        assert_eq!(*depths.borrow(), vec![0, 1]);
+    }
+
+    #[test]
+    fn preupdate_event_access_after_the_callback_returns_is_rejected() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+
+        let stashed: Rc<RefCell<Option<PreUpdateEvent>>> = Rc::new(RefCell::new(None));
+        let stashed_handle = stashed.clone();
+        conn.set_preupdate_hook(move |event| {
+            *stashed_handle.borrow_mut() = Some(event);
+        });
+
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        let event = stashed.borrow_mut().take().unwrap();
+        assert!(matches!(event.old_value(0), Err(Error::Misuse(_))));
+        assert!(matches!(event.column_count(), Err(Error::Misuse(_))));
+    }
+
+    #[test]
+    fn busy_handler_is_invoked_with_increasing_counts_until_the_writer_commits() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("busy_handler");
+        {
+            let setup = Connection::open(path.to_str().unwrap()).unwrap();
+            setup.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        }
+
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (commit_tx, commit_rx) = std::sync::mpsc::channel();
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            let conn = Connection::open(writer_path.to_str().unwrap()).unwrap();
+            conn.execute("BEGIN EXCLUSIVE").unwrap().step().unwrap();
+            conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+            locked_tx.send(()).unwrap();
+            commit_rx.recv().unwrap();
+            conn.execute("COMMIT").unwrap().step().unwrap();
+        });
+        locked_rx.recv().unwrap();
+
+        let reader = Connection::open(path.to_str().unwrap()).unwrap();
+        let counts: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(Vec::new()));
+        let counts_handle = counts.clone();
+        let mut told_writer_to_commit = false;
+        reader.set_busy_handler(move |count| {
+            counts_handle.borrow_mut().push(count);
+            if !told_writer_to_commit {
+                let _ = commit_tx.send(());
+                told_writer_to_commit = true;
+            }
+            true
+        });
+
+        reader.execute("INSERT INTO t VALUES (2)").unwrap().step().unwrap();
+        writer.join().unwrap();
+
+        let counts = counts.borrow();
+        assert!(!counts.is_empty());
+        assert!(counts.windows(2).all(|w| w[1] > w[0]));
+        assert_eq!(counts[0], 0);
+    }
+
+    #[test]
+    fn setting_a_timeout_after_a_handler_replaces_it() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        struct DropFlag(Rc<Cell<bool>>);
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let dropped = Rc::new(Cell::new(false));
+        let flag = DropFlag(dropped.clone());
+        conn.set_busy_handler(move |_count| {
+            let _keep_alive = &flag;
+            true
+        });
+        assert!(!dropped.get());
+
+        conn.set_busy_timeout(50).unwrap();
+        assert!(dropped.get());
+    }
+
+    #[test]
+    fn table_column_metadata_reports_declared_type_and_constraints() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL COLLATE NOCASE)")
+            .unwrap()
+            .step()
+            .unwrap();
+
+        let id = conn.table_column_metadata(None, "t", "id").unwrap();
+        assert_eq!(id.data_type.as_deref(), Some("INTEGER"));
+        assert!(id.primary_key);
+        assert!(id.autoincrement);
+
+        let name = conn.table_column_metadata(None, "t", "name").unwrap();
+        assert_eq!(name.data_type.as_deref(), Some("TEXT"));
+        assert_eq!(name.collation.as_deref(), Some("NOCASE"));
+        assert!(name.not_null);
+        assert!(!name.primary_key);
+        assert!(!name.autoincrement);
+    }
+
+    #[test]
+    fn table_column_metadata_rejects_an_unknown_column() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER)").unwrap().step().unwrap();
+        assert!(conn.table_column_metadata(None, "t", "missing").is_err());
+    }
+
+    #[test]
+    fn table_column_metadata_honors_an_explicit_schema() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER)").unwrap().step().unwrap();
+        let meta = conn.table_column_metadata(Some("main"), "t", "id").unwrap();
+        assert_eq!(meta.data_type.as_deref(), Some("INTEGER"));
+    }
+
+    #[test]
+    fn vacuum_into_copies_the_database_to_a_fresh_file() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1), (2), (3)").unwrap().step().unwrap();
+
+        let dest = temp_db_path("vacuum_into_dest");
+        conn.vacuum_into(dest.to_str().unwrap()).unwrap();
+
+        let copy = Connection::open(dest.to_str().unwrap()).unwrap();
+        let mut stmt = copy.execute("SELECT COUNT(*) FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(3));
+        drop(stmt);
+        drop(copy);
+        let _ = std::fs::remove_file(&dest);
+    }
+
+    #[test]
+    fn vacuum_into_fails_if_the_target_already_exists() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let dest = temp_db_path("vacuum_into_conflict");
+        std::fs::write(&dest, b"not a database").unwrap();
+        assert!(conn.vacuum_into(dest.to_str().unwrap()).is_err());
+        let _ = std::fs::remove_file(&dest);
+    }
+
+    #[test]
+    fn integrity_check_and_quick_check_are_empty_for_a_healthy_database() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+        assert_eq!(conn.integrity_check(None, 100).unwrap(), Vec::<String>::new());
+        assert_eq!(conn.quick_check(None, 100).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn integrity_check_reports_problems_in_a_corrupted_file_without_crashing() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("integrity_check_corrupt");
+        let conn = Connection::open(path.to_str().unwrap()).unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute(
+            "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 2000) \
+             INSERT INTO t SELECT x FROM c",
+        )
+        .unwrap()
+        .step()
+        .unwrap();
+        conn.close().unwrap();
+
+        // Flip a few bytes partway into the table's b-tree pages -- found by
+        // trial and error to land inside a cell rather than unused space, so
+        // `integrity_check` has something concrete to report.
+        let mut bytes = std::fs::read(&path).unwrap();
+        for byte in bytes.iter_mut().skip(10_100).take(4) {
+            *byte ^= 0xFF;
+        }
+        std::fs::write(&path, &bytes).unwrap();
+
+        let reopened = Connection::open(path.to_str().unwrap()).unwrap();
+        let problems = reopened.integrity_check(None, 100).unwrap();
+        assert!(!problems.is_empty());
+        drop(reopened);
+        let _ = std::fs::remove_file(&path);
+    }
+}
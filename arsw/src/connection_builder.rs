@@ -0,0 +1,251 @@
+//! Fluent construction of a [`Connection`] with pragmas applied at open,
+//! so callers don't have to hand-write the same handful of `PRAGMA`
+//! statements after every `Connection::open`.
+
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use crate::value::Value;
+use std::os::raw::c_int;
+use std::time::Duration;
+
+/// What [`ConnectionBuilder::open`] opens: either a named path (as
+/// [`Connection::open`]/[`Connection::open_with_flags`] would take it) or an
+/// in-memory database.
+enum Target {
+    Path(String),
+    Memory,
+}
+
+/// A pragma queued by [`ConnectionBuilder::pragma`] (or one of its typed
+/// convenience methods), applied in order once [`ConnectionBuilder::open`]
+/// has a live connection.
+struct Pragma {
+    name: String,
+    value: String,
+}
+
+/// Builds a [`Connection`], applying a batch of pragmas right after opening
+/// it -- `journal_mode`, `synchronous`, `foreign_keys`, `busy_timeout`, and
+/// `cache_size` are common enough that most callers set the same five every
+/// time, which this collects into one place with one point of failure.
+///
+/// Pragmas are applied in the order they were added. `journal_mode` echoes
+/// back the mode SQLite actually applied, so [`Self::open`] checks it
+/// against what was requested and fails with [`Error::Pragma`] (whose source
+/// is [`Error::PragmaRejected`]) if SQLite silently kept something else -- except on an in-memory database,
+/// where WAL is never actually usable (`journal_mode` there settles on
+/// `memory` regardless of what's requested) but is still harmless to ask
+/// for, so that particular mismatch is allowed through rather than treated
+/// as a failure.
+pub struct ConnectionBuilder {
+    target: Target,
+    flags: c_int,
+    vfs: Option<String>,
+    pragmas: Vec<Pragma>,
+}
+
+/// A `journal_mode` value for [`ConnectionBuilder::journal_mode`], spelled
+/// out so callers don't have to remember `PRAGMA journal_mode`'s string
+/// vocabulary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Wal,
+    Off,
+}
+
+impl JournalMode {
+    fn as_pragma_value(self) -> &'static str {
+        match self {
+            JournalMode::Delete => "DELETE",
+            JournalMode::Truncate => "TRUNCATE",
+            JournalMode::Persist => "PERSIST",
+            JournalMode::Memory => "MEMORY",
+            JournalMode::Wal => "WAL",
+            JournalMode::Off => "OFF",
+        }
+    }
+}
+
+impl ConnectionBuilder {
+    /// Start building a connection to the on-disk (or special, e.g. `""`
+    /// for a private temp database) file at `path`.
+    pub fn path(path: impl Into<String>) -> Self {
+        ConnectionBuilder {
+            target: Target::Path(path.into()),
+            flags: ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+            vfs: None,
+            pragmas: Vec::new(),
+        }
+    }
+
+    /// Start building a connection to a private in-memory database,
+    /// equivalent to `ConnectionBuilder::path(":memory:")`.
+    pub fn memory() -> Self {
+        ConnectionBuilder {
+            target: Target::Memory,
+            flags: ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+            vfs: None,
+            pragmas: Vec::new(),
+        }
+    }
+
+    /// Override the `sqlite3_open_v2` flags used to open the connection
+    /// (default `SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE`, matching
+    /// [`Connection::open`]).
+    pub fn flags(mut self, flags: c_int) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Open through the named VFS instead of the default one.
+    pub fn vfs(mut self, name: impl Into<String>) -> Self {
+        self.vfs = Some(name.into());
+        self
+    }
+
+    /// Queue `PRAGMA {name} = {value}` to run once the connection is open.
+    /// `value` is spliced into the pragma statement as-is (pragmas don't
+    /// accept bound parameters), so quote it yourself if it needs quoting.
+    pub fn pragma(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.pragmas.push(Pragma {
+            name: name.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Queue `PRAGMA foreign_keys = ON`/`OFF`.
+    pub fn foreign_keys(self, on: bool) -> Self {
+        self.pragma("foreign_keys", if on { "ON" } else { "OFF" })
+    }
+
+    /// Queue `PRAGMA journal_mode = ...`, verified against what SQLite
+    /// actually applies (see [`Self`]'s docs for the in-memory exception).
+    pub fn journal_mode(self, mode: JournalMode) -> Self {
+        self.pragma("journal_mode", mode.as_pragma_value())
+    }
+
+    /// Queue `PRAGMA cache_size = ...`.
+    pub fn cache_size(self, pages: i64) -> Self {
+        self.pragma("cache_size", pages.to_string())
+    }
+
+    /// Set the busy timeout via [`Connection::set_busy_timeout`] once the
+    /// connection is open, rather than `PRAGMA busy_timeout` -- the two are
+    /// equivalent, but the dedicated method also tears down any
+    /// `set_busy_handler` callback the same way `sqlite3_busy_timeout`
+    /// itself would.
+    pub fn busy_timeout(mut self, timeout: Duration) -> Self {
+        self.pragmas.push(Pragma {
+            name: "busy_timeout".to_string(),
+            value: timeout.as_millis().to_string(),
+        });
+        self
+    }
+
+    /// Open the connection and apply every queued pragma in order. If a
+    /// pragma fails -- its `PRAGMA` statement errors, or (for `journal_mode`)
+    /// SQLite reports back a mode other than what was requested -- returns
+    /// [`Error::Pragma`] naming it, leaving every earlier pragma applied.
+    pub fn open(self) -> Result<Connection> {
+        let conn = match &self.target {
+            Target::Memory => Connection::open_with_flags_and_vfs(":memory:", self.flags, self.vfs.as_deref())?,
+            Target::Path(path) => Connection::open_with_flags_and_vfs(path, self.flags, self.vfs.as_deref())?,
+        };
+        let is_memory = match &self.target {
+            Target::Memory => true,
+            Target::Path(path) => path == ":memory:",
+        };
+
+        for pragma in &self.pragmas {
+            if pragma.name.eq_ignore_ascii_case("busy_timeout") {
+                let ms: i32 = pragma.value.parse().unwrap_or(i32::MAX);
+                conn.set_busy_timeout(ms)
+                    .map_err(|source| Error::Pragma { name: pragma.name.clone(), source: Box::new(source) })?;
+                continue;
+            }
+            Self::apply_pragma(&conn, pragma, is_memory)
+                .map_err(|source| Error::Pragma { name: pragma.name.clone(), source: Box::new(source) })?;
+        }
+
+        Ok(conn)
+    }
+
+    fn apply_pragma(conn: &Connection, pragma: &Pragma, is_memory: bool) -> Result<()> {
+        let sql = format!("PRAGMA {}={}", pragma.name, pragma.value);
+        let mut stmt = conn.execute(&sql)?;
+        if pragma.name.eq_ignore_ascii_case("journal_mode") && stmt.step()? {
+            let actual = match stmt.column_value(0)? {
+                Value::Text(text) => text,
+                other => format!("{other:?}"),
+            };
+            if !is_memory && !actual.eq_ignore_ascii_case(&pragma.value) {
+                return Err(Error::PragmaRejected { requested: pragma.value.clone(), actual });
+            }
+        } else {
+            while stmt.step()? {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+
+    #[test]
+    fn builder_applies_wal_and_foreign_keys_together() {
+        // In memory `journal_mode` settles on `memory`, so this checks that
+        // asking for WAL alongside `foreign_keys` opens cleanly and that
+        // `foreign_keys` took effect; WAL itself is not observable here.
+        let _guard = lock_global_sqlite_state();
+        let conn = ConnectionBuilder::memory()
+            .foreign_keys(true)
+            .journal_mode(JournalMode::Wal)
+            .open()
+            .unwrap();
+
+        let mut stmt = conn.execute("PRAGMA foreign_keys").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(1));
+    }
+
+    /// A path in the system temp dir that's unique to this test run.
+    fn temp_db_path(name: &str) -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        std::env::temp_dir().join(format!("arsw_test_{name}_{}_{nanos}.db", std::process::id()))
+    }
+
+    #[test]
+    fn builder_reports_the_offending_pragma_by_name() {
+        let _guard = lock_global_sqlite_state();
+        let path = temp_db_path("connection_builder_pragma_failure");
+        Connection::open(path.to_str().unwrap()).unwrap();
+
+        let err = ConnectionBuilder::path(path.to_str().unwrap())
+            .flags(ffi::SQLITE_OPEN_READONLY)
+            .journal_mode(JournalMode::Wal)
+            .open()
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::Pragma { ref name, .. } if name == "journal_mode"));
+        assert!(err.to_string().contains("journal_mode"));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn memory_shortcut_opens_a_usable_connection() {
+        let _guard = lock_global_sqlite_state();
+        let conn = ConnectionBuilder::memory().open().unwrap();
+        let mut stmt = conn.execute("SELECT 1").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(1));
+    }
+}
@@ -0,0 +1,154 @@
+//! The crate's error type.
+
+use std::ffi::NulError;
+
+/// Errors produced by `arsw`.
+///
+/// `Clone`/`PartialEq`/`Eq` compare every field, `sql`/`param_summary`
+/// included -- two [`Error::Sqlite`]s from the same failure but different
+/// statements (or different [`ErrorVerbosity`](crate::connection::ErrorVerbosity)
+/// settings) are unequal, matching how `Debug`/`Display` would show them as
+/// different messages.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    /// A SQLite API call returned a non-`SQLITE_OK` result code.
+    ///
+    /// `sql` and `param_summary` are set when this came from a prepare or
+    /// [`Statement::step`](crate::statement::Statement::step) failure --
+    /// `message` already has them folded into its text (see
+    /// [`Self::sqlite_with_statement_context`]), so the two fields
+    /// exist for callers who want the pieces separately rather than by
+    /// scraping `message`. `param_summary` describes each bound parameter's
+    /// type and length; it only includes the actual values once
+    /// [`Connection::set_error_verbosity`](crate::connection::Connection::set_error_verbosity)
+    /// has raised verbosity to
+    /// [`ErrorVerbosity::WithValues`](crate::connection::ErrorVerbosity::WithValues).
+    #[error("sqlite error {code}: {message}")]
+    Sqlite {
+        code: i32,
+        message: String,
+        sql: Option<String>,
+        param_summary: Option<String>,
+    },
+    /// A string argument that SQLite expects to be NUL-terminated contained
+    /// an embedded NUL byte partway through.
+    #[error("argument contains an embedded NUL byte at position {position}")]
+    EmbeddedNul { position: usize },
+    /// An operation was attempted in a state, or with an argument, that
+    /// SQLite or this crate doesn't allow, e.g.
+    /// `sqlite3_config(SQLITE_CONFIG_LOG, ...)` once a connection has already
+    /// been opened, or
+    /// [`Connection::vtab_config`](crate::connection::Connection::vtab_config)
+    /// outside `xCreate`/`xConnect`.
+    #[error("{0}")]
+    Misuse(&'static str),
+    /// A JSONB blob ([`crate::jsonb`]) failed to validate or decode;
+    /// `offset` is the byte position within the blob where the problem was
+    /// found.
+    #[error("invalid JSONB at byte {offset}: {message}")]
+    Jsonb { offset: usize, message: String },
+    /// A [`TypeHook`](crate::type_hooks::TypeHook) registered via
+    /// [`Connection::set_type_hooks`](crate::connection::Connection::set_type_hooks)
+    /// returned an error while converting `column`.
+    #[error("type hook for column {column:?} failed: {source}")]
+    TypeHook {
+        column: String,
+        #[source]
+        source: Box<Error>,
+    },
+    /// A [`crate::serde_support`] (de)serialization failure, e.g. a missing
+    /// column or a type mismatch. Only constructed when the `serde` feature
+    /// is enabled.
+    #[cfg(feature = "serde")]
+    #[error("{0}")]
+    Serde(String),
+    /// [`Connection::execute_script`](crate::connection::Connection::execute_script)
+    /// failed to prepare or step `statement_index`'th (0-based) statement in
+    /// the script.
+    #[error("script failed at statement {statement_index}: {source}")]
+    Script {
+        statement_index: usize,
+        #[source]
+        source: Box<Error>,
+    },
+    /// [`ConnectionBuilder::open`](crate::connection_builder::ConnectionBuilder::open)
+    /// failed to apply the `name`'d pragma, either because the underlying
+    /// `PRAGMA` statement itself errored, or (for pragmas that echo back the
+    /// value they actually applied, like `journal_mode`) because
+    /// [`Error::PragmaRejected`] says the database refused it.
+    #[error("failed to apply pragma {name}: {source}")]
+    Pragma {
+        name: String,
+        #[source]
+        source: Box<Error>,
+    },
+    /// A pragma that echoes the value it applied (e.g. `journal_mode`)
+    /// reported back something other than what was requested, meaning
+    /// SQLite silently kept its previous setting instead of erroring.
+    #[error("requested {requested:?} but sqlite reports {actual:?}")]
+    PragmaRejected { requested: String, actual: String },
+}
+
+impl From<NulError> for Error {
+    fn from(err: NulError) -> Self {
+        Error::EmbeddedNul {
+            position: err.nul_position(),
+        }
+    }
+}
+
+impl Error {
+    /// Build a [`Error::Sqlite`] from a raw result code, using
+    /// `sqlite3_errstr` for the human-readable part of the message.
+    pub(crate) fn sqlite_code(code: i32, context: &str) -> Self {
+        let errstr = unsafe {
+            let ptr = crate::ffi::sqlite3_errstr(code);
+            std::ffi::CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        };
+        Error::Sqlite {
+            code,
+            message: format!("{context}: {errstr}"),
+            sql: None,
+            param_summary: None,
+        }
+    }
+
+    /// Build a [`Error::Sqlite`] with `sql`/`param_summary` (if any) folded
+    /// into `message` as `" [sql: ..., params: ...]"`, for a prepare or
+    /// [`Statement::step`](crate::statement::Statement::step) failure that
+    /// has that context available.
+    pub(crate) fn sqlite_with_statement_context(
+        code: i32,
+        message: String,
+        sql: Option<String>,
+        param_summary: Option<String>,
+    ) -> Self {
+        let mut message = message;
+        if let Some(sql) = &sql {
+            message.push_str(&format!(" [sql: {sql:?}"));
+            if let Some(summary) = &param_summary {
+                message.push_str(&format!(", params: {summary}"));
+            }
+            message.push(']');
+        }
+        Error::Sqlite { code, message, sql, param_summary }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sqlite_errors_are_equal_only_when_their_statement_context_matches() {
+        let a = Error::sqlite_with_statement_context(1, "no such column: x".to_string(), Some("SELECT x".to_string()), None);
+        let b = Error::sqlite_with_statement_context(1, "no such column: x".to_string(), Some("SELECT x".to_string()), None);
+        let c = Error::sqlite_with_statement_context(1, "no such column: x".to_string(), Some("SELECT y".to_string()), None);
+
+        assert_eq!(a, b);
+        assert_eq!(a.clone(), a);
+        assert_ne!(a, c);
+    }
+}
@@ -0,0 +1,276 @@
+//! Ready-made scalar SQL functions that most applications end up registering
+//! by hand: `uuid()`/`uuid_blob()`/`uuid_str()`, the `REGEXP` operator, and
+//! `sha1()`/`sha256()`/`md5()` digests. Built on top of
+//! [`crate::function::create_scalar_function`] the same way any other
+//! caller would be; nothing here has special access to SQLite internals.
+//!
+//! Gated behind the `extra-functions` feature so that the `regex`, `uuid`,
+//! `sha1`, `sha2`, `md-5`, and `lru` dependencies stay out of the build for
+//! callers who don't want them.
+
+use crate::connection::Connection;
+use crate::error::Result;
+use crate::ffi;
+use crate::function::{create_scalar_function, set_result, set_result_error, value_to_value};
+use crate::value::Value;
+use std::ffi::c_void;
+use std::num::NonZeroUsize;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The bytes a digest/regexp function should operate on: text is hashed (or
+/// matched) as its UTF-8 encoding, a blob as itself. Any other argument
+/// type is a misuse of the function, reported as a SQL error rather than a
+/// panic or a silently wrong answer.
+fn value_bytes<'a>(ctx: *mut ffi::sqlite3_context, value: &'a Value, function_name: &str) -> Option<&'a [u8]> {
+    match value {
+        Value::Text(s) => Some(s.as_bytes()),
+        Value::Blob(b) => Some(b),
+        other => {
+            unsafe {
+                set_result_error(
+                    ctx,
+                    &format!("{function_name}() requires a TEXT or BLOB argument, got {other:?}"),
+                )
+            };
+            None
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Register `uuid()` (a random version-4 UUID as lowercase hyphenated
+/// text, e.g. `sqlite3_create_function_v2` without `SQLITE_DETERMINISTIC`
+/// since it's different every call), `uuid_blob(text)` (the same UUID
+/// packed into its 16-byte binary form), and `uuid_str(blob)` (the
+/// reverse) -- both conversions are deterministic. See
+/// [`Connection::register_uuid`].
+pub fn register_uuid(conn: &Connection) -> Result<()> {
+    unsafe extern "C" fn uuid4(ctx: *mut ffi::sqlite3_context, argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 0);
+        unsafe { set_result(ctx, &Value::Text(uuid::Uuid::new_v4().to_string())) };
+    }
+    unsafe extern "C" fn uuid_blob(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let value = unsafe { value_to_value(*argv) };
+        let Value::Text(text) = &value else {
+            unsafe { set_result_error(ctx, "uuid_blob() requires a TEXT argument") };
+            return;
+        };
+        match text.parse::<uuid::Uuid>() {
+            Ok(uuid) => unsafe { set_result(ctx, &Value::Blob(uuid.as_bytes().to_vec())) },
+            Err(err) => unsafe { set_result_error(ctx, &format!("uuid_blob(): {err}")) },
+        }
+    }
+    unsafe extern "C" fn uuid_str(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let value = unsafe { value_to_value(*argv) };
+        let Some(bytes) = value_bytes(ctx, &value, "uuid_str") else {
+            return;
+        };
+        match <[u8; 16]>::try_from(bytes) {
+            Ok(bytes) => unsafe { set_result(ctx, &Value::Text(uuid::Uuid::from_bytes(bytes).to_string())) },
+            Err(_) => unsafe { set_result_error(ctx, "uuid_str() requires a 16-byte BLOB argument") },
+        }
+    }
+    unsafe {
+        create_scalar_function(conn, "uuid", 0, 0, std::ptr::null_mut(), uuid4, None)?;
+        create_scalar_function(conn, "uuid_blob", 1, ffi::SQLITE_DETERMINISTIC, std::ptr::null_mut(), uuid_blob, None)?;
+        create_scalar_function(conn, "uuid_str", 1, ffi::SQLITE_DETERMINISTIC, std::ptr::null_mut(), uuid_str, None)?;
+    }
+    Ok(())
+}
+
+/// Compiled-pattern cache backing [`register_regexp`], keyed by pattern
+/// text. Bounded (LRU-evicted) so that a query pattern built from
+/// unbounded user input can't grow the cache without limit.
+pub struct RegexpCache {
+    cache: Mutex<lru::LruCache<String, Arc<regex::Regex>>>,
+    compiles: AtomicUsize,
+}
+
+impl RegexpCache {
+    fn new() -> Self {
+        RegexpCache {
+            cache: Mutex::new(lru::LruCache::new(NonZeroUsize::new(128).unwrap())),
+            compiles: AtomicUsize::new(0),
+        }
+    }
+
+    fn get_or_compile(&self, pattern: &str) -> std::result::Result<Arc<regex::Regex>, regex::Error> {
+        let mut cache = self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(regex) = cache.get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Arc::new(regex::Regex::new(pattern)?);
+        cache.put(pattern.to_string(), regex.clone());
+        self.compiles.fetch_add(1, Ordering::Relaxed);
+        Ok(regex)
+    }
+
+    /// How many times a pattern has actually been compiled so far (as
+    /// opposed to served from the cache; a pattern evicted and compiled
+    /// again counts twice) -- for tests to confirm the cache is doing its
+    /// job.
+    pub fn compile_count(&self) -> usize {
+        self.compiles.load(Ordering::Relaxed)
+    }
+}
+
+/// Register the `regexp(pattern, text)` function backing SQLite's `X
+/// REGEXP Y` operator (SQLite compiles that operator to a call to a
+/// two-argument function named `regexp`, in `(needle-pattern,
+/// haystack-text)` order -- see <https://sqlite.org/lang_expr.html#regexp>),
+/// via the `regex` crate with an LRU cache of compiled patterns keyed by
+/// pattern text. Deterministic: the same `(pattern, text)` pair always
+/// matches the same way. Returns the cache so callers (and tests) can
+/// inspect [`RegexpCache::compile_count`]; the connection holds its own
+/// reference, so the returned one may be dropped at any time. Each call
+/// creates a new cache, used only by `conn`. See
+/// [`Connection::register_regexp`].
+pub fn register_regexp(conn: &Connection) -> Result<Arc<RegexpCache>> {
+    unsafe extern "C" fn regexp(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 2);
+        let args = unsafe { std::slice::from_raw_parts(argv, 2) };
+        let pattern = unsafe { value_to_value(args[0]) };
+        let text = unsafe { value_to_value(args[1]) };
+        let Value::Text(pattern) = &pattern else {
+            unsafe { set_result_error(ctx, "REGEXP pattern must be TEXT") };
+            return;
+        };
+        let Some(text) = value_bytes(ctx, &text, "regexp") else {
+            return;
+        };
+        let Ok(text) = std::str::from_utf8(text) else {
+            unsafe { set_result_error(ctx, "regexp() requires a BLOB argument to be valid UTF-8") };
+            return;
+        };
+        let cache = unsafe { &*(ffi::sqlite3_user_data(ctx) as *const RegexpCache) };
+        match cache.get_or_compile(pattern) {
+            Ok(regex) => unsafe { set_result(ctx, &Value::Integer(regex.is_match_at(text, 0) as i64)) },
+            Err(err) => unsafe { set_result_error(ctx, &format!("REGEXP: {err}")) },
+        }
+    }
+    unsafe extern "C" fn destroy(user_data: *mut c_void) {
+        drop(unsafe { Arc::from_raw(user_data as *const RegexpCache) });
+    }
+    let cache = Arc::new(RegexpCache::new());
+    let user_data = Arc::into_raw(cache.clone()) as *mut c_void;
+    unsafe {
+        create_scalar_function(conn, "regexp", 2, ffi::SQLITE_DETERMINISTIC, user_data, regexp, Some(destroy))?;
+    }
+    Ok(cache)
+}
+
+/// Register `sha1(x)`, `sha256(x)`, and `md5(x)`, each hashing a TEXT
+/// (as UTF-8) or BLOB argument and returning the digest as lowercase hex
+/// text. All three are deterministic. See [`Connection::register_digest`].
+pub fn register_digest(conn: &Connection) -> Result<()> {
+    use digest::Digest as _;
+
+    unsafe extern "C" fn sha1_fn(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let value = unsafe { value_to_value(*argv) };
+        let Some(bytes) = value_bytes(ctx, &value, "sha1") else {
+            return;
+        };
+        unsafe { set_result(ctx, &Value::Text(hex(&sha1::Sha1::digest(bytes)))) };
+    }
+    unsafe extern "C" fn sha256_fn(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let value = unsafe { value_to_value(*argv) };
+        let Some(bytes) = value_bytes(ctx, &value, "sha256") else {
+            return;
+        };
+        unsafe { set_result(ctx, &Value::Text(hex(&sha2::Sha256::digest(bytes)))) };
+    }
+    unsafe extern "C" fn md5_fn(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let value = unsafe { value_to_value(*argv) };
+        let Some(bytes) = value_bytes(ctx, &value, "md5") else {
+            return;
+        };
+        unsafe { set_result(ctx, &Value::Text(hex(&md5::Md5::digest(bytes)))) };
+    }
+    unsafe {
+        create_scalar_function(conn, "sha1", 1, ffi::SQLITE_DETERMINISTIC, std::ptr::null_mut(), sha1_fn, None)?;
+        create_scalar_function(conn, "sha256", 1, ffi::SQLITE_DETERMINISTIC, std::ptr::null_mut(), sha256_fn, None)?;
+        create_scalar_function(conn, "md5", 1, ffi::SQLITE_DETERMINISTIC, std::ptr::null_mut(), md5_fn, None)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+
+    fn column_value(conn: &Connection, sql: &str) -> Value {
+        let mut stmt = conn.execute(sql).unwrap();
+        stmt.step().unwrap();
+        stmt.column_value(0).unwrap()
+    }
+
+    #[test]
+    fn uuid_round_trips_blob_and_text() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        register_uuid(&conn).unwrap();
+
+        let text = column_value(&conn, "SELECT uuid()");
+        let Value::Text(text) = text else { panic!("expected text") };
+        assert_eq!(text.len(), 36);
+
+        let round_tripped = column_value(&conn, &format!("SELECT uuid_str(uuid_blob('{text}'))"));
+        assert_eq!(round_tripped, Value::Text(text.to_lowercase()));
+    }
+
+    #[test]
+    fn regexp_filters_rows_and_caches_compiled_patterns() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let cache = register_regexp(&conn).unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        for value in ["az", "abz", "abc", "z"] {
+            conn.execute(&format!("INSERT INTO t VALUES ('{value}')")).unwrap().step().unwrap();
+        }
+
+        let mut stmt = conn.execute("SELECT a FROM t WHERE a REGEXP '^a.*z$' ORDER BY a").unwrap();
+        let mut matched = Vec::new();
+        while stmt.step().unwrap() {
+            matched.push(stmt.column_value(0).unwrap());
+        }
+        assert_eq!(matched, vec![Value::Text("abz".to_string()), Value::Text("az".to_string())]);
+        assert_eq!(cache.compile_count(), 1);
+
+        // Re-running the same pattern against a fresh statement doesn't
+        // compile it again.
+        let mut stmt = conn.execute("SELECT a FROM t WHERE a REGEXP '^a.*z$'").unwrap();
+        while stmt.step().unwrap() {}
+        assert_eq!(cache.compile_count(), 1);
+    }
+
+    #[test]
+    fn digests_match_known_test_vectors() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        register_digest(&conn).unwrap();
+
+        assert_eq!(
+            column_value(&conn, "SELECT sha1('abc')"),
+            Value::Text("a9993e364706816aba3e25717850c26c9cd0d89d".to_string())
+        );
+        assert_eq!(
+            column_value(&conn, "SELECT sha256('abc')"),
+            Value::Text("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string())
+        );
+        assert_eq!(
+            column_value(&conn, "SELECT md5('abc')"),
+            Value::Text("900150983cd24fb0d6963f7d28e17f72".to_string())
+        );
+    }
+}
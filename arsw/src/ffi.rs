@@ -0,0 +1,66 @@
+//! Thin re-export of the raw SQLite C API.
+//!
+//! This module is the only place in the crate allowed to call into
+//! `libsqlite3-sys` directly; every other module builds safe abstractions on
+//! top of it. Keeping the unsafe surface in one place makes it easy to audit
+//! and to track which parts of the C API `arsw` currently binds.
+pub use libsqlite3_sys::*;
+
+// `libsqlite3-sys`'s pregenerated bindings omit a handful of stable,
+// long-standing SQLite C API functions. Declare them ourselves rather than
+// pull in `buildtime_bindgen` (which needs libclang) just for these.
+extern "C" {
+    /// Like [`sqlite3_close`], but defers the actual close (and ignores the
+    /// "still has unfinalized statements" error) until the last statement,
+    /// backup, or blob handle on the connection is finalized/closed.
+    pub fn sqlite3_close_v2(db: *mut sqlite3) -> std::os::raw::c_int;
+}
+
+// `sqlite3_normalized_sql` is missing from the pregenerated bindings for a
+// different reason than the functions above: it's compiled out of SQLite
+// itself unless `SQLITE_ENABLE_NORMALIZE` is defined, and the bundled
+// amalgamation isn't built with that flag by default. The workspace
+// `.cargo/config.toml` turns it on via `LIBSQLITE3_FLAGS`, so the symbol is
+// present in the linked library; declare it by hand since bindgen never ran
+// with the flag set to pick it up.
+extern "C" {
+    /// Returns `pStmt`'s SQL text with literals, bound parameters, and
+    /// whitespace/comments normalized away, for query fingerprinting. The
+    /// returned pointer is owned by the statement; do not `sqlite3_free` it.
+    pub fn sqlite3_normalized_sql(stmt: *mut sqlite3_stmt) -> *const std::os::raw::c_char;
+}
+
+// `sqlite3_vtab_config` is a C variadic function; `libsqlite3-sys`'s
+// bindings declare it with a bare `...`, which Rust can't call directly.
+// Declare the two fixed-arity shapes it's actually used with instead --
+// `SQLITE_VTAB_CONSTRAINT_SUPPORT` takes one `int` argument, the other
+// `SQLITE_VTAB_*` ops take none -- both bound to the same real symbol. This
+// intentionally redeclares `sqlite3_vtab_config` with narrower signatures
+// than its real variadic one, which is exactly what `clashing_extern_declarations`
+// exists to flag; it's a false positive here, not a real ABI mismatch.
+#[allow(clashing_extern_declarations)]
+extern "C" {
+    #[link_name = "sqlite3_vtab_config"]
+    pub fn sqlite3_vtab_config_noarg(db: *mut sqlite3, op: std::os::raw::c_int) -> std::os::raw::c_int;
+    #[link_name = "sqlite3_vtab_config"]
+    pub fn sqlite3_vtab_config_int(
+        db: *mut sqlite3,
+        op: std::os::raw::c_int,
+        val: std::os::raw::c_int,
+    ) -> std::os::raw::c_int;
+}
+
+// Same situation as `sqlite3_vtab_config` above, for `sqlite3_db_config`:
+// its boolean-flag ops (e.g. `SQLITE_DBCONFIG_TRUSTED_SCHEMA`) all take one
+// `int` argument plus an output `int*` that's safe to pass as `NULL` when
+// the caller doesn't need the resulting value echoed back.
+#[allow(clashing_extern_declarations)]
+extern "C" {
+    #[link_name = "sqlite3_db_config"]
+    pub fn sqlite3_db_config_int(
+        db: *mut sqlite3,
+        op: std::os::raw::c_int,
+        val: std::os::raw::c_int,
+        out: *mut std::os::raw::c_int,
+    ) -> std::os::raw::c_int;
+}
@@ -0,0 +1,284 @@
+//! Registration of custom FTS5 tokenizers through the real `fts5_api`.
+//!
+//! FTS5 doesn't expose its `fts5_api` through an ordinary exported C
+//! function; the documented way to retrieve it (see `ext/fts5/fts5.h`) is
+//! to prepare `SELECT fts5(?1)` and bind a pointer tagged `"fts5_api_ptr"`
+//! for FTS5's own scalar function to write the real pointer into.
+//!
+//! This module hands out the raw `fts5_api`/`fts5_tokenizer` C types
+//! directly rather than wrapping them in a Rust trait, matching
+//! [`crate::log::set_log_callback`]'s raw-callback style: callers build
+//! their own `xCreate`/`xDelete`/`xTokenize` trampolines.
+
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+/// Retrieve the process's `fts5_api` pointer through `conn`.
+fn fts5_api(conn: &Connection) -> Result<*mut ffi::fts5_api> {
+    let mut api: *mut ffi::fts5_api = std::ptr::null_mut();
+    let mut stmt = conn.execute("SELECT fts5(?1)")?;
+    unsafe { stmt.bind_pointer(1, (&mut api as *mut *mut ffi::fts5_api).cast(), c"fts5_api_ptr", None) }?;
+    stmt.step()?;
+    if api.is_null() {
+        return Err(Error::Misuse("fts5_api unavailable (FTS5 not compiled in)"));
+    }
+    Ok(api)
+}
+
+/// Register `tokenizer` under `name` with the real `fts5_api`, via
+/// `xCreateTokenizer`. `user_data` is passed back to `tokenizer`'s
+/// `xCreate`/`xDelete` unchanged; `destroy` (if given) runs once the
+/// tokenizer is replaced or the connection closes.
+///
+/// # Safety
+///
+/// `tokenizer`'s function pointers must honor the `fts5_tokenizer`
+/// contract (see `ext/fts5/fts5.h`), and `user_data` must remain valid
+/// until `destroy` runs (or forever, if `destroy` is `None`).
+pub unsafe fn create_tokenizer(
+    conn: &Connection,
+    name: &str,
+    user_data: *mut c_void,
+    mut tokenizer: ffi::fts5_tokenizer,
+    destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+) -> Result<()> {
+    let api = fts5_api(conn)?;
+    let cname = CString::new(name)?;
+    let create_tokenizer = unsafe { &*api }
+        .xCreateTokenizer
+        .ok_or(Error::Misuse("fts5_api has no xCreateTokenizer"))?;
+    let rc = unsafe { create_tokenizer(api, cname.as_ptr(), user_data, &mut tokenizer, destroy) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "fts5_api::xCreateTokenizer failed"));
+    }
+    Ok(())
+}
+
+/// `true` if a tokenizer named `name` is registered with FTS5, checked by
+/// actually calling `xFindTokenizer` -- the real registry, not a side table
+/// of our own that could drift from it.
+pub fn tokenizer_available(conn: &Connection, name: &str) -> Result<bool> {
+    let api = fts5_api(conn)?;
+    let cname = CString::new(name)?;
+    let find_tokenizer = unsafe { &*api }
+        .xFindTokenizer
+        .ok_or(Error::Misuse("fts5_api has no xFindTokenizer"))?;
+    let mut user_data: *mut c_void = std::ptr::null_mut();
+    let mut tokenizer: ffi::fts5_tokenizer = unsafe { std::mem::zeroed() };
+    let rc = unsafe { find_tokenizer(api, cname.as_ptr(), &mut user_data, &mut tokenizer) };
+    Ok(rc == ffi::SQLITE_OK)
+}
+
+/// Register `function` as an FTS5 auxiliary function callable as
+/// `name(...)` from inside a query against an FTS5 table, via
+/// `fts5_api::xCreateFunction`. Unlike an ordinary scalar function, it is
+/// passed an `Fts5Context*` it can use (through the `Fts5ExtensionApi*`
+/// it's also given) to inspect the matching row: `xColumnText`,
+/// `xInstCount`/`xInst`, `xRowid`, and so on.
+///
+/// # Safety
+///
+/// `user_data` must remain valid until `destroy` runs (or forever, if
+/// `destroy` is `None`).
+pub unsafe fn create_function(
+    conn: &Connection,
+    name: &str,
+    user_data: *mut c_void,
+    function: ffi::fts5_extension_function,
+    destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+) -> Result<()> {
+    let api = fts5_api(conn)?;
+    let cname = CString::new(name)?;
+    let create_function = unsafe { &*api }
+        .xCreateFunction
+        .ok_or(Error::Misuse("fts5_api has no xCreateFunction"))?;
+    let rc = unsafe { create_function(api, cname.as_ptr(), user_data, function, destroy) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "fts5_api::xCreateFunction failed"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::value::Value;
+    use std::os::raw::{c_char, c_int};
+
+    /// A tokenizer with no state of its own: `xCreate` hands back a null
+    /// `Fts5Tokenizer*`, and every other callback ignores it.
+    unsafe extern "C" fn whitespace_create(
+        _user_data: *mut c_void,
+        _args: *mut *const c_char,
+        _nargs: c_int,
+        out: *mut *mut ffi::Fts5Tokenizer,
+    ) -> c_int {
+        unsafe { *out = std::ptr::null_mut() };
+        ffi::SQLITE_OK
+    }
+
+    unsafe extern "C" fn whitespace_delete(_tokenizer: *mut ffi::Fts5Tokenizer) {}
+
+    /// Splits on single ASCII spaces -- just enough to prove a custom
+    /// tokenizer actually ran, not a serious tokenizer implementation.
+    unsafe extern "C" fn whitespace_tokenize(
+        _tokenizer: *mut ffi::Fts5Tokenizer,
+        ctx: *mut c_void,
+        _flags: c_int,
+        text: *const c_char,
+        text_len: c_int,
+        on_token: Option<
+            unsafe extern "C" fn(*mut c_void, c_int, *const c_char, c_int, c_int, c_int) -> c_int,
+        >,
+    ) -> c_int {
+        let Some(on_token) = on_token else { return ffi::SQLITE_OK };
+        let bytes = unsafe { std::slice::from_raw_parts(text.cast::<u8>(), text_len as usize) };
+        let mut start = 0usize;
+        for i in 0..=bytes.len() {
+            if i < bytes.len() && bytes[i] != b' ' {
+                continue;
+            }
+            if i > start {
+                let rc = unsafe {
+                    on_token(
+                        ctx,
+                        0,
+                        bytes[start..i].as_ptr().cast(),
+                        (i - start) as c_int,
+                        start as c_int,
+                        i as c_int,
+                    )
+                };
+                if rc != ffi::SQLITE_OK {
+                    return rc;
+                }
+            }
+            start = i + 1;
+        }
+        ffi::SQLITE_OK
+    }
+
+    #[test]
+    fn create_tokenizer_and_availability_use_the_real_registry() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        assert!(!conn.fts5_tokenizer_available("rust_test_tok").unwrap());
+
+        let tokenizer = ffi::fts5_tokenizer {
+            xCreate: Some(whitespace_create),
+            xDelete: Some(whitespace_delete),
+            xTokenize: Some(whitespace_tokenize),
+        };
+        unsafe {
+            conn.create_fts5_tokenizer("rust_test_tok", std::ptr::null_mut(), tokenizer, None)
+                .unwrap();
+        }
+
+        assert!(conn.fts5_tokenizer_available("rust_test_tok").unwrap());
+
+        conn.execute("CREATE VIRTUAL TABLE docs USING fts5(body, tokenize = 'rust_test_tok')")
+            .unwrap()
+            .step()
+            .unwrap();
+        conn.execute("INSERT INTO docs(body) VALUES ('hello world')")
+            .unwrap()
+            .step()
+            .unwrap();
+        conn.execute("INSERT INTO docs(body) VALUES ('goodbye moon')")
+            .unwrap()
+            .step()
+            .unwrap();
+
+        // Only matches if `rust_test_tok` (not FTS5's default `unicode61`)
+        // actually indexed "world" as its own token.
+        let mut stmt = conn.execute("SELECT body FROM docs WHERE docs MATCH 'world'").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Text("hello world".to_string()));
+        assert!(!stmt.step().unwrap());
+    }
+
+    /// `first_upper(tbl)` -- the matched row's first column, uppercased.
+    unsafe extern "C" fn first_upper(
+        api: *const ffi::Fts5ExtensionApi,
+        fts: *mut ffi::Fts5Context,
+        ctx: *mut ffi::sqlite3_context,
+        _n_val: c_int,
+        _ap_val: *mut *mut ffi::sqlite3_value,
+    ) {
+        let x_column_text = unsafe { &*api }.xColumnText.unwrap();
+        let mut ptr: *const c_char = std::ptr::null();
+        let mut len: c_int = 0;
+        if unsafe { x_column_text(fts, 0, &mut ptr, &mut len) } != ffi::SQLITE_OK {
+            unsafe { ffi::sqlite3_result_null(ctx) };
+            return;
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), len as usize) };
+        let upper: Vec<u8> = bytes.iter().map(|b| b.to_ascii_uppercase()).collect();
+        // SQLITE_TRANSIENT: sqlite3_result_text copies `upper` before this
+        // call returns, so the destructor we pass doesn't need to free
+        // anything -- it's just the `-1` sentinel meaning "copy it".
+        let transient: unsafe extern "C" fn(*mut c_void) = unsafe { std::mem::transmute(-1isize) };
+        unsafe {
+            ffi::sqlite3_result_text(ctx, upper.as_ptr().cast(), upper.len() as c_int, Some(transient));
+        }
+    }
+
+    /// `phrase_hits(tbl)` -- total phrase-match instances in the matched
+    /// row, for ranking by how often the query actually hit.
+    unsafe extern "C" fn phrase_hits(
+        api: *const ffi::Fts5ExtensionApi,
+        fts: *mut ffi::Fts5Context,
+        ctx: *mut ffi::sqlite3_context,
+        _n_val: c_int,
+        _ap_val: *mut *mut ffi::sqlite3_value,
+    ) {
+        let x_inst_count = unsafe { &*api }.xInstCount.unwrap();
+        let mut count: c_int = 0;
+        unsafe { x_inst_count(fts, &mut count) };
+        unsafe { ffi::sqlite3_result_int64(ctx, count as i64) };
+    }
+
+    #[test]
+    fn create_function_exposes_fts5_context_to_sql() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe {
+            conn.create_fts5_function("first_upper", std::ptr::null_mut(), Some(first_upper), None)
+                .unwrap();
+            conn.create_fts5_function("phrase_hits", std::ptr::null_mut(), Some(phrase_hits), None)
+                .unwrap();
+        }
+
+        conn.execute("CREATE VIRTUAL TABLE docs USING fts5(body)")
+            .unwrap()
+            .step()
+            .unwrap();
+        conn.execute("INSERT INTO docs(body) VALUES ('alpha alpha beta')")
+            .unwrap()
+            .step()
+            .unwrap();
+        conn.execute("INSERT INTO docs(body) VALUES ('alpha beta beta beta')")
+            .unwrap()
+            .step()
+            .unwrap();
+
+        let mut stmt = conn
+            .execute("SELECT first_upper(docs) FROM docs WHERE docs MATCH 'alpha' ORDER BY rowid LIMIT 1")
+            .unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Text("ALPHA ALPHA BETA".to_string()));
+
+        // "beta" hits three times in the second row, once in the first --
+        // a real ranking function, not a fixed ORDER BY rowid.
+        let mut stmt = conn
+            .execute("SELECT body FROM docs WHERE docs MATCH 'beta' ORDER BY phrase_hits(docs) DESC")
+            .unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Text("alpha beta beta beta".to_string()));
+    }
+}
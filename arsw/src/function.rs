@@ -0,0 +1,535 @@
+//! Registration of scalar SQL functions and subtype propagation, via
+//! `sqlite3_create_function_v2` / `sqlite3_value_subtype` /
+//! `sqlite3_result_subtype`.
+//!
+//! Like [`crate::fts5`], this hands out the raw `xFunc`/`destroy` callback
+//! shape directly rather than wrapping it in a Rust trait or closure,
+//! matching [`crate::log::set_log_callback`]'s raw-callback style: callers
+//! build their own `xFunc` trampoline. Marshalling Python callables is
+//! `arsw-py`'s job (its own `function` module); the `apsw.with_subtype`
+//! wrapper object JSON-aware functions need is not implemented there.
+
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use crate::value::Value;
+use std::ffi::CString;
+use std::os::raw::{c_int, c_uint, c_void};
+
+/// Every flag bit [`create_scalar_function`] understands. Defined once here;
+/// `arsw-py` passes its flags straight through [`create_scalar_function`],
+/// so Rust- and Python-level callers are validated against the same
+/// compile-time constants instead of each keeping their own copy that can
+/// drift.
+pub const KNOWN_FUNCTION_FLAGS: i32 =
+    ffi::SQLITE_DETERMINISTIC | ffi::SQLITE_DIRECTONLY | ffi::SQLITE_INNOCUOUS | ffi::SQLITE_SUBTYPE | ffi::SQLITE_RESULT_SUBTYPE;
+
+/// Register `function` as a scalar SQL function callable as `name(...)`,
+/// via `sqlite3_create_function_v2`. `flags` is OR'd with `SQLITE_UTF8`
+/// and passed through, so callers can set `SQLITE_DETERMINISTIC`,
+/// `SQLITE_DIRECTONLY`, `SQLITE_INNOCUOUS`, `SQLITE_SUBTYPE`, and/or
+/// `SQLITE_RESULT_SUBTYPE` (see [`value_subtype`]/[`set_result_subtype`])
+/// as needed; any bit outside [`KNOWN_FUNCTION_FLAGS`] is rejected with
+/// [`Error::Misuse`] rather than silently passed through to SQLite (which
+/// would otherwise just ignore it). `user_data` is passed back to
+/// `function` unchanged; `destroy` (if given) runs once the function is
+/// replaced or the connection closes.
+///
+/// # Safety
+///
+/// `function` must honor the `xFunc` contract (see `sqlite3_create_function`
+/// in `sqlite3.h`), and `user_data` must remain valid until `destroy` runs
+/// (or forever, if `destroy` is `None`).
+pub unsafe fn create_scalar_function(
+    conn: &Connection,
+    name: &str,
+    nargs: i32,
+    flags: i32,
+    user_data: *mut c_void,
+    function: unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value),
+    destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+) -> Result<()> {
+    if flags & !KNOWN_FUNCTION_FLAGS != 0 {
+        return Err(Error::Misuse("create_scalar_function: unknown flag bits"));
+    }
+    let cname = CString::new(name)?;
+    let rc = unsafe {
+        ffi::sqlite3_create_function_v2(
+            conn.as_ptr(),
+            cname.as_ptr(),
+            nargs as c_int,
+            ffi::SQLITE_UTF8 | flags,
+            user_data,
+            Some(function),
+            None,
+            None,
+            destroy,
+        )
+    };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_create_function_v2 failed"));
+    }
+    Ok(())
+}
+
+/// Unregister the scalar/aggregate/window function overload matching
+/// `name`/`nargs` exactly, via `sqlite3_create_function_v2` with every
+/// callback `NULL` -- SQLite's own documented way to remove a function.
+/// Other arities registered under the same `name` are unaffected. If
+/// SQLite is still executing a statement that uses this overload when it's
+/// removed, that statement keeps running against the old definition;
+/// SQLite (not this crate) owns that guarantee.
+pub fn remove_function(conn: &Connection, name: &str, nargs: i32) -> Result<()> {
+    let cname = CString::new(name)?;
+    let rc = unsafe {
+        ffi::sqlite3_create_function_v2(
+            conn.as_ptr(),
+            cname.as_ptr(),
+            nargs as c_int,
+            ffi::SQLITE_UTF8,
+            std::ptr::null_mut(),
+            None,
+            None,
+            None,
+            None,
+        )
+    };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_create_function_v2 failed"));
+    }
+    Ok(())
+}
+
+/// Read an `xFunc` argument as a [`Value`], via `sqlite3_value_type` and the
+/// matching `sqlite3_value_*` accessor -- the `xFunc` counterpart of
+/// [`crate::statement::Statement::column_value`].
+///
+/// # Safety
+///
+/// `value` must be a valid `sqlite3_value*` for the duration of the call,
+/// e.g. one of the `argv` pointers an `xFunc` callback was given.
+pub unsafe fn value_to_value(value: *mut ffi::sqlite3_value) -> Value {
+    match unsafe { ffi::sqlite3_value_type(value) } {
+        ffi::SQLITE_NULL => Value::Null,
+        ffi::SQLITE_INTEGER => Value::Integer(unsafe { ffi::sqlite3_value_int64(value) }),
+        ffi::SQLITE_FLOAT => Value::Real(unsafe { ffi::sqlite3_value_double(value) }),
+        ffi::SQLITE_TEXT => {
+            let ptr = unsafe { ffi::sqlite3_value_text(value) };
+            let len = unsafe { ffi::sqlite3_value_bytes(value) } as usize;
+            if ptr.is_null() || len == 0 {
+                Value::Text(String::new())
+            } else {
+                let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+                Value::Text(String::from_utf8_lossy(bytes).into_owned())
+            }
+        }
+        ffi::SQLITE_BLOB => {
+            let ptr = unsafe { ffi::sqlite3_value_blob(value) };
+            let len = unsafe { ffi::sqlite3_value_bytes(value) } as usize;
+            if ptr.is_null() || len == 0 {
+                Value::Blob(Vec::new())
+            } else {
+                let bytes = unsafe { std::slice::from_raw_parts(ptr.cast(), len) };
+                Value::Blob(bytes.to_vec())
+            }
+        }
+        other => unreachable!("sqlite3_value_type returned unknown type {other}"),
+    }
+}
+
+/// Read back a type-tagged pointer bound with
+/// [`Statement::bind_pointer`](crate::statement::Statement::bind_pointer),
+/// via `sqlite3_value_pointer`. `None` if `value` wasn't bound as a
+/// pointer, or was tagged with a different `type_tag`.
+///
+/// # Safety
+///
+/// `value` must be a valid `sqlite3_value*` for the duration of the call,
+/// e.g. one of the `argv` pointers an `xFunc` callback was given.
+pub unsafe fn value_pointer(value: *mut ffi::sqlite3_value, type_tag: &std::ffi::CStr) -> Option<*mut c_void> {
+    let ptr = unsafe { ffi::sqlite3_value_pointer(value, type_tag.as_ptr()) };
+    (!ptr.is_null()).then_some(ptr)
+}
+
+/// The typed counterpart of [`value_pointer`]: read back a pointer bound
+/// with [`Statement::bind_rust_pointer`](crate::statement::Statement::bind_rust_pointer)
+/// as a `&T`. `None` if `value` wasn't bound as a pointer tagged
+/// `type_tag`.
+///
+/// # Safety
+///
+/// `value` must be a valid `sqlite3_value*` for the duration of the call.
+/// The caller must know that any pointer tagged `type_tag` on `value` was
+/// really bound from a `Box<T>` via `bind_rust_pointer::<T>` -- the tag
+/// alone doesn't prove the pointee's type.
+pub unsafe fn value_rust_pointer<'a, T>(value: *mut ffi::sqlite3_value, type_tag: &std::ffi::CStr) -> Option<&'a T> {
+    let ptr = unsafe { value_pointer(value, type_tag) }?;
+    Some(unsafe { &*ptr.cast::<T>() })
+}
+
+/// Set the function result currently being built on `ctx` to `value`, via
+/// the matching `sqlite3_result_*` call.
+///
+/// # Safety
+///
+/// `ctx` must be a valid `sqlite3_context*` for the duration of the call,
+/// e.g. the one an `xFunc` callback was given.
+pub unsafe fn set_result(ctx: *mut ffi::sqlite3_context, value: &Value) {
+    // SQLITE_TRANSIENT: every branch below copies its payload before
+    // returning, so the destructor we pass doesn't need to free anything --
+    // it's just the `-1` sentinel meaning "copy it" (see `fts5::first_upper`
+    // for the same trick).
+    let transient: unsafe extern "C" fn(*mut c_void) = unsafe { std::mem::transmute(-1isize) };
+    match value {
+        Value::Null => unsafe { ffi::sqlite3_result_null(ctx) },
+        Value::Integer(i) => unsafe { ffi::sqlite3_result_int64(ctx, *i) },
+        Value::Real(f) => unsafe { ffi::sqlite3_result_double(ctx, *f) },
+        Value::Text(s) => unsafe { ffi::sqlite3_result_text(ctx, s.as_ptr().cast(), s.len() as c_int, Some(transient)) },
+        Value::Blob(b) => unsafe { ffi::sqlite3_result_blob(ctx, b.as_ptr().cast(), b.len() as c_int, Some(transient)) },
+    }
+}
+
+/// Report that the function currently computing a result on `ctx` failed,
+/// via `sqlite3_result_error`. Aborts the statement with `SQLITE_ERROR` and
+/// `message` as its error text.
+///
+/// # Safety
+///
+/// `ctx` must be a valid `sqlite3_context*` for the duration of the call.
+pub unsafe fn set_result_error(ctx: *mut ffi::sqlite3_context, message: &str) {
+    unsafe { ffi::sqlite3_result_error(ctx, message.as_ptr().cast(), message.len() as c_int) };
+}
+
+/// The subtype an argument value was tagged with, via `sqlite3_value_subtype`.
+/// `0` means untagged (the overwhelming majority of values); SQLite's own
+/// JSON functions tag JSON text results with subtype `74` (`'J'`) so that
+/// functions taking JSON as input can tell it apart from ordinary text.
+///
+/// # Safety
+///
+/// `value` must be a valid `sqlite3_value*` for the duration of the call,
+/// e.g. one of the `argv` pointers an `xFunc` callback was given.
+pub unsafe fn value_subtype(value: *mut ffi::sqlite3_value) -> u32 {
+    unsafe { ffi::sqlite3_value_subtype(value) as u32 }
+}
+
+/// Tag the function result currently being built on `ctx` with `subtype`,
+/// via `sqlite3_result_subtype`. Only takes effect if the function was
+/// registered with the `SQLITE_RESULT_SUBTYPE` flag; SQLite ignores it
+/// otherwise.
+///
+/// # Safety
+///
+/// `ctx` must be a valid `sqlite3_context*` for the duration of the call,
+/// e.g. the one an `xFunc` callback was given.
+pub unsafe fn set_result_subtype(ctx: *mut ffi::sqlite3_context, subtype: u32) {
+    unsafe { ffi::sqlite3_result_subtype(ctx, subtype as c_uint) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::value::Value;
+
+    /// Prepare and fully step `sql`, for statements (like DDL) run purely
+    /// for their side effect.
+    fn run(conn: &Connection, sql: &str) -> Result<()> {
+        conn.execute(sql)?.step()?;
+        Ok(())
+    }
+
+    /// Copies its single argument to the result unchanged, then re-applies
+    /// whatever subtype the argument carried -- `sqlite3_result_value`
+    /// copies content but not subtype, which is exactly the gap this
+    /// request is about.
+    unsafe extern "C" fn passthrough_preserving_subtype(
+        ctx: *mut ffi::sqlite3_context,
+        argc: c_int,
+        argv: *mut *mut ffi::sqlite3_value,
+    ) {
+        assert_eq!(argc, 1);
+        let arg = unsafe { *argv };
+        let subtype = unsafe { value_subtype(arg) };
+        unsafe { ffi::sqlite3_result_value(ctx, arg) };
+        unsafe { set_result_subtype(ctx, subtype) };
+    }
+
+    fn column_value(conn: &Connection, sql: &str) -> Value {
+        let mut stmt = conn.execute(sql).unwrap();
+        stmt.step().unwrap();
+        stmt.column_value(0).unwrap()
+    }
+
+    #[test]
+    fn passthrough_function_preserves_json_subtype() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe {
+            create_scalar_function(
+                &conn,
+                "myfunc",
+                1,
+                ffi::SQLITE_RESULT_SUBTYPE | ffi::SQLITE_SUBTYPE,
+                std::ptr::null_mut(),
+                passthrough_preserving_subtype,
+                None,
+            )
+            .unwrap();
+        }
+
+        // json_insert leaves the new value as a raw JSON string if it's
+        // plain text, but splices it in unquoted if it carries the JSON
+        // subtype -- so this distinguishes the two outcomes purely from
+        // what comes back out of SQL.
+        let with_myfunc =
+            column_value(&conn, "SELECT json_insert('{}', '$.a', myfunc(json('[1,2]')))");
+        let without_myfunc =
+            column_value(&conn, "SELECT json_insert('{}', '$.a', json('[1,2]'))");
+        assert_eq!(with_myfunc, without_myfunc);
+        assert_eq!(with_myfunc, Value::Text(r#"{"a":[1,2]}"#.to_string()));
+
+        // Without going through myfunc, a plain text argument that merely
+        // looks like JSON is inserted as a quoted string -- proving the
+        // subtype tag, not the function, was responsible for the above.
+        let plain_text = column_value(&conn, "SELECT json_insert('{}', '$.a', '[1,2]')");
+        assert_eq!(plain_text, Value::Text(r#"{"a":"[1,2]"}"#.to_string()));
+    }
+
+    #[test]
+    fn create_scalar_function_rejects_unknown_flag_bits() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe extern "C" fn noop(ctx: *mut ffi::sqlite3_context, _argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+            unsafe { ffi::sqlite3_result_null(ctx) };
+        }
+        let bogus_bit = 1 << 30;
+        assert!((bogus_bit & KNOWN_FUNCTION_FLAGS) == 0, "test bit must not collide with a real flag");
+        let result = unsafe { create_scalar_function(&conn, "f", 0, bogus_bit, std::ptr::null_mut(), noop, None) };
+        assert!(matches!(result, Err(Error::Misuse(_))));
+    }
+
+    #[test]
+    fn value_to_value_and_set_result_round_trip_every_variant() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe extern "C" fn echo(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+            assert_eq!(argc, 1);
+            let value = unsafe { value_to_value(*argv) };
+            unsafe { set_result(ctx, &value) };
+        }
+        unsafe {
+            create_scalar_function(&conn, "echo", 1, 0, std::ptr::null_mut(), echo, None).unwrap();
+        }
+        assert_eq!(column_value(&conn, "SELECT echo(NULL)"), Value::Null);
+        assert_eq!(column_value(&conn, "SELECT echo(42)"), Value::Integer(42));
+        assert_eq!(column_value(&conn, "SELECT echo(1.5)"), Value::Real(1.5));
+        assert_eq!(column_value(&conn, "SELECT echo('hi')"), Value::Text("hi".to_string()));
+        assert_eq!(column_value(&conn, "SELECT echo(x'cafe')"), Value::Blob(vec![0xca, 0xfe]));
+    }
+
+    #[test]
+    fn set_result_error_aborts_the_statement() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe extern "C" fn fails(ctx: *mut ffi::sqlite3_context, _argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+            unsafe { set_result_error(ctx, "deliberate failure") };
+        }
+        unsafe {
+            create_scalar_function(&conn, "fails", 0, 0, std::ptr::null_mut(), fails, None).unwrap();
+        }
+        let mut stmt = conn.execute("SELECT fails()").unwrap();
+        let err = stmt.step().unwrap_err();
+        assert!(matches!(err, Error::Sqlite { .. }));
+        assert!(err.to_string().contains("deliberate failure"));
+    }
+
+    #[test]
+    fn a_bound_rust_pointer_is_readable_by_type_tag_and_hidden_from_a_mismatched_one() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe extern "C" fn reads_i32_pointer(
+            ctx: *mut ffi::sqlite3_context,
+            argc: c_int,
+            argv: *mut *mut ffi::sqlite3_value,
+        ) {
+            assert_eq!(argc, 1);
+            let arg = unsafe { *argv };
+            match unsafe { value_rust_pointer::<i32>(arg, c"my_i32") } {
+                Some(n) => unsafe { ffi::sqlite3_result_int64(ctx, i64::from(*n)) },
+                None => unsafe { ffi::sqlite3_result_null(ctx) },
+            }
+        }
+        unsafe {
+            create_scalar_function(&conn, "read_i32", 1, 0, std::ptr::null_mut(), reads_i32_pointer, None).unwrap();
+        }
+
+        let mut stmt = conn.execute("SELECT read_i32(?1)").unwrap();
+        stmt.bind_rust_pointer(1, Box::new(42i32), c"my_i32").unwrap();
+        stmt.step().unwrap();
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Integer(42));
+
+        let mut mismatched = conn.execute("SELECT read_i32(?1)").unwrap();
+        mismatched.bind_rust_pointer(1, Box::new(7i32), c"someone_elses_tag").unwrap();
+        mismatched.step().unwrap();
+        assert_eq!(mismatched.column_value(0).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn bind_rust_pointer_drops_the_boxed_value_once_the_statement_is_finalized() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        struct DropFlag(std::rc::Rc<std::cell::Cell<bool>>);
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let dropped = std::rc::Rc::new(std::cell::Cell::new(false));
+        {
+            let mut stmt = conn.execute("SELECT ?1").unwrap();
+            stmt.bind_rust_pointer(1, Box::new(DropFlag(dropped.clone())), c"drop_flag").unwrap();
+            stmt.step().unwrap();
+            assert!(!dropped.get());
+        }
+        assert!(dropped.get());
+    }
+
+    unsafe extern "C" fn constant_one(ctx: *mut ffi::sqlite3_context, _argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+        unsafe { ffi::sqlite3_result_int64(ctx, 1) };
+    }
+
+    #[test]
+    fn directonly_function_cannot_be_used_in_a_check_constraint_or_view() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        // SQLite only tags a CHECK constraint's function calls as
+        // schema-sourced (enforcing DIRECTONLY) if they're also marked
+        // deterministic -- non-deterministic functions are curiously
+        // allowed in CHECK constraints regardless of DIRECTONLY, since
+        // a CHECK only ever runs against rows the same connection is
+        // writing. Views enforce DIRECTONLY either way.
+        unsafe {
+            create_scalar_function(
+                &conn,
+                "one",
+                0,
+                ffi::SQLITE_DIRECTONLY | ffi::SQLITE_DETERMINISTIC,
+                std::ptr::null_mut(),
+                constant_one,
+                None,
+            )
+            .unwrap();
+        }
+
+        // Top-level SQL is fine -- DIRECTONLY only restricts use from
+        // *inside* the schema.
+        assert_eq!(column_value(&conn, "SELECT one()"), Value::Integer(1));
+
+        let check_err = run(&conn, "CREATE TABLE t(a CHECK (one() = 1))").unwrap_err();
+        assert!(matches!(check_err, Error::Sqlite { .. }));
+        assert!(check_err.to_string().contains("unsafe use"));
+
+        run(&conn, "CREATE VIEW v AS SELECT one()").unwrap();
+        let view_err = run(&conn, "SELECT * FROM v").unwrap_err();
+        assert!(matches!(view_err, Error::Sqlite { .. }));
+        assert!(view_err.to_string().contains("unsafe use"));
+    }
+
+    #[test]
+    fn innocuous_function_works_under_trusted_schema_off() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.set_trusted_schema(false).unwrap();
+        unsafe {
+            create_scalar_function(&conn, "innocuous_one", 0, ffi::SQLITE_INNOCUOUS, std::ptr::null_mut(), constant_one, None)
+                .unwrap();
+            create_scalar_function(&conn, "plain_one", 0, 0, std::ptr::null_mut(), constant_one, None).unwrap();
+        }
+
+        // A view is schema SQL, so with trusted_schema off it must go
+        // through the INNOCUOUS check -- the plain (non-innocuous)
+        // function is rejected there even though it works fine called
+        // directly, while the INNOCUOUS one is allowed either way.
+        assert_eq!(column_value(&conn, "SELECT plain_one()"), Value::Integer(1));
+        run(&conn, "CREATE VIEW innocuous_v AS SELECT innocuous_one()").unwrap();
+        assert_eq!(column_value(&conn, "SELECT * FROM innocuous_v"), Value::Integer(1));
+        run(&conn, "CREATE VIEW plain_v AS SELECT plain_one()").unwrap();
+        let err = run(&conn, "SELECT * FROM plain_v").unwrap_err();
+        assert!(matches!(err, Error::Sqlite { .. }));
+    }
+
+    #[test]
+    fn deterministic_function_is_usable_in_an_index_expression() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe extern "C" fn double_it(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+            assert_eq!(argc, 1);
+            let n = unsafe { ffi::sqlite3_value_int64(*argv) };
+            unsafe { ffi::sqlite3_result_int64(ctx, n * 2) };
+        }
+        unsafe {
+            create_scalar_function(&conn, "nondeterministic_double", 1, 0, std::ptr::null_mut(), double_it, None)
+                .unwrap();
+            create_scalar_function(
+                &conn,
+                "double",
+                1,
+                ffi::SQLITE_DETERMINISTIC,
+                std::ptr::null_mut(),
+                double_it,
+                None,
+            )
+            .unwrap();
+        }
+        run(&conn, "CREATE TABLE t(a)").unwrap();
+
+        // A non-deterministic function is rejected in an index expression;
+        // the deterministic one is accepted.
+        let err = run(&conn, "CREATE INDEX idx_bad ON t(nondeterministic_double(a))").unwrap_err();
+        assert!(matches!(err, Error::Sqlite { .. }));
+        run(&conn, "CREATE INDEX idx_good ON t(double(a))").unwrap();
+    }
+
+    #[test]
+    fn value_subtype_is_zero_for_untagged_arguments() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe extern "C" fn report_subtype(
+            ctx: *mut ffi::sqlite3_context,
+            _argc: c_int,
+            argv: *mut *mut ffi::sqlite3_value,
+        ) {
+            let subtype = unsafe { value_subtype(*argv) };
+            unsafe { ffi::sqlite3_result_int64(ctx, subtype as i64) };
+        }
+        unsafe {
+            create_scalar_function(&conn, "subtype_of", 1, 0, std::ptr::null_mut(), report_subtype, None)
+                .unwrap();
+        }
+        assert_eq!(column_value(&conn, "SELECT subtype_of(123)"), Value::Integer(0));
+    }
+
+    #[test]
+    fn remove_function_drops_only_the_matching_overload() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe extern "C" fn constant_two(ctx: *mut ffi::sqlite3_context, _argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+            unsafe { ffi::sqlite3_result_int64(ctx, 2) };
+        }
+        unsafe {
+            create_scalar_function(&conn, "f", 0, 0, std::ptr::null_mut(), constant_one, None).unwrap();
+            create_scalar_function(&conn, "f", 1, 0, std::ptr::null_mut(), constant_two, None).unwrap();
+        }
+        assert_eq!(column_value(&conn, "SELECT f()"), Value::Integer(1));
+        assert_eq!(column_value(&conn, "SELECT f(0)"), Value::Integer(2));
+
+        remove_function(&conn, "f", 0).unwrap();
+
+        let err = run(&conn, "SELECT f()").unwrap_err();
+        assert!(matches!(err, Error::Sqlite { .. }));
+        assert_eq!(column_value(&conn, "SELECT f(0)"), Value::Integer(2));
+    }
+}
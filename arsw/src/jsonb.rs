@@ -0,0 +1,560 @@
+//! SQLite's binary JSON ("JSONB") format: detection, decoding, and encoding.
+//!
+//! See <https://sqlite.org/jsonb.html> for the on-disk format this module
+//! implements directly in Rust. Each element is a 1-to-9-byte header
+//! (a type nibble plus a payload-length descriptor, with the length itself
+//! stored big-endian out-of-line once it no longer fits in the descriptor)
+//! followed by that many payload bytes; arrays and objects nest further
+//! elements inside their own payload. This module has no dependency on
+//! SQLite's own JSON1 extension being linked in -- it reads and writes the
+//! format itself.
+
+use crate::error::{Error, Result};
+
+const TYPE_NULL: u8 = 0;
+const TYPE_TRUE: u8 = 1;
+const TYPE_FALSE: u8 = 2;
+const TYPE_INT: u8 = 3;
+const TYPE_INT5: u8 = 4;
+const TYPE_FLOAT: u8 = 5;
+const TYPE_FLOAT5: u8 = 6;
+const TYPE_TEXT: u8 = 7;
+const TYPE_TEXTJ: u8 = 8;
+const TYPE_TEXT5: u8 = 9;
+const TYPE_TEXTRAW: u8 = 10;
+const TYPE_ARRAY: u8 = 11;
+const TYPE_OBJECT: u8 = 12;
+
+/// A decoded JSON value.
+///
+/// Numbers are kept as the exact ASCII text JSONB stored them as, rather
+/// than normalized through a `f64`: JSONB preserves numeric literals
+/// verbatim (so `1` and `1.0` round-trip distinctly, and integers wider
+/// than 64 bits survive unchanged), and this type mirrors that.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    Text(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn from_i64(value: i64) -> Self {
+        JsonValue::Number(value.to_string())
+    }
+}
+
+/// How thoroughly [`is_jsonb`] should check `blob`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    /// An O(1) check of just the outermost element's header: a valid type
+    /// nibble and a payload-length claim that doesn't overrun the blob.
+    /// Cheap enough to run on every row, but a crafted or coincidental blob
+    /// can pass this while still being invalid further in -- see
+    /// [`Detection::Validate`].
+    Sniff,
+    /// Fully walks the structure via [`validate`]. Precise, but pays the
+    /// cost of visiting every element; best reserved for callers that
+    /// aren't on a per-row hot path, or that have already passed
+    /// [`Detection::Sniff`] and want a second, conclusive opinion before
+    /// doing expensive work on the result.
+    Validate,
+}
+
+/// `true` if `blob` is JSONB by the given [`Detection`] strictness.
+pub fn is_jsonb(blob: &[u8], detection: Detection) -> bool {
+    match detection {
+        Detection::Sniff => sniff(blob),
+        Detection::Validate => validate(blob).is_ok(),
+    }
+}
+
+/// The O(1) header sniff behind [`Detection::Sniff`].
+fn sniff(blob: &[u8]) -> bool {
+    match parse_header(blob, 0) {
+        Ok(header) => header.type_code <= TYPE_OBJECT && header.end <= blob.len(),
+        Err(_) => false,
+    }
+}
+
+/// Walk the entire structure, erroring with the byte offset of the first
+/// problem found. This runs a full [`decode`], so the [`JsonValue`] tree is
+/// built and then discarded.
+pub fn validate(blob: &[u8]) -> Result<()> {
+    decode(blob).map(|_| ())
+}
+
+/// Decode `blob` as a single top-level JSONB element.
+pub fn decode(blob: &[u8]) -> Result<JsonValue> {
+    let mut pos = 0;
+    let value = decode_element(blob, &mut pos)?;
+    if pos != blob.len() {
+        return Err(jsonb_err(pos, "trailing bytes after the top-level value"));
+    }
+    Ok(value)
+}
+
+struct Header {
+    type_code: u8,
+    payload_start: usize,
+    /// Byte offset one past the end of this element's payload.
+    end: usize,
+}
+
+/// Parse the header at `offset`, without validating the payload it
+/// describes.
+fn parse_header(blob: &[u8], offset: usize) -> Result<Header> {
+    let first = *blob
+        .get(offset)
+        .ok_or_else(|| jsonb_err(offset, "truncated header"))?;
+    let type_code = first & 0x0f;
+    let (header_len, payload_len): (usize, usize) = match first >> 4 {
+        size @ 0..=11 => (1, size as usize),
+        12 => (2, read_be::<1>(blob, offset + 1)? as usize),
+        13 => (3, read_be::<2>(blob, offset + 1)? as usize),
+        14 => (5, read_be::<4>(blob, offset + 1)? as usize),
+        15 => (9, read_be::<8>(blob, offset + 1)? as usize),
+        _ => unreachable!("a nibble is always 0..=15"),
+    };
+    let payload_start = offset + header_len;
+    let end = payload_start
+        .checked_add(payload_len)
+        .ok_or_else(|| jsonb_err(offset, "payload length overflows"))?;
+    Ok(Header { type_code, payload_start, end })
+}
+
+/// Read an `N`-byte big-endian unsigned integer starting at `offset`.
+fn read_be<const N: usize>(blob: &[u8], offset: usize) -> Result<u64> {
+    let bytes = blob
+        .get(offset..offset + N)
+        .ok_or_else(|| jsonb_err(offset, "truncated header"))?;
+    let mut value = 0u64;
+    for &b in bytes {
+        value = (value << 8) | b as u64;
+    }
+    Ok(value)
+}
+
+fn decode_element(blob: &[u8], pos: &mut usize) -> Result<JsonValue> {
+    let start = *pos;
+    let header = parse_header(blob, start)?;
+    if header.end > blob.len() {
+        return Err(jsonb_err(start, "payload overruns the blob"));
+    }
+    let payload = &blob[header.payload_start..header.end];
+    *pos = header.end;
+    match header.type_code {
+        TYPE_NULL => Ok(JsonValue::Null),
+        TYPE_TRUE => Ok(JsonValue::Bool(true)),
+        TYPE_FALSE => Ok(JsonValue::Bool(false)),
+        TYPE_INT | TYPE_INT5 | TYPE_FLOAT | TYPE_FLOAT5 => {
+            decode_number(payload, header.payload_start, header.type_code)
+        }
+        TYPE_TEXT | TYPE_TEXTRAW => decode_ascii(payload, header.payload_start, "text")
+            .map(str::to_string)
+            .map(JsonValue::Text),
+        TYPE_TEXTJ => unescape(payload, header.payload_start, false).map(JsonValue::Text),
+        TYPE_TEXT5 => unescape(payload, header.payload_start, true).map(JsonValue::Text),
+        TYPE_ARRAY => {
+            let mut items = Vec::new();
+            let mut p = header.payload_start;
+            while p < header.end {
+                items.push(decode_element(blob, &mut p)?);
+            }
+            if p != header.end {
+                return Err(jsonb_err(p, "child element overruns its container"));
+            }
+            Ok(JsonValue::Array(items))
+        }
+        TYPE_OBJECT => {
+            let mut pairs = Vec::new();
+            let mut p = header.payload_start;
+            while p < header.end {
+                let key_pos = p;
+                let key = match decode_element(blob, &mut p)? {
+                    JsonValue::Text(key) => key,
+                    _ => return Err(jsonb_err(key_pos, "object key is not a JSON string")),
+                };
+                if p >= header.end {
+                    return Err(jsonb_err(p, "object key has no matching value"));
+                }
+                pairs.push((key, decode_element(blob, &mut p)?));
+            }
+            if p != header.end {
+                return Err(jsonb_err(p, "child element overruns its container"));
+            }
+            Ok(JsonValue::Object(pairs))
+        }
+        other => Err(jsonb_err(start, format!("unknown JSONB element type {other}"))),
+    }
+}
+
+fn decode_ascii<'a>(payload: &'a [u8], offset: usize, what: &str) -> Result<&'a str> {
+    std::str::from_utf8(payload).map_err(|_| jsonb_err(offset, format!("{what} payload is not valid UTF-8")))
+}
+
+/// Validate and pass through an INT/INT5/FLOAT/FLOAT5 payload verbatim --
+/// JSONB stores numeric literals as their exact source text, so decoding a
+/// number is just confirming it's plausible, not converting it to an `f64`
+/// that would lose precision on wide integers.
+fn decode_number(payload: &[u8], offset: usize, type_code: u8) -> Result<JsonValue> {
+    let text = decode_ascii(payload, offset, "numeric")?;
+    let plausible = match type_code {
+        TYPE_INT | TYPE_FLOAT => {
+            !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E'))
+        }
+        // INT5/FLOAT5 additionally allow JSON5 literals: hex ints (0x1f),
+        // and the non-finite float keywords.
+        _ => {
+            !text.is_empty()
+                && (matches!(text, "NaN" | "Infinity" | "-Infinity")
+                    || text
+                        .bytes()
+                        .all(|b| b.is_ascii_hexdigit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E' | b'x' | b'X')))
+        }
+    };
+    if !plausible {
+        return Err(jsonb_err(offset, format!("not a valid numeric literal: {text:?}")));
+    }
+    Ok(JsonValue::Number(text.to_string()))
+}
+
+/// Unescape a TEXTJ (`json5 = false`) or TEXT5 (`json5 = true`) payload.
+/// TEXTJ supports the standard JSON backslash escapes; TEXT5 additionally
+/// allows JSON5's `\0`, `\v`, `\xXX`, `\'`, and escaped line continuations.
+fn unescape(payload: &[u8], offset: usize, json5: bool) -> Result<String> {
+    let text = decode_ascii(payload, offset, "escaped text")?;
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let (ei, esc) = chars
+            .next()
+            .ok_or_else(|| jsonb_err(offset + i, "dangling escape at end of text"))?;
+        match esc {
+            '"' => out.push('"'),
+            '\\' => out.push('\\'),
+            '/' => out.push('/'),
+            'b' => out.push('\u{8}'),
+            'f' => out.push('\u{c}'),
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            'u' => out.push(read_unicode_escape(&mut chars, offset + i)?),
+            '\'' if json5 => out.push('\''),
+            '0' if json5 => out.push('\0'),
+            'v' if json5 => out.push('\u{b}'),
+            'x' if json5 => out.push(read_hex_char(&mut chars, offset + i, 2)?),
+            '\n' if json5 => {} // escaped line continuation: produces nothing
+            '\r' if json5 => {
+                // `\r\n` is one continuation, not two escaped characters.
+                let mut rest = chars.clone();
+                if matches!(rest.next(), Some((_, '\n'))) {
+                    chars = rest;
+                }
+            }
+            other => return Err(jsonb_err(offset + ei, format!("unsupported escape \\{other}"))),
+        }
+    }
+    Ok(out)
+}
+
+fn read_unicode_escape(chars: &mut std::str::CharIndices<'_>, err_pos: usize) -> Result<char> {
+    let hi = read_hex_u32(chars, err_pos, 4)?;
+    if !(0xD800..=0xDBFF).contains(&hi) {
+        return char::from_u32(hi).ok_or_else(|| jsonb_err(err_pos, "invalid \\u escape"));
+    }
+    let mut rest = chars.clone();
+    if !matches!(rest.next(), Some((_, '\\'))) || !matches!(rest.next(), Some((_, 'u'))) {
+        return Err(jsonb_err(err_pos, "unpaired UTF-16 surrogate"));
+    }
+    *chars = rest;
+    let lo = read_hex_u32(chars, err_pos, 4)?;
+    if !(0xDC00..=0xDFFF).contains(&lo) {
+        return Err(jsonb_err(err_pos, "invalid low surrogate"));
+    }
+    let code = 0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00);
+    char::from_u32(code).ok_or_else(|| jsonb_err(err_pos, "invalid surrogate pair"))
+}
+
+fn read_hex_char(chars: &mut std::str::CharIndices<'_>, err_pos: usize, digits: u32) -> Result<char> {
+    let code = read_hex_u32(chars, err_pos, digits)?;
+    char::from_u32(code).ok_or_else(|| jsonb_err(err_pos, "invalid hex escape"))
+}
+
+fn read_hex_u32(chars: &mut std::str::CharIndices<'_>, err_pos: usize, digits: u32) -> Result<u32> {
+    let mut value = 0u32;
+    for _ in 0..digits {
+        let (_, c) = chars.next().ok_or_else(|| jsonb_err(err_pos, "truncated hex escape"))?;
+        let digit = c.to_digit(16).ok_or_else(|| jsonb_err(err_pos, "invalid hex digit in escape"))?;
+        value = value * 16 + digit;
+    }
+    Ok(value)
+}
+
+/// Options controlling [`encode`]'s output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncodeOptions {
+    /// Sort object keys (byte-wise) rather than preserving insertion order.
+    pub sorted_keys: bool,
+    /// Refuse to encode `NaN`/`Infinity`/`-Infinity` numbers instead of
+    /// emitting them as FLOAT5 literals (valid JSON5, but not plain JSON).
+    pub reject_non_finite: bool,
+}
+
+/// Encode `value` as a JSONB blob.
+pub fn encode(value: &JsonValue, options: EncodeOptions) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    encode_element(value, options, &mut out)?;
+    Ok(out)
+}
+
+fn encode_element(value: &JsonValue, options: EncodeOptions, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        JsonValue::Null => push_element(out, TYPE_NULL, &[]),
+        JsonValue::Bool(true) => push_element(out, TYPE_TRUE, &[]),
+        JsonValue::Bool(false) => push_element(out, TYPE_FALSE, &[]),
+        JsonValue::Number(text) => encode_number(text, options, out)?,
+        JsonValue::Text(text) => encode_text(text, out),
+        JsonValue::Array(items) => {
+            let mut payload = Vec::new();
+            for item in items {
+                encode_element(item, options, &mut payload)?;
+            }
+            push_element(out, TYPE_ARRAY, &payload);
+        }
+        JsonValue::Object(pairs) => {
+            let mut ordered: Vec<&(String, JsonValue)> = pairs.iter().collect();
+            if options.sorted_keys {
+                ordered.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            let mut payload = Vec::new();
+            for (key, value) in ordered {
+                encode_text(key, &mut payload);
+                encode_element(value, options, &mut payload)?;
+            }
+            push_element(out, TYPE_OBJECT, &payload);
+        }
+    }
+    Ok(())
+}
+
+fn encode_number(text: &str, options: EncodeOptions, out: &mut Vec<u8>) -> Result<()> {
+    if matches!(text, "NaN" | "Infinity" | "-Infinity") {
+        if options.reject_non_finite {
+            return Err(Error::Misuse("refusing to encode a non-finite number with reject_non_finite set"));
+        }
+        push_element(out, TYPE_FLOAT5, text.as_bytes());
+        return Ok(());
+    }
+    let is_int = !text.bytes().any(|b| matches!(b, b'.' | b'e' | b'E'));
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let is_canonical_int = is_int && digits.bytes().all(|b| b.is_ascii_digit()) && (digits == "0" || !digits.starts_with('0'));
+    let type_code = if is_canonical_int { TYPE_INT } else if is_int { TYPE_INT5 } else { TYPE_FLOAT };
+    push_element(out, type_code, text.as_bytes());
+    Ok(())
+}
+
+/// Encode a string as TEXT (no escaping needed) when every byte is already
+/// safe to embed literally, falling back to TEXTJ (standard JSON escapes)
+/// otherwise.
+fn encode_text(text: &str, out: &mut Vec<u8>) {
+    if text.bytes().all(|b| b >= 0x20 && b != b'"' && b != b'\\') {
+        push_element(out, TYPE_TEXT, text.as_bytes());
+        return;
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    push_element(out, TYPE_TEXTJ, escaped.as_bytes());
+}
+
+fn push_element(out: &mut Vec<u8>, type_code: u8, payload: &[u8]) {
+    let len = payload.len();
+    match len {
+        0..=11 => out.push(((len as u8) << 4) | type_code),
+        12..=0xff => {
+            out.push((12 << 4) | type_code);
+            out.push(len as u8);
+        }
+        0x100..=0xffff => {
+            out.push((13 << 4) | type_code);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push((14 << 4) | type_code);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+        _ => {
+            out.push((15 << 4) | type_code);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(payload);
+}
+
+fn jsonb_err(offset: usize, message: impl Into<String>) -> Error {
+    Error::Jsonb { offset, message: message.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::Connection;
+
+    fn real_jsonb(conn: &Connection, json: &str) -> Vec<u8> {
+        // A single-quoted SQL string literal, not Rust's `{:?}` Debug
+        // escaping: SQL doesn't process backslashes in string literals, so
+        // this passes `json`'s own `\n`-style JSON escapes through to
+        // `jsonb()` untouched, and only the single quotes need doubling.
+        let escaped = json.replace('\'', "''");
+        let mut stmt = conn.execute(&format!("SELECT jsonb('{escaped}')")).unwrap();
+        stmt.step().unwrap();
+        match stmt.column_value(0).unwrap() {
+            crate::value::Value::Blob(b) => b,
+            other => panic!("expected a blob, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decode_matches_sqlites_own_jsonb_output() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        let blob = real_jsonb(&conn, r#"{"a":1,"b":true,"c":null,"d":1.5,"e":[1,2,"hi\nthere"]}"#);
+        assert!(is_jsonb(&blob, Detection::Sniff));
+        assert!(is_jsonb(&blob, Detection::Validate));
+        assert_eq!(
+            decode(&blob).unwrap(),
+            JsonValue::Object(vec![
+                ("a".to_string(), JsonValue::from_i64(1)),
+                ("b".to_string(), JsonValue::Bool(true)),
+                ("c".to_string(), JsonValue::Null),
+                ("d".to_string(), JsonValue::Number("1.5".to_string())),
+                (
+                    "e".to_string(),
+                    JsonValue::Array(vec![
+                        JsonValue::from_i64(1),
+                        JsonValue::from_i64(2),
+                        JsonValue::Text("hi\nthere".to_string()),
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn decode_preserves_wide_integers_as_text() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let blob = real_jsonb(&conn, "9999999999999999999");
+        assert_eq!(decode(&blob).unwrap(), JsonValue::Number("9999999999999999999".to_string()));
+    }
+
+    #[test]
+    fn round_trips_deeply_nested_structures() {
+        let mut value = JsonValue::from_i64(0);
+        for i in 1..200 {
+            value = JsonValue::Array(vec![JsonValue::from_i64(i), value]);
+        }
+        let encoded = encode(&value, EncodeOptions::default()).unwrap();
+        assert!(is_jsonb(&encoded, Detection::Validate));
+        assert_eq!(decode(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn encode_sorts_keys_when_requested() {
+        let value = JsonValue::Object(vec![
+            ("z".to_string(), JsonValue::from_i64(1)),
+            ("a".to_string(), JsonValue::from_i64(2)),
+        ]);
+        let sorted = encode(&value, EncodeOptions { sorted_keys: true, ..Default::default() }).unwrap();
+        match decode(&sorted).unwrap() {
+            JsonValue::Object(pairs) => {
+                let keys: Vec<&str> = pairs.iter().map(|(k, _)| k.as_str()).collect();
+                assert_eq!(keys, vec!["a", "z"]);
+            }
+            other => panic!("expected an object, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn encode_rejects_non_finite_when_asked() {
+        let value = JsonValue::Number("NaN".to_string());
+        let result = encode(&value, EncodeOptions { reject_non_finite: true, ..Default::default() });
+        assert!(matches!(result, Err(Error::Misuse(_))));
+
+        let encoded = encode(&value, EncodeOptions::default()).unwrap();
+        assert_eq!(decode(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn corrupt_payload_reports_a_precise_byte_offset() {
+        // A well-formed ARRAY header whose declared payload claims a
+        // second element that doesn't actually fit.
+        let blob = vec![0x2b, 0x00, 0x15];
+        let err = match decode(&blob) {
+            Err(err) => err,
+            Ok(v) => panic!("expected a decode error, got {v:?}"),
+        };
+        assert!(matches!(err, Error::Jsonb { offset: 2, .. }));
+    }
+
+    #[test]
+    fn sniff_can_false_positive_where_validate_does_not() {
+        // Header claims a 2-byte ARRAY payload; the sniff only reads the
+        // outer header, so it doesn't notice the payload doesn't actually
+        // decode into two complete, well-formed elements -- this is
+        // exactly the gap a `convert_jsonb`-style cursor call site would
+        // need `Detection::Validate` (or its own recursive check) to close,
+        // though this crate has no such value-conversion pipeline yet.
+        let blob = vec![0x2b, 0x00, 0x15];
+        assert!(is_jsonb(&blob, Detection::Sniff));
+        assert!(!is_jsonb(&blob, Detection::Validate));
+    }
+
+    #[test]
+    fn a_child_element_overrunning_its_container_is_rejected() {
+        // Outer ARRAY declares a 2-byte payload (offsets 1..3), but its
+        // only child is a TEXT element whose own header declares a 3-byte
+        // payload, so the child actually spans offsets 1..5 -- comfortably
+        // inside the overall blob (len 5), just past its container's own
+        // declared boundary.
+        let blob = vec![0x2b, 0x37, b'a', b'b', b'c'];
+        assert!(is_jsonb(&blob, Detection::Sniff));
+        assert!(!is_jsonb(&blob, Detection::Validate));
+        let err = match decode(&blob) {
+            Err(err) => err,
+            Ok(v) => panic!("expected a decode error, got {v:?}"),
+        };
+        assert!(matches!(err, Error::Jsonb { offset: 5, .. }));
+    }
+
+    #[test]
+    fn truncated_header_reports_its_own_offset() {
+        let blob = vec![0xfb]; // ARRAY with a 9-byte header that never arrives
+        let err = match decode(&blob) {
+            Err(err) => err,
+            Ok(v) => panic!("expected a decode error, got {v:?}"),
+        };
+        assert!(matches!(err, Error::Jsonb { offset: 1, .. }));
+    }
+}
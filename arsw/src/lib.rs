@@ -0,0 +1,73 @@
+//! Safe Rust bindings to SQLite.
+//!
+//! `arsw` is the core crate backing the `arsw-py` extension module, which
+//! exposes an APSW-compatible API from Python. This crate has no Python
+//! dependency of its own: it is a standalone SQLite wrapper that `arsw-py`
+//! binds with `pyo3`.
+
+pub mod affinity;
+#[cfg(feature = "async")]
+pub mod async_connection;
+pub mod auto_extension;
+pub mod backup;
+pub mod blob;
+pub mod compile_options;
+pub mod connection;
+pub mod connection_builder;
+pub mod error;
+#[cfg(feature = "extra-functions")]
+pub mod extra_functions;
+pub mod ffi;
+pub mod fts5;
+pub mod function;
+pub mod jsonb;
+pub mod log;
+pub mod random;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+pub mod snapshot;
+pub mod statement;
+pub mod type_hooks;
+#[cfg(feature = "unlock_notify")]
+pub mod unlock_notify;
+pub mod util;
+pub mod value;
+pub mod vfs;
+pub mod window;
+
+pub use affinity::{affinity, Affinity};
+#[cfg(feature = "async")]
+pub use async_connection::AsyncConnection;
+pub use auto_extension::{register_auto_extension, reset_auto_extensions, AutoExtensionGuard};
+pub use backup::Backup;
+pub use blob::Blob;
+pub use connection::Connection;
+pub use connection_builder::{ConnectionBuilder, JournalMode};
+pub use error::{Error, Result};
+pub use random::{fill_randomness, randomness, seed_randomness};
+pub use snapshot::Snapshot;
+pub use statement::{Row, Statement, Statements};
+pub use type_hooks::TypeHooks;
+pub use value::Value;
+
+/// Test-only helpers shared across modules.
+///
+/// `sqlite3_config`/`sqlite3_initialize`'s process-global state (and our own
+/// [`log::INITIALIZED`](crate::log) mirror of it) is touched by tests in both
+/// `log` and `connection`, so they all serialize on one lock rather than
+/// racing each other when `cargo test` runs them in parallel threads.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::sync::{Mutex, MutexGuard};
+
+    static GLOBAL_SQLITE_STATE: Mutex<()> = Mutex::new(());
+
+    /// Acquire the shared lock, recovering from poisoning so that one
+    /// panicking test doesn't cascade-fail every other test that touches
+    /// global SQLite state.
+    pub(crate) fn lock_global_sqlite_state() -> MutexGuard<'static, ()> {
+        GLOBAL_SQLITE_STATE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
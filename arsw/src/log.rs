@@ -0,0 +1,157 @@
+//! Hooks into SQLite's global error log (`SQLITE_CONFIG_LOG`, `sqlite3_log`).
+
+use crate::error::{Error, Result};
+use crate::ffi;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Tracks whether SQLite has been initialized (implicitly, by opening a
+/// connection, or explicitly via [`initialize`]). `sqlite3_config` refuses
+/// most options -- `SQLITE_CONFIG_LOG` among them -- once that has happened.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// Mark SQLite as initialized. Called by connection setup once a real
+/// `sqlite3_open` happens; also callable directly.
+pub fn mark_initialized() {
+    INITIALIZED.store(true, Ordering::SeqCst);
+}
+
+/// `true` once SQLite has been initialized and config options like
+/// `SQLITE_CONFIG_LOG` can no longer be changed.
+pub fn is_initialized() -> bool {
+    INITIALIZED.load(Ordering::SeqCst)
+}
+
+/// Explicitly initialize SQLite via `sqlite3_initialize`.
+pub fn initialize() -> Result<()> {
+    let rc = unsafe { ffi::sqlite3_initialize() };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_initialize failed"));
+    }
+    mark_initialized();
+    Ok(())
+}
+
+/// Undo [`initialize`] via `sqlite3_shutdown`, allowing config options to be
+/// changed again. All open connections must already be closed.
+pub fn shutdown() -> Result<()> {
+    let rc = unsafe { ffi::sqlite3_shutdown() };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_shutdown failed"));
+    }
+    INITIALIZED.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+/// A `SQLITE_CONFIG_LOG` callback: error code plus formatted message.
+pub type LogCallback = unsafe extern "C" fn(ctx: *mut c_void, err_code: c_int, msg: *const c_char);
+
+/// Register `callback` with `sqlite3_config(SQLITE_CONFIG_LOG, ...)` so it
+/// receives every message SQLite logs internally, not just calls routed
+/// through [`log`]. `ctx` is passed back to `callback` unchanged.
+///
+/// Returns [`Error::Misuse`] if SQLite has already been initialized, per
+/// `SQLITE_CONFIG_LOG`'s "must be called before initialization" contract.
+///
+/// # Safety
+///
+/// `ctx` must remain valid for as long as `callback` may be invoked, i.e.
+/// until this function is called again (or with `callback: None`) and
+/// returns successfully.
+pub unsafe fn set_log_callback(callback: Option<LogCallback>, ctx: *mut c_void) -> Result<()> {
+    if is_initialized() {
+        return Err(Error::Misuse(
+            "cannot change SQLITE_CONFIG_LOG after sqlite3_initialize",
+        ));
+    }
+    let rc = unsafe { ffi::sqlite3_config(ffi::SQLITE_CONFIG_LOG, callback, ctx) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_config(SQLITE_CONFIG_LOG) failed"));
+    }
+    Ok(())
+}
+
+/// Set the default maximum size of an in-memory database created via
+/// `sqlite3_deserialize` (see [`crate::connection::Connection::open_from_memory`]),
+/// via `sqlite3_config(SQLITE_CONFIG_MEMDB_MAXSIZE, bytes)`. 1 GiB if never
+/// called.
+///
+/// Returns [`Error::Misuse`] if SQLite has already been initialized, per
+/// `sqlite3_config`'s general "must be called before initialization" rule.
+pub fn config_memdb_maxsize(bytes: i64) -> Result<()> {
+    if is_initialized() {
+        return Err(Error::Misuse(
+            "cannot change SQLITE_CONFIG_MEMDB_MAXSIZE after sqlite3_initialize",
+        ));
+    }
+    let rc = unsafe { ffi::sqlite3_config(ffi::SQLITE_CONFIG_MEMDB_MAXSIZE, bytes as ffi::sqlite3_int64) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_config(SQLITE_CONFIG_MEMDB_MAXSIZE) failed"));
+    }
+    Ok(())
+}
+
+/// Emit `message` through `sqlite3_log`, the same path SQLite's own
+/// internal warnings (automatic index creation, statement aborts, ...) use.
+pub fn log(err_code: i32, message: &str) -> Result<()> {
+    let message = CString::new(message)?;
+    unsafe {
+        ffi::sqlite3_log(err_code as c_int, c"%s".as_ptr(), message.as_ptr());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+    use std::sync::atomic::AtomicI32;
+
+    static RECEIVED_CODE: AtomicI32 = AtomicI32::new(0);
+    static RECEIVED_COUNT: AtomicI32 = AtomicI32::new(0);
+
+    unsafe extern "C" fn record(_ctx: *mut c_void, err_code: c_int, _msg: *const c_char) {
+        RECEIVED_CODE.store(err_code, Ordering::SeqCst);
+        RECEIVED_COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn register_receives_log_then_unregister_stops_delivery() {
+        let _guard = lock_global_sqlite_state();
+        // A connection opened by another test may have really initialized
+        // SQLite already, in which case `SQLITE_CONFIG_LOG` is legitimately
+        // off-limits until it's undone.
+        let _ = shutdown();
+        RECEIVED_COUNT.store(0, Ordering::SeqCst);
+        unsafe { set_log_callback(Some(record), std::ptr::null_mut()).unwrap() };
+
+        log(12345, "test message").unwrap();
+        assert_eq!(RECEIVED_CODE.load(Ordering::SeqCst), 12345);
+        assert_eq!(RECEIVED_COUNT.load(Ordering::SeqCst), 1);
+
+        unsafe { set_log_callback(None, std::ptr::null_mut()).unwrap() };
+        log(1, "should not be delivered").unwrap();
+        assert_eq!(RECEIVED_COUNT.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn config_memdb_maxsize_rejects_call_after_initialize() {
+        let _guard = lock_global_sqlite_state();
+        let _ = shutdown();
+        config_memdb_maxsize(1024).unwrap();
+        mark_initialized();
+        let result = config_memdb_maxsize(1024);
+        INITIALIZED.store(false, Ordering::SeqCst);
+        assert!(matches!(result, Err(Error::Misuse(_))));
+    }
+
+    #[test]
+    fn rejects_changing_log_handler_after_initialize() {
+        let _guard = lock_global_sqlite_state();
+        mark_initialized();
+        let result = unsafe { set_log_callback(Some(record), std::ptr::null_mut()) };
+        INITIALIZED.store(false, Ordering::SeqCst);
+        assert!(matches!(result, Err(Error::Misuse(_))));
+    }
+}
@@ -0,0 +1,70 @@
+//! Access to SQLite's own PRNG via `sqlite3_randomness`.
+
+use crate::ffi;
+use std::os::raw::c_int;
+
+/// Return `amount` bytes of randomness straight from `sqlite3_randomness`.
+///
+/// `sqlite3_randomness` takes its length as a C `int`, so requests larger
+/// than `c_int::MAX` are split into chunks rather than truncated or cast
+/// incorrectly. `amount == 0` returns an empty vector without calling into
+/// SQLite at all.
+pub fn randomness(amount: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; amount];
+    fill_randomness(&mut buf);
+    buf
+}
+
+/// Fill `buf` with bytes from `sqlite3_randomness`, chunking as needed so
+/// that the length passed to SQLite always fits in a C `int`.
+pub fn fill_randomness(buf: &mut [u8]) {
+    for chunk in buf.chunks_mut(c_int::MAX as usize) {
+        unsafe {
+            ffi::sqlite3_randomness(chunk.len() as c_int, chunk.as_mut_ptr().cast());
+        }
+    }
+}
+
+/// Reseed SQLite's PRNG via `SQLITE_TESTCTRL_PRNG_SEED`.
+///
+/// SQLite's seed hook takes a plain `int`; the provided bytes are folded
+/// into one via a little-endian read of up to the first four bytes (shorter
+/// inputs are zero-extended, longer ones are combined with a cheap rolling
+/// xor so every byte still influences the seed).
+pub fn seed_randomness(seed: &[u8]) {
+    let mut acc = 0i32;
+    for (i, &byte) in seed.iter().enumerate() {
+        let shift = (i % 4) * 8;
+        acc ^= (byte as i32) << shift;
+    }
+    unsafe {
+        ffi::sqlite3_test_control(ffi::SQLITE_TESTCTRL_PRNG_SEED, acc, std::ptr::null_mut::<ffi::sqlite3>());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_amount_is_empty() {
+        assert_eq!(randomness(0), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn returns_requested_length() {
+        assert_eq!(randomness(32).len(), 32);
+    }
+
+    #[test]
+    fn two_calls_differ() {
+        // Astronomically unlikely to collide for a 32-byte draw.
+        assert_ne!(randomness(32), randomness(32));
+    }
+
+    #[test]
+    fn large_amount_does_not_overflow_c_int() {
+        let buf = randomness(1024 * 1024);
+        assert_eq!(buf.len(), 1024 * 1024);
+    }
+}
@@ -0,0 +1,735 @@
+//! Optional `serde` integration for [`Value`] and rows.
+//!
+//! [`Value`] itself implements `Serialize`/`Deserialize` (`Null` as a serde
+//! unit, the other variants as their natural scalar). On top of that,
+//! [`from_row`] deserializes a plain struct from a [`Row`] by matching field
+//! names (post `#[serde(rename)]`) to column names, and
+//! [`params_from_serialize`] serializes a tuple or sequence into positional
+//! [`Value`] parameters. Gated behind the `serde` feature.
+
+use crate::error::Error;
+use crate::statement::Row;
+use crate::value::Value;
+use serde::de::value::StrDeserializer;
+use serde::de::{DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::ser::{Impossible, SerializeSeq, SerializeTuple};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Real(f) => serializer.serialize_f64(*f),
+            Value::Text(s) => serializer.serialize_str(s),
+            Value::Blob(b) => serializer.serialize_bytes(b),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a SQLite value (NULL, INTEGER, FLOAT, TEXT, or BLOB)")
+            }
+
+            fn visit_unit<E>(self) -> Result<Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_none<E>(self) -> Result<Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+                Deserialize::deserialize(deserializer)
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+                Ok(Value::Integer(v as i64))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+                Ok(Value::Integer(v))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+                Ok(Value::Integer(v as i64))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+                Ok(Value::Real(v))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+                Ok(Value::Text(v.to_owned()))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Value, E> {
+                Ok(Value::Text(v))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+                Ok(Value::Blob(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+                Ok(Value::Blob(v))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Deserializes `T` from `row`, matching struct fields to column names
+/// (post `#[serde(rename)]`). A column missing for a non-`Option`,
+/// non-defaulted field, or a value that doesn't match the field's type,
+/// produces an [`Error::Serde`] naming the offending field/column.
+pub fn from_row<T: DeserializeOwned>(row: &Row) -> crate::error::Result<T> {
+    T::deserialize(RowDeserializer { row })
+}
+
+/// Serializes `value` -- a tuple or a sequence (`Vec`/slice/array) of
+/// scalars -- into a positional parameter list, for binding into a
+/// statement with `?` placeholders.
+pub fn params_from_serialize<T: Serialize>(value: &T) -> crate::error::Result<Vec<Value>> {
+    value.serialize(ParamsSerializer)
+}
+
+struct RowDeserializer<'a> {
+    row: &'a Row,
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(RowMapAccess { row: self.row, index: 0 })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
+
+struct RowMapAccess<'a> {
+    row: &'a Row,
+    index: usize,
+}
+
+impl<'de> MapAccess<'de> for RowMapAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some(column) = self.row.columns().get(self.index) else {
+            return Ok(None);
+        };
+        let deserializer: StrDeserializer<'_, Error> = column.as_str().into_deserializer();
+        seed.deserialize(deserializer).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .row
+            .values()
+            .get(self.index)
+            .ok_or_else(|| Error::Serde(format!("row has no value for column at index {}", self.index)))?;
+        self.index += 1;
+        seed.deserialize(ValueDeserializer { value })
+    }
+}
+
+struct ValueDeserializer<'a> {
+    value: &'a Value,
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Real(f) => visitor.visit_f64(*f),
+            Value::Text(s) => visitor.visit_str(s),
+            Value::Blob(b) => visitor.visit_bytes(b),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Text(s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            other => Err(Error::Serde(format!("expected a TEXT value for an enum field, got {other:?}"))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Serializes only a top-level tuple or sequence into `Vec<Value>`; any
+/// other shape (a scalar, a map, a struct) is a caller error, since there's
+/// no column/field name to bind it by.
+struct ParamsSerializer;
+
+struct ParamsCollector {
+    values: Vec<Value>,
+}
+
+impl SerializeSeq for ParamsCollector {
+    type Ok = Vec<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Vec<Value>, Error> {
+        Ok(self.values)
+    }
+}
+
+impl SerializeTuple for ParamsCollector {
+    type Ok = Vec<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Vec<Value>, Error> {
+        Ok(self.values)
+    }
+}
+
+fn not_positional<T>() -> Result<T, Error> {
+    Err(Error::Serde(
+        "params_from_serialize expects a tuple or sequence, not a scalar/map/struct".to_string(),
+    ))
+}
+
+impl Serializer for ParamsSerializer {
+    type Ok = Vec<Value>;
+    type Error = Error;
+    type SerializeSeq = ParamsCollector;
+    type SerializeTuple = ParamsCollector;
+    type SerializeTupleStruct = Impossible<Vec<Value>, Error>;
+    type SerializeTupleVariant = Impossible<Vec<Value>, Error>;
+    type SerializeMap = Impossible<Vec<Value>, Error>;
+    type SerializeStruct = Impossible<Vec<Value>, Error>;
+    type SerializeStructVariant = Impossible<Vec<Value>, Error>;
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(ParamsCollector { values: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        Ok(ParamsCollector { values: Vec::with_capacity(len) })
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Error> {
+        not_positional()
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        let _ = len;
+        not_positional()
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        not_positional()
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        not_positional()
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Error> {
+        not_positional()
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        not_positional()
+    }
+}
+
+/// Serializes a single scalar field into a [`Value`] -- used for each
+/// element of a [`ParamsCollector`], and reachable from a struct field
+/// serialized through [`params_from_serialize`]'s element serializer (e.g.
+/// an inner `Option<i64>` or a unit-only enum stored as TEXT).
+struct ValueSerializer;
+
+struct BlobCollector {
+    bytes: Vec<u8>,
+}
+
+impl SerializeSeq for BlobCollector {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let byte = value.serialize(SingleByteSerializer)?;
+        self.bytes.push(byte);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Blob(self.bytes))
+    }
+}
+
+/// Accepts only `u8`, for [`BlobCollector`] -- a sequence field is assumed
+/// to be a `Vec<u8>`/`[u8]` BLOB, since that's the only sequence shape a
+/// single [`Value`] can represent.
+struct SingleByteSerializer;
+
+macro_rules! reject_non_u8 {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(self, _v: $ty) -> Result<Self::Ok, Error> {
+                Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+            }
+        )*
+    };
+}
+
+impl Serializer for SingleByteSerializer {
+    type Ok = u8;
+    type Error = Error;
+    type SerializeSeq = Impossible<u8, Error>;
+    type SerializeTuple = Impossible<u8, Error>;
+    type SerializeTupleStruct = Impossible<u8, Error>;
+    type SerializeTupleVariant = Impossible<u8, Error>;
+    type SerializeMap = Impossible<u8, Error>;
+    type SerializeStruct = Impossible<u8, Error>;
+    type SerializeStructVariant = Impossible<u8, Error>;
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+        Ok(v)
+    }
+
+    reject_non_u8!(
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64),
+        serialize_u16(u16), serialize_u32(u32), serialize_u64(u64), serialize_f32(f32), serialize_f64(f64),
+        serialize_char(char), serialize_str(&str), serialize_bytes(&[u8]),
+    );
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::Serde("expected a byte (u8) inside a BLOB sequence field".to_string()))
+    }
+}
+
+macro_rules! serialize_via_value {
+    ($($method:ident($ty:ty) => $variant:expr),* $(,)?) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Error> {
+                #[allow(clippy::redundant_closure_call)]
+                Ok(($variant)(v))
+            }
+        )*
+    };
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = BlobCollector;
+    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    serialize_via_value! {
+        serialize_bool(bool) => |v: bool| Value::Integer(v as i64),
+        serialize_i8(i8) => |v: i8| Value::Integer(v as i64),
+        serialize_i16(i16) => |v: i16| Value::Integer(v as i64),
+        serialize_i32(i32) => |v: i32| Value::Integer(v as i64),
+        serialize_i64(i64) => Value::Integer,
+        serialize_u8(u8) => |v: u8| Value::Integer(v as i64),
+        serialize_u16(u16) => |v: u16| Value::Integer(v as i64),
+        serialize_u32(u32) => |v: u32| Value::Integer(v as i64),
+        serialize_u64(u64) => |v: u64| Value::Integer(v as i64),
+        serialize_f32(f32) => |v: f32| Value::Real(v as f64),
+        serialize_f64(f64) => Value::Real,
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+        Ok(Value::Blob(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        Ok(Value::Text(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Error> {
+        Err(Error::Serde("cannot represent a newtype enum variant as a single SQLite value".to_string()))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(BlobCollector { bytes: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error::Serde("cannot represent a tuple field as a single SQLite value".to_string()))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::Serde("cannot represent a tuple struct field as a single SQLite value".to_string()))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::Serde("cannot represent a tuple enum variant as a single SQLite value".to_string()))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error::Serde("cannot represent a map field as a single SQLite value".to_string()))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Error> {
+        Err(Error::Serde("cannot represent a struct field as a single SQLite value".to_string()))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::Serde("cannot represent a struct enum variant as a single SQLite value".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_row, params_from_serialize};
+    use crate::connection::Connection;
+    use crate::error::Error;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::value::Value;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Status {
+        Active,
+        Retired,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Person {
+        id: i64,
+        #[serde(rename = "full_name")]
+        name: String,
+        nickname: Option<String>,
+        status: Status,
+    }
+
+    #[test]
+    fn struct_round_trips_through_insert_and_select() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE people(id, full_name, nickname, status)").unwrap().step().unwrap();
+
+        let params = params_from_serialize(&(1_i64, "Ada Lovelace", (), "Retired")).unwrap();
+        assert_eq!(
+            params,
+            vec![
+                Value::Integer(1),
+                Value::Text("Ada Lovelace".to_string()),
+                Value::Null,
+                Value::Text("Retired".to_string()),
+            ]
+        );
+
+        conn.execute("INSERT INTO people VALUES (1, 'Ada Lovelace', NULL, 'Retired')")
+            .unwrap()
+            .step()
+            .unwrap();
+
+        let mut stmt = conn.execute("SELECT id, full_name, nickname, status FROM people").unwrap();
+        assert!(stmt.step().unwrap());
+        let row = stmt.named_row().unwrap();
+        let person: Person = from_row(&row).unwrap();
+        assert_eq!(
+            person,
+            Person { id: 1, name: "Ada Lovelace".to_string(), nickname: None, status: Status::Retired }
+        );
+    }
+
+    #[test]
+    fn option_field_maps_a_non_null_column() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE people(id, full_name, nickname, status)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO people VALUES (2, 'Alan Turing', 'Prof', 'Active')")
+            .unwrap()
+            .step()
+            .unwrap();
+
+        let mut stmt = conn.execute("SELECT id, full_name, nickname, status FROM people").unwrap();
+        assert!(stmt.step().unwrap());
+        let person: Person = from_row(&stmt.named_row().unwrap()).unwrap();
+        assert_eq!(
+            person,
+            Person {
+                id: 2,
+                name: "Alan Turing".to_string(),
+                nickname: Some("Prof".to_string()),
+                status: Status::Active,
+            }
+        );
+    }
+
+    #[test]
+    fn missing_column_produces_a_clear_error() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let mut stmt = conn.execute("SELECT 1 AS id, 'x' AS full_name").unwrap();
+        assert!(stmt.step().unwrap());
+        let row = stmt.named_row().unwrap();
+
+        let err = from_row::<Person>(&row).unwrap_err();
+        let message = match err {
+            Error::Serde(message) => message,
+            other => panic!("expected Error::Serde, got {other:?}"),
+        };
+        assert!(message.contains("nickname") || message.contains("status"), "unexpected message: {message}");
+    }
+}
@@ -0,0 +1,1024 @@
+//! Prepared statements.
+
+use crate::affinity::{affinity, Affinity};
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use crate::value::Value;
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::os::raw::c_int;
+
+/// A prepared statement, borrowed from the [`Connection`] that created it.
+///
+/// Only the first SQL statement found in the source text is prepared; any
+/// trailing SQL is ignored, matching `sqlite3_prepare_v2`'s own behavior.
+/// To prepare every statement in a `;`-separated script, see
+/// [`Connection::prepare_all`].
+pub struct Statement<'conn> {
+    stmt: *mut ffi::sqlite3_stmt,
+    conn: &'conn Connection,
+    /// When the currently-running execution's first [`Self::step`] call
+    /// happened, for [`Connection::set_slow_query_threshold`]. `None`
+    /// between executions.
+    exec_started: std::cell::Cell<Option<std::time::Instant>>,
+    /// What's been bound to each parameter so far, indexed from 0, for
+    /// [`Connection::set_error_verbosity`]'s `param_summary`. SQLite has no
+    /// API to read a bound value back, so this is this crate's own record
+    /// of what the `bind_*` methods below were called with; it isn't
+    /// cleared by [`Self::reset`], matching `sqlite3_reset` itself leaving
+    /// bindings in place.
+    bound_params: std::cell::RefCell<Vec<BoundParam>>,
+    _not_send_across_drop: PhantomData<*const ()>,
+}
+
+/// One parameter's [`Statement::bound_params`] entry.
+#[derive(Clone)]
+enum BoundParam {
+    Unbound,
+    Value(Value),
+    Zeroblob(u64),
+    Pointer,
+}
+
+impl<'conn> Statement<'conn> {
+    pub(crate) fn prepare(conn: &'conn Connection, sql: &str) -> Result<Self> {
+        let csql = CString::new(sql)?;
+        let mut stmt: *mut ffi::sqlite3_stmt = std::ptr::null_mut();
+        let rc = unsafe {
+            ffi::sqlite3_prepare_v2(
+                conn.as_ptr(),
+                csql.as_ptr(),
+                -1,
+                &mut stmt,
+                std::ptr::null_mut(),
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(conn.last_error_with_statement_context("sqlite3_prepare_v2 failed", Some(sql.to_string()), None));
+        }
+        Ok(Statement::from_raw(stmt, conn))
+    }
+
+    pub(crate) fn from_raw(stmt: *mut ffi::sqlite3_stmt, conn: &'conn Connection) -> Self {
+        let param_count = unsafe { ffi::sqlite3_bind_parameter_count(stmt) };
+        Statement {
+            stmt,
+            conn,
+            exec_started: std::cell::Cell::new(None),
+            bound_params: std::cell::RefCell::new(vec![BoundParam::Unbound; param_count.max(0) as usize]),
+            _not_send_across_drop: PhantomData,
+        }
+    }
+
+    /// If an execution is being timed (see [`Self::exec_started`]), stop
+    /// timing it and report the elapsed time to
+    /// [`Connection::set_slow_query_threshold`], if one is installed and the
+    /// threshold was met. Called whenever an execution ends, one way or
+    /// another: exhaustion, an error, an early [`Self::reset`], or this
+    /// statement being dropped mid-execution.
+    fn finish_slow_query_timing(&self) {
+        if let Some(started) = self.exec_started.take() {
+            let sql = self.expanded_sql().unwrap_or_default();
+            self.conn.report_slow_query(&sql, started.elapsed());
+        }
+    }
+
+    /// Advance to the next result row. Returns `true` if a row is now
+    /// available (`SQLITE_ROW`), `false` once the statement is exhausted
+    /// (`SQLITE_DONE`).
+    ///
+    /// If this is the first `step` of a fresh execution (i.e. the statement
+    /// wasn't already mid-execution) and [`Connection::set_query_timeout`]
+    /// has configured one, this call arms a fresh deadline for the
+    /// execution that's about to run; `SQLITE_INTERRUPT` caused by that
+    /// deadline firing is reported with the elapsed time in its message.
+    ///
+    /// If [`Connection::set_unlock_notify_blocking`] has enabled it, a
+    /// `SQLITE_LOCKED`/`SQLITE_LOCKED_SHAREDCACHE` result blocks and retries
+    /// via [`crate::unlock_notify`] instead of being returned as an error.
+    pub fn step(&mut self) -> Result<bool> {
+        let started_idle = unsafe { ffi::sqlite3_stmt_busy(self.stmt) } == 0;
+        let deadline_start = if started_idle {
+            self.conn.arm_query_timeout();
+            let now = std::time::Instant::now();
+            self.exec_started.set(Some(now));
+            Some(now)
+        } else {
+            None
+        };
+        #[cfg_attr(not(feature = "unlock_notify"), allow(clippy::never_loop))]
+        loop {
+            let rc = unsafe { ffi::sqlite3_step(self.stmt) };
+            match rc {
+                ffi::SQLITE_ROW => return Ok(true),
+                ffi::SQLITE_DONE => {
+                    self.finish_slow_query_timing();
+                    return Ok(false);
+                }
+                ffi::SQLITE_INTERRUPT => {
+                    if let (Some(started), Some(timeout)) = (deadline_start, self.conn.query_timeout()) {
+                        if started.elapsed() >= timeout {
+                            self.finish_slow_query_timing();
+                            let message = format!("query timed out after {:?} (limit {timeout:?})", started.elapsed());
+                            return Err(Error::sqlite_with_statement_context(
+                                rc,
+                                message,
+                                self.sql(),
+                                self.param_summary(),
+                            ));
+                        }
+                    }
+                    self.finish_slow_query_timing();
+                    return Err(self.step_error("sqlite3_step failed"));
+                }
+                #[cfg(feature = "unlock_notify")]
+                ffi::SQLITE_LOCKED | ffi::SQLITE_LOCKED_SHAREDCACHE if self.conn.unlock_notify_blocking() => {
+                    crate::unlock_notify::wait_for_unlock(self.conn)?;
+                    unsafe { ffi::sqlite3_reset(self.stmt) };
+                }
+                _ => {
+                    self.finish_slow_query_timing();
+                    return Err(self.step_error("sqlite3_step failed"));
+                }
+            }
+        }
+    }
+
+    /// [`Self::step`], but overriding [`Connection::set_query_timeout`] for
+    /// just this one call -- the connection's own timeout (if any) is
+    /// restored once `step` returns.
+    pub fn step_timeout(&mut self, timeout: std::time::Duration) -> Result<bool> {
+        let previous = self.conn.query_timeout();
+        self.conn.set_query_timeout(Some(timeout));
+        let result = self.step();
+        self.conn.set_query_timeout(previous);
+        result
+    }
+
+    /// Reset this statement to its unstarted state, via `sqlite3_reset`, so
+    /// it can be stepped again (optionally after rebinding parameters or
+    /// [`Self::set_explain`]) without re-preparing it. If this statement was
+    /// mid-execution, its slow-query timer (see
+    /// [`Connection::set_slow_query_threshold`]) is stopped and reported
+    /// here, as if it had run to exhaustion.
+    pub fn reset(&mut self) -> Result<()> {
+        self.finish_slow_query_timing();
+        let rc = unsafe { ffi::sqlite3_reset(self.stmt) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.step_error("sqlite3_reset failed"));
+        }
+        Ok(())
+    }
+
+    /// Switch this already-prepared statement between normal execution
+    /// (`mode = 0`), `EXPLAIN` (`mode = 1`), and `EXPLAIN QUERY PLAN`
+    /// (`mode = 2`) output, via `sqlite3_stmt_explain`, without re-preparing
+    /// it. Changing the mode can require SQLite to reprepare the statement
+    /// internally, which it refuses to do mid-iteration (i.e. after
+    /// [`Self::step`] has returned a row but before the statement is
+    /// exhausted or [`Self::reset`]) -- SQLite doesn't document a specific
+    /// result code for that case (this crate has observed `SQLITE_BUSY`),
+    /// so callers should treat any [`Error::Sqlite`] here as "try
+    /// `reset()` first" rather than matching on a particular code.
+    pub fn set_explain(&mut self, mode: i32) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_stmt_explain(self.stmt, mode as c_int) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "sqlite3_stmt_explain failed"));
+        }
+        Ok(())
+    }
+
+    /// Number of columns in the result set.
+    pub fn column_count(&self) -> usize {
+        unsafe { ffi::sqlite3_column_count(self.stmt) as usize }
+    }
+
+    /// The name of column `i`, e.g. `"a"` for `SELECT a FROM t` (or the
+    /// expression text for computed columns, per `sqlite3_column_name`).
+    pub fn column_name(&self, i: usize) -> Option<String> {
+        let ptr = unsafe { ffi::sqlite3_column_name(self.stmt, i as c_int) };
+        c_str_to_string(ptr)
+    }
+
+    /// The declared type of column `i` from its `CREATE TABLE` statement,
+    /// e.g. `"INTEGER"`, or `None` for computed columns that have none.
+    pub fn column_decltype(&self, i: usize) -> Option<String> {
+        let ptr = unsafe { ffi::sqlite3_column_decltype(self.stmt, i as c_int) };
+        c_str_to_string(ptr)
+    }
+
+    /// This statement's original SQL text, as passed to
+    /// [`Connection::execute`](crate::connection::Connection::execute) --
+    /// unlike [`Self::expanded_sql`], parameter placeholders are left as-is
+    /// rather than substituted with bound values, via `sqlite3_sql`. Used
+    /// for [`Connection::set_error_verbosity`]'s `sql` context, so a prepare
+    /// or step failure's SQL doesn't leak bound values by itself regardless
+    /// of verbosity.
+    pub fn sql(&self) -> Option<String> {
+        let ptr = unsafe { ffi::sqlite3_sql(self.stmt) };
+        c_str_to_string(ptr)
+    }
+
+    /// This statement's SQL text with literals and bound parameter values
+    /// substituted in, via `sqlite3_expanded_sql`, or `None` if SQLite
+    /// couldn't allocate the expanded string.
+    pub fn expanded_sql(&self) -> Option<String> {
+        let ptr = unsafe { ffi::sqlite3_expanded_sql(self.stmt) };
+        let sql = c_str_to_string(ptr);
+        unsafe { ffi::sqlite3_free(ptr.cast()) };
+        sql
+    }
+
+    /// This statement's SQL text with literals, bound parameters, and
+    /// whitespace/comments normalized away, via `sqlite3_normalized_sql`,
+    /// for fingerprinting equivalent queries together. Always `None` unless
+    /// the linked SQLite was built with `SQLITE_ENABLE_NORMALIZE` (the
+    /// workspace's bundled build turns this on; see `arsw::ffi`).
+    pub fn normalized_sql(&self) -> Option<String> {
+        let ptr = unsafe { ffi::sqlite3_normalized_sql(self.stmt) };
+        c_str_to_string(ptr)
+    }
+
+    /// Per-loop query-plan counters gathered by SQLite's bytecode engine
+    /// while this statement ran, via `sqlite3_stmt_scanstatus_v2`. Empty
+    /// unless the linked SQLite was built with `SQLITE_ENABLE_STMT_SCANSTATUS`
+    /// (the workspace's bundled build turns this on; see the workspace
+    /// `.cargo/config.toml`) -- checked at runtime with [`crate::compile_options::compile_option_used`]
+    /// rather than assumed, since a caller could in principle link a
+    /// differently-built `libsqlite3`.
+    pub fn scan_status(&self) -> Vec<ScanStatus> {
+        if !crate::compile_options::compile_option_used("ENABLE_STMT_SCANSTATUS").unwrap_or(false) {
+            return Vec::new();
+        }
+        let mut loops = Vec::new();
+        let mut idx: c_int = 0;
+        while let Some(scan) = self.scan_status_at(idx) {
+            loops.push(scan);
+            idx += 1;
+        }
+        loops
+    }
+
+    fn scan_status_at(&self, idx: c_int) -> Option<ScanStatus> {
+        let mut nloop: i64 = 0;
+        let rc = unsafe {
+            ffi::sqlite3_stmt_scanstatus_v2(
+                self.stmt,
+                idx,
+                ffi::SQLITE_SCANSTAT_NLOOP,
+                0,
+                (&mut nloop as *mut i64).cast(),
+            )
+        };
+        if rc != 0 {
+            return None;
+        }
+        let mut nvisit: i64 = 0;
+        unsafe {
+            ffi::sqlite3_stmt_scanstatus_v2(
+                self.stmt,
+                idx,
+                ffi::SQLITE_SCANSTAT_NVISIT,
+                0,
+                (&mut nvisit as *mut i64).cast(),
+            )
+        };
+        let mut est: f64 = 0.0;
+        unsafe {
+            ffi::sqlite3_stmt_scanstatus_v2(self.stmt, idx, ffi::SQLITE_SCANSTAT_EST, 0, (&mut est as *mut f64).cast())
+        };
+        let mut name_ptr: *const std::os::raw::c_char = std::ptr::null();
+        unsafe {
+            ffi::sqlite3_stmt_scanstatus_v2(
+                self.stmt,
+                idx,
+                ffi::SQLITE_SCANSTAT_NAME,
+                0,
+                (&mut name_ptr as *mut *const std::os::raw::c_char).cast(),
+            )
+        };
+        let mut explain_ptr: *const std::os::raw::c_char = std::ptr::null();
+        unsafe {
+            ffi::sqlite3_stmt_scanstatus_v2(
+                self.stmt,
+                idx,
+                ffi::SQLITE_SCANSTAT_EXPLAIN,
+                0,
+                (&mut explain_ptr as *mut *const std::os::raw::c_char).cast(),
+            )
+        };
+        Some(ScanStatus {
+            nloop,
+            nvisit,
+            est,
+            name: c_str_to_string(name_ptr),
+            explain: c_str_to_string(explain_ptr),
+        })
+    }
+
+    /// The storage class SQLite currently reports for column `i`, via
+    /// `sqlite3_column_type`. Note this reflects the value actually stored,
+    /// not [`Self::column_decltype`]'s declared type -- SQLite is dynamically
+    /// typed.
+    pub fn column_type(&self, i: usize) -> ColumnType {
+        match unsafe { ffi::sqlite3_column_type(self.stmt, i as c_int) } {
+            ffi::SQLITE_NULL => ColumnType::Null,
+            ffi::SQLITE_INTEGER => ColumnType::Integer,
+            ffi::SQLITE_FLOAT => ColumnType::Float,
+            ffi::SQLITE_TEXT => ColumnType::Text,
+            ffi::SQLITE_BLOB => ColumnType::Blob,
+            other => unreachable!("sqlite3_column_type returned unknown type {other}"),
+        }
+    }
+
+    /// Column `i`'s type affinity, derived from its [`Self::column_decltype`]
+    /// via [`crate::affinity::affinity`]. A column with no declared type
+    /// (e.g. an expression result) gets [`Affinity::Blob`], per SQLite's own
+    /// rule for that case.
+    pub fn column_affinity(&self, i: usize) -> Affinity {
+        match self.column_decltype(i) {
+            Some(decltype) => affinity(&decltype),
+            None => Affinity::Blob,
+        }
+    }
+
+    /// The size in bytes of column `i`'s TEXT or BLOB value, via
+    /// `sqlite3_column_bytes`. `0` for NULL, INTEGER, and FLOAT columns.
+    pub fn column_bytes(&self, i: usize) -> usize {
+        unsafe { ffi::sqlite3_column_bytes(self.stmt, i as c_int) as usize }
+    }
+
+    /// The value of column `i` in the current row.
+    ///
+    /// `sqlite3_column_text`/`sqlite3_column_blob` return a NULL pointer both
+    /// for a genuine zero-length value (where `sqlite3_column_bytes` reports
+    /// `0` alongside it) and, per their own documentation, if an
+    /// out-of-memory error occurs while SQLite coerces the column to the
+    /// requested storage class -- since `column_type` above already told us
+    /// the column itself isn't NULL, a null pointer paired with a *nonzero*
+    /// declared length can only be that OOM case, which we surface as an
+    /// error rather than silently truncating to an empty value.
+    pub fn column_value(&self, i: usize) -> Result<Value> {
+        match self.column_type(i) {
+            ColumnType::Null => Ok(Value::Null),
+            ColumnType::Integer => Ok(Value::Integer(unsafe { ffi::sqlite3_column_int64(self.stmt, i as c_int) })),
+            ColumnType::Float => Ok(Value::Real(unsafe { ffi::sqlite3_column_double(self.stmt, i as c_int) })),
+            ColumnType::Text => {
+                let ptr = unsafe { ffi::sqlite3_column_text(self.stmt, i as c_int) };
+                let len = self.column_bytes(i);
+                if ptr.is_null() {
+                    if len != 0 {
+                        return Err(self.conn.last_error("sqlite3_column_text failed (out of memory)"));
+                    }
+                    return Ok(Value::Text(String::new()));
+                }
+                let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+                Ok(Value::Text(String::from_utf8_lossy(bytes).into_owned()))
+            }
+            ColumnType::Blob => {
+                let ptr = unsafe { ffi::sqlite3_column_blob(self.stmt, i as c_int) };
+                let len = self.column_bytes(i);
+                if ptr.is_null() {
+                    if len != 0 {
+                        return Err(self.conn.last_error("sqlite3_column_blob failed (out of memory)"));
+                    }
+                    return Ok(Value::Blob(Vec::new()));
+                }
+                let bytes = unsafe { std::slice::from_raw_parts(ptr.cast(), len) };
+                Ok(Value::Blob(bytes.to_vec()))
+            }
+        }
+    }
+
+    /// All column values in the current row, run through the connection's
+    /// [`TypeHooks`](crate::type_hooks::TypeHooks) (see
+    /// [`Connection::set_type_hooks`](crate::connection::Connection::set_type_hooks))
+    /// if any are registered. A column whose `column_decltype` has no
+    /// matching hook (including every column, when no hooks are registered
+    /// at all) is passed through unchanged.
+    pub fn row(&self) -> Result<Vec<Value>> {
+        let hooks = self.conn.type_hooks();
+        (0..self.column_count()).map(|i| self.typed_column_value(i, hooks.as_deref())).collect()
+    }
+
+    /// [`Self::row`] paired with each column's name, as a [`Row`] -- for
+    /// callers that want to look values up by name (e.g.
+    /// [`crate::serde_support::from_row`]) rather than position.
+    pub fn named_row(&self) -> Result<Row> {
+        let hooks = self.conn.type_hooks();
+        let columns = (0..self.column_count()).map(|i| self.column_name(i).unwrap_or_default()).collect();
+        let values = (0..self.column_count())
+            .map(|i| self.typed_column_value(i, hooks.as_deref()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Row { columns, values })
+    }
+
+    /// [`Self::column_value`] for column `i`, run through `hooks`'s
+    /// conversion for that column's `column_decltype` if one is registered.
+    fn typed_column_value(&self, i: usize, hooks: Option<&crate::type_hooks::TypeHooks>) -> Result<Value> {
+        let value = self.column_value(i)?;
+        let Some(hooks) = hooks else {
+            return Ok(value);
+        };
+        let Some(decltype) = self.column_decltype(i) else {
+            return Ok(value);
+        };
+        let Some(hook) = hooks.get(&decltype) else {
+            return Ok(value);
+        };
+        hook(value).map_err(|source| Error::TypeHook {
+            column: self.column_name(i).unwrap_or_default(),
+            source: Box::new(source),
+        })
+    }
+
+    /// Bind `value` to parameter `i` (1-based) as UTF-8 text, via
+    /// `sqlite3_bind_text` with `SQLITE_TRANSIENT` -- SQLite copies `value`
+    /// before this call returns, so it doesn't need to outlive it. `value`'s
+    /// byte length is passed explicitly rather than relying on a C-string
+    /// scan, so an embedded NUL byte binds and round-trips (via
+    /// [`Self::column_value`]) intact rather than being rejected or
+    /// truncated.
+    pub fn bind_text(&mut self, i: usize, value: &str) -> Result<()> {
+        // SQLITE_TRANSIENT, same trick as `crate::fts5`'s result-text helper.
+        let transient: unsafe extern "C" fn(*mut std::ffi::c_void) = unsafe { std::mem::transmute(-1isize) };
+        let rc = unsafe {
+            ffi::sqlite3_bind_text(self.stmt, i as c_int, value.as_ptr().cast(), value.len() as c_int, Some(transient))
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.conn.last_error("sqlite3_bind_text failed"));
+        }
+        self.record_bound_param(i, BoundParam::Value(Value::Text(value.to_string())));
+        Ok(())
+    }
+
+    /// Bind an `n`-byte zero-filled BLOB to parameter `i` (1-based), via
+    /// `sqlite3_bind_zeroblob64`, without allocating or copying `n` bytes of
+    /// zeroes -- SQLite reserves the space directly in the database page(s).
+    /// Meant to be filled in afterwards with [`crate::blob::Blob`]'s
+    /// incremental I/O.
+    pub fn bind_zeroblob64(&mut self, i: usize, n: u64) -> Result<()> {
+        let rc = unsafe { ffi::sqlite3_bind_zeroblob64(self.stmt, i as c_int, n) };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.conn.last_error("sqlite3_bind_zeroblob64 failed"));
+        }
+        self.record_bound_param(i, BoundParam::Zeroblob(n));
+        Ok(())
+    }
+
+    /// Bind an opaque, type-tagged pointer to parameter `i` (1-based), via
+    /// `sqlite3_bind_pointer`. `type_tag` must match the tag the receiving
+    /// function reads it back with via `sqlite3_value_pointer`, e.g.
+    /// `"fts5_api_ptr"` for [`crate::fts5`]'s `fts5_api` retrieval trick.
+    /// `destructor`, if given, runs once SQLite is done with `ptr` (when the
+    /// statement is finalized or the parameter is rebound);
+    /// otherwise the caller remains responsible for `ptr`'s lifetime.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for `destructor` (or, if `destructor` is `None`,
+    /// for every step of this statement) to receive it and must be safe for
+    /// whatever function reads it back via `sqlite3_value_pointer` with the
+    /// same `type_tag` to use as it sees fit.
+    pub unsafe fn bind_pointer(
+        &mut self,
+        i: usize,
+        ptr: *mut std::ffi::c_void,
+        type_tag: &CStr,
+        destructor: Option<unsafe extern "C" fn(*mut std::ffi::c_void)>,
+    ) -> Result<()> {
+        let rc = unsafe {
+            ffi::sqlite3_bind_pointer(self.stmt, i as c_int, ptr, type_tag.as_ptr(), destructor)
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(self.conn.last_error("sqlite3_bind_pointer failed"));
+        }
+        self.record_bound_param(i, BoundParam::Pointer);
+        Ok(())
+    }
+
+    /// Bind `value` to parameter `i` (1-based) as a type-tagged pointer,
+    /// transferring ownership to SQLite: `value` is dropped via a
+    /// destructor installed on the bind, once SQLite is done with it. The
+    /// safe counterpart of [`bind_pointer`](Self::bind_pointer) for the
+    /// common case of handing over a boxed Rust value (e.g. for
+    /// `carray`-style extension interop or `arsw-py`'s `apsw.pyobject`).
+    pub fn bind_rust_pointer<T: 'static>(&mut self, i: usize, value: Box<T>, type_tag: &CStr) -> Result<()> {
+        unsafe extern "C" fn drop_boxed<T>(ptr: *mut std::ffi::c_void) {
+            drop(unsafe { Box::from_raw(ptr.cast::<T>()) });
+        }
+        let ptr = Box::into_raw(value).cast::<std::ffi::c_void>();
+        unsafe { self.bind_pointer(i, ptr, type_tag, Some(drop_boxed::<T>)) }
+    }
+
+    /// [`Connection::last_error_with_statement_context`] for a
+    /// `sqlite3_step` failure, attaching this statement's original SQL
+    /// ([`Self::sql`]) and
+    /// [`Self::param_summary`].
+    fn step_error(&self, context: &str) -> Error {
+        self.conn.last_error_with_statement_context(context, self.sql(), self.param_summary())
+    }
+
+    /// Record what parameter `i` (1-based) was just bound to, for
+    /// [`Self::param_summary`]. Silently does nothing if `i` is out of
+    /// range -- SQLite itself would have already rejected the bind in that
+    /// case, so this is never reached with a bad index.
+    fn record_bound_param(&self, i: usize, param: BoundParam) {
+        if let Some(slot) = self.bound_params.borrow_mut().get_mut(i.wrapping_sub(1)) {
+            *slot = param;
+        }
+    }
+
+    /// A one-line summary of this statement's bound parameters -- e.g. `"2
+    /// params (Text(5), Unbound)"` -- for [`Connection::set_error_verbosity`]
+    /// to attach to a prepare/step failure. `None` if the statement takes no
+    /// parameters. Values only appear once verbosity is raised to
+    /// [`crate::connection::ErrorVerbosity::WithValues`]; otherwise each
+    /// parameter is described by type and length alone.
+    pub(crate) fn param_summary(&self) -> Option<String> {
+        let bound = self.bound_params.borrow();
+        if bound.is_empty() {
+            return None;
+        }
+        let with_values = self.conn.error_verbosity() == crate::connection::ErrorVerbosity::WithValues;
+        let described: Vec<String> = bound.iter().map(|param| param.describe(with_values)).collect();
+        Some(format!("{} params ({})", bound.len(), described.join(", ")))
+    }
+}
+
+impl BoundParam {
+    fn describe(&self, with_values: bool) -> String {
+        match self {
+            BoundParam::Unbound => "Unbound".to_string(),
+            BoundParam::Value(Value::Null) => "Null".to_string(),
+            BoundParam::Value(Value::Integer(n)) => {
+                if with_values {
+                    format!("Integer({n})")
+                } else {
+                    "Integer".to_string()
+                }
+            }
+            BoundParam::Value(Value::Real(n)) => {
+                if with_values {
+                    format!("Real({n})")
+                } else {
+                    "Real".to_string()
+                }
+            }
+            BoundParam::Value(Value::Text(s)) => {
+                if with_values {
+                    format!("Text({s:?})")
+                } else {
+                    format!("Text({})", s.len())
+                }
+            }
+            BoundParam::Value(Value::Blob(b)) => {
+                if with_values {
+                    format!("Blob({b:?})")
+                } else {
+                    format!("Blob({})", b.len())
+                }
+            }
+            BoundParam::Zeroblob(n) => format!("Zeroblob({n})"),
+            BoundParam::Pointer => "Pointer".to_string(),
+        }
+    }
+}
+
+impl Drop for Statement<'_> {
+    fn drop(&mut self) {
+        self.finish_slow_query_timing();
+        unsafe { ffi::sqlite3_finalize(self.stmt) };
+    }
+}
+
+fn c_str_to_string(ptr: *const std::os::raw::c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+    }
+}
+
+/// The storage class SQLite reports for a column's current value, as
+/// returned by [`Statement::column_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Null,
+    Integer,
+    Float,
+    Text,
+    Blob,
+}
+
+/// A single result row paired with its column names, as produced by
+/// [`Statement::named_row`]. Column order matches [`Statement::column_count`].
+#[derive(Debug, Clone)]
+pub struct Row {
+    columns: Vec<String>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// This row's column names, in column order.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// This row's values, in column order.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// The value of the column named `name`, or `None` if no column has
+    /// that name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.columns.iter().position(|c| c == name).map(|i| &self.values[i])
+    }
+}
+
+/// One query-plan loop's counters, as reported by
+/// [`Statement::scan_status`].
+#[derive(Debug, Clone)]
+pub struct ScanStatus {
+    /// Number of times this loop's body started running.
+    pub nloop: i64,
+    /// Number of rows this loop actually visited.
+    pub nvisit: i64,
+    /// The query planner's estimated average number of rows visited per
+    /// invocation of this loop.
+    pub est: f64,
+    /// The loop's `EXPLAIN QUERY PLAN` table/index name, if any.
+    pub name: Option<String>,
+    /// The loop's full `EXPLAIN QUERY PLAN` description, if any.
+    pub explain: Option<String>,
+}
+
+/// Prepares each `;`-separated statement in a script one at a time, as the
+/// iterator is advanced, via repeated `sqlite3_prepare_v2` calls that follow
+/// its `pzTail` output. See [`Connection::prepare_all`].
+pub struct Statements<'conn> {
+    conn: &'conn Connection,
+    sql: CString,
+    offset: usize,
+}
+
+impl<'conn> Statements<'conn> {
+    pub(crate) fn new(conn: &'conn Connection, sql: &str) -> Result<Self> {
+        Ok(Statements {
+            conn,
+            sql: CString::new(sql)?,
+            offset: 0,
+        })
+    }
+}
+
+impl<'conn> Iterator for Statements<'conn> {
+    type Item = Result<Statement<'conn>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let remaining = unsafe { self.sql.as_ptr().add(self.offset) };
+            if unsafe { *remaining == 0 } {
+                return None;
+            }
+            let mut stmt: *mut ffi::sqlite3_stmt = std::ptr::null_mut();
+            let mut tail: *const std::os::raw::c_char = std::ptr::null();
+            let rc = unsafe {
+                ffi::sqlite3_prepare_v2(self.conn.as_ptr(), remaining, -1, &mut stmt, &mut tail)
+            };
+            if rc != ffi::SQLITE_OK {
+                let sql = unsafe { CStr::from_ptr(remaining) }.to_string_lossy().into_owned();
+                return Some(Err(self.conn.last_error_with_statement_context(
+                    "sqlite3_prepare_v2 failed",
+                    Some(sql),
+                    None,
+                )));
+            }
+            self.offset = unsafe { tail.offset_from(self.sql.as_ptr()) } as usize;
+            if stmt.is_null() {
+                // Nothing left but whitespace/comments after the last
+                // statement; we're done.
+                continue;
+            }
+            return Some(Ok(Statement::from_raw(stmt, self.conn)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::Connection;
+    use crate::error::Error;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::type_hooks::TypeHooks;
+    use crate::value::Value;
+
+    #[test]
+    fn bind_zeroblob64_reserves_space_that_blob_open_can_then_fill_in() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a BLOB)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("INSERT INTO t VALUES (?)").unwrap();
+        stmt.bind_zeroblob64(1, 8).unwrap();
+        stmt.step().unwrap();
+        let rowid = conn.last_insert_rowid();
+
+        let mut check = conn.execute("SELECT length(a) FROM t").unwrap();
+        assert!(check.step().unwrap());
+        assert_eq!(check.column_value(0).unwrap(), Value::Integer(8));
+
+        let blob = crate::blob::Blob::open(&conn, "main", "t", "a", rowid, true).unwrap();
+        assert_eq!(blob.length(), 8);
+        blob.write_at(0, b"abcdefgh").unwrap();
+        let mut buf = [0u8; 8];
+        blob.read_at(0, &mut buf).unwrap();
+        assert_eq!(&buf, b"abcdefgh");
+    }
+
+    #[test]
+    fn zero_length_text_and_blob_round_trip_distinctly_from_null() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        let mut stmt = conn.execute("SELECT '', x'', NULL, NULL").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_value(0).unwrap(), Value::Text(String::new()));
+        assert_eq!(stmt.column_value(1).unwrap(), Value::Blob(Vec::new()));
+        assert_eq!(stmt.column_value(2).unwrap(), Value::Null);
+        assert_eq!(stmt.column_value(3).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn bind_text_round_trips_an_embedded_nul_byte_intact() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("INSERT INTO t VALUES (?)").unwrap();
+        stmt.bind_text(1, "a\0b").unwrap();
+        stmt.step().unwrap();
+
+        let mut check = conn.execute("SELECT a FROM t").unwrap();
+        assert!(check.step().unwrap());
+        assert_eq!(check.column_bytes(0), 3);
+        assert_eq!(check.column_value(0).unwrap(), Value::Text("a\0b".to_string()));
+    }
+
+    #[test]
+    fn column_type_reports_the_storage_class_of_each_column() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let mut stmt = conn.execute("SELECT NULL, 1, 1.5, 'x', x'cafe'").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_type(0), super::ColumnType::Null);
+        assert_eq!(stmt.column_type(1), super::ColumnType::Integer);
+        assert_eq!(stmt.column_type(2), super::ColumnType::Float);
+        assert_eq!(stmt.column_type(3), super::ColumnType::Text);
+        assert_eq!(stmt.column_type(4), super::ColumnType::Blob);
+    }
+
+    #[test]
+    fn column_affinity_reads_the_declared_type_and_falls_back_to_blob() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t (a INTEGER, b TEXT, c REAL, d, e)")
+            .unwrap()
+            .step()
+            .unwrap();
+        let stmt = conn.execute("SELECT a, b, c, d, e, 1 + 1 FROM t").unwrap();
+        assert_eq!(stmt.column_affinity(0), super::Affinity::Integer);
+        assert_eq!(stmt.column_affinity(1), super::Affinity::Text);
+        assert_eq!(stmt.column_affinity(2), super::Affinity::Real);
+        // `d`/`e` have no declared type at all, so SQLite's own rule 3 gives
+        // them BLOB affinity, same as an expression result like `1 + 1`.
+        assert_eq!(stmt.column_affinity(3), super::Affinity::Blob);
+        assert_eq!(stmt.column_affinity(4), super::Affinity::Blob);
+        assert_eq!(stmt.column_affinity(5), super::Affinity::Blob);
+    }
+
+    #[test]
+    fn column_bytes_reports_the_length_of_text_and_blob_columns() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let mut stmt = conn.execute("SELECT 'hello', x'cafe', NULL").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.column_bytes(0), 5);
+        assert_eq!(stmt.column_bytes(1), 2);
+        assert_eq!(stmt.column_bytes(2), 0);
+    }
+
+    #[test]
+    fn normalized_sql_ignores_literal_differences() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let a = conn.execute("SELECT 1 WHERE 'x' = 'y'").unwrap();
+        let b = conn.execute("SELECT 2 WHERE 'p' = 'q'").unwrap();
+        let (a, b) = (a.normalized_sql().unwrap(), b.normalized_sql().unwrap());
+        assert_eq!(a, b);
+        assert_ne!(a, "SELECT 1 WHERE 'x' = 'y'");
+    }
+
+    #[test]
+    fn expanded_sql_contains_the_original_literal() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let stmt = conn.execute("SELECT 42").unwrap();
+        assert!(stmt.expanded_sql().unwrap().contains("42"));
+    }
+
+    #[test]
+    fn scan_status_for_a_two_table_join_names_both_loops() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t1(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t1 VALUES (1), (2), (3)").unwrap().step().unwrap();
+        conn.execute("CREATE TABLE t2(b)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t2 VALUES (10), (20)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("SELECT * FROM t1, t2").unwrap();
+        let mut rows = 0;
+        while stmt.step().unwrap() {
+            rows += 1;
+        }
+        assert_eq!(rows, 6);
+
+        let scans = stmt.scan_status();
+        assert_eq!(scans.len(), 2);
+        let names: Vec<&str> = scans.iter().filter_map(|s| s.name.as_deref()).collect();
+        assert!(names.iter().any(|n| n.contains("t1")));
+        assert!(names.iter().any(|n| n.contains("t2")));
+
+        let visits: Vec<i64> = scans.iter().map(|s| s.nvisit).collect();
+        assert!(visits.contains(&3), "expected the outer loop to visit 3 rows: {visits:?}");
+        assert!(visits.contains(&6), "expected the inner loop to visit 6 rows: {visits:?}");
+    }
+
+    #[test]
+    fn set_explain_switches_an_already_prepared_statement_to_query_plan_mode() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1), (2)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("SELECT a FROM t").unwrap();
+        let mut rows = 0;
+        while stmt.step().unwrap() {
+            rows += 1;
+        }
+        assert_eq!(rows, 2);
+
+        stmt.reset().unwrap();
+        stmt.set_explain(2).unwrap();
+        let mut plan_rows = 0;
+        while stmt.step().unwrap() {
+            plan_rows += 1;
+        }
+        assert!(plan_rows > 0, "EXPLAIN QUERY PLAN should report at least one row for a table scan");
+    }
+
+    #[test]
+    fn set_explain_mid_iteration_is_rejected() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1), (2)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("SELECT a FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        let err = stmt.set_explain(1).unwrap_err();
+        assert!(matches!(err, Error::Sqlite { .. }));
+    }
+
+    #[test]
+    fn type_hooks_convert_a_matching_boolean_column() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(flag BOOLEAN)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (0), (1)").unwrap().step().unwrap();
+
+        let mut hooks = TypeHooks::new();
+        hooks.register(
+            "BOOLEAN",
+            Box::new(|value| match value {
+                Value::Integer(0) => Ok(Value::Integer(0)),
+                Value::Integer(_) => Ok(Value::Integer(1)),
+                other => Ok(other),
+            }),
+        );
+        conn.set_type_hooks(hooks);
+
+        let mut stmt = conn.execute("SELECT flag FROM t ORDER BY flag").unwrap();
+        let mut rows = Vec::new();
+        while stmt.step().unwrap() {
+            rows.push(stmt.row().unwrap());
+        }
+        assert_eq!(rows, vec![vec![Value::Integer(0)], vec![Value::Integer(1)]]);
+    }
+
+    #[test]
+    fn type_hooks_leave_an_unmatched_column_untouched() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a INTEGER)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (7)").unwrap().step().unwrap();
+
+        let mut hooks = TypeHooks::new();
+        hooks.register("BOOLEAN", Box::new(|_| unreachable!("no BOOLEAN column present")));
+        conn.set_type_hooks(hooks);
+
+        let mut stmt = conn.execute("SELECT a FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.row().unwrap(), vec![Value::Integer(7)]);
+    }
+
+    #[test]
+    fn type_hooks_error_surfaces_with_the_column_name() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(uuid UUID)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (x'00')").unwrap().step().unwrap();
+
+        let mut hooks = TypeHooks::new();
+        hooks.register(
+            "UUID",
+            Box::new(|_| {
+                Err(Error::Misuse("blob is not a valid UUID"))
+            }),
+        );
+        conn.set_type_hooks(hooks);
+
+        let mut stmt = conn.execute("SELECT uuid FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        let err = stmt.row().unwrap_err();
+        assert!(matches!(err, Error::TypeHook { ref column, .. } if column == "uuid"));
+    }
+
+    #[test]
+    fn type_hooks_add_no_overhead_when_none_are_registered() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a BOOLEAN)").unwrap().step().unwrap();
+        conn.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("SELECT a FROM t").unwrap();
+        assert!(stmt.step().unwrap());
+        assert_eq!(stmt.row().unwrap(), vec![Value::Integer(1)]);
+    }
+
+    #[test]
+    fn a_bad_prepare_reports_the_offending_sql() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+
+        let err = match conn.execute("SELEC 1") {
+            Ok(_) => panic!("expected prepare to fail"),
+            Err(err) => err,
+        };
+        assert!(matches!(err, Error::Sqlite { ref sql, .. } if sql.as_deref() == Some("SELEC 1")));
+        assert!(err.to_string().contains("SELEC 1"));
+    }
+
+    #[test]
+    fn a_bound_statement_that_fails_to_step_reports_a_param_summary() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a UNIQUE)").unwrap().step().unwrap();
+
+        let mut stmt = conn.execute("INSERT INTO t VALUES (?)").unwrap();
+        stmt.bind_text(1, "dup").unwrap();
+        stmt.step().unwrap();
+
+        let mut stmt = conn.execute("INSERT INTO t VALUES (?)").unwrap();
+        stmt.bind_text(1, "dup").unwrap();
+        let err = stmt.step().unwrap_err();
+        let Error::Sqlite { ref param_summary, .. } = err else {
+            panic!("expected Error::Sqlite, got {err:?}");
+        };
+        assert_eq!(param_summary.as_deref(), Some("1 params (Text(3))"));
+        assert!(!err.to_string().contains("dup"));
+    }
+
+    #[test]
+    fn error_verbosity_with_values_includes_the_actual_bound_text() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE t(a UNIQUE)").unwrap().step().unwrap();
+        conn.set_error_verbosity(crate::connection::ErrorVerbosity::WithValues);
+
+        let mut stmt = conn.execute("INSERT INTO t VALUES (?)").unwrap();
+        stmt.bind_text(1, "dup").unwrap();
+        stmt.step().unwrap();
+
+        let mut stmt = conn.execute("INSERT INTO t VALUES (?)").unwrap();
+        stmt.bind_text(1, "dup").unwrap();
+        let err = stmt.step().unwrap_err();
+        let Error::Sqlite { ref param_summary, .. } = err else {
+            panic!("expected Error::Sqlite, got {err:?}");
+        };
+        assert_eq!(param_summary.as_deref(), Some(r#"1 params (Text("dup"))"#));
+        assert!(err.to_string().contains("dup"));
+    }
+}
@@ -0,0 +1,50 @@
+//! Opt-in declared-type-aware value conversion for [`Statement::row`](crate::statement::Statement::row).
+//!
+//! By default `arsw` hands back columns as the untyped [`Value`] SQLite
+//! itself produced (SQLite's own type affinity notwithstanding). Some
+//! applications want more: a `BOOLEAN` column's `0`/`1` coming back as
+//! something boolean-shaped, a `DATETIME` column parsed, a `UUID` column's
+//! blob turned into a formatted string. [`TypeHooks`] lets a connection opt
+//! into that without the core crate committing to any particular date/UUID
+//! library -- callers register their own conversion for the decltypes they
+//! care about, keyed on the column's declared type from `CREATE TABLE`.
+
+use crate::error::Result;
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// A conversion applied to a column's raw [`Value`] when its declared type
+/// matches the decltype it was registered under.
+pub type TypeHook = Box<dyn Fn(Value) -> Result<Value>>;
+
+/// A set of decltype-keyed conversions, installed on a [`Connection`](crate::connection::Connection)
+/// via [`Connection::set_type_hooks`](crate::connection::Connection::set_type_hooks).
+///
+/// Decltypes are matched case-insensitively against the whole declared type:
+/// `"DATE"` and `"date"` both match a hook registered under `"DATE"`, but a
+/// column declared `"VARCHAR(10)"` does not match a hook registered under
+/// `"VARCHAR"` (SQLite's type affinity rules already fold arbitrary type
+/// names down to a handful of affinities if that's what a caller wants;
+/// this is a literal decltype match, not an affinity match).
+#[derive(Default)]
+pub struct TypeHooks {
+    hooks: HashMap<String, TypeHook>,
+}
+
+impl TypeHooks {
+    /// An empty set of hooks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `hook` to run on every column whose `column_decltype` is
+    /// `decltype`, replacing any hook already registered for it.
+    pub fn register(&mut self, decltype: &str, hook: TypeHook) {
+        self.hooks.insert(decltype.to_ascii_uppercase(), hook);
+    }
+
+    /// The hook registered for `decltype`, if any.
+    pub(crate) fn get(&self, decltype: &str) -> Option<&TypeHook> {
+        self.hooks.get(&decltype.to_ascii_uppercase())
+    }
+}
@@ -0,0 +1,211 @@
+//! Blocking recovery from `SQLITE_LOCKED`/`SQLITE_LOCKED_SHAREDCACHE`, via
+//! `sqlite3_unlock_notify`. See [`Connection::set_unlock_notify_blocking`].
+//!
+//! Requires the linked SQLite to have been built with
+//! `SQLITE_ENABLE_UNLOCK_NOTIFY` -- without it `sqlite3_unlock_notify` is
+//! declared but not defined, so calling it is a link error rather than a
+//! runtime one (see the top-level `.cargo/config.toml`).
+
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use std::os::raw::{c_int, c_void};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Shared between [`wait_for_unlock`] and the `sqlite3_unlock_notify`
+/// callback SQLite invokes once the blocking connection releases its lock.
+/// `Arc`-owned: `wait_for_unlock` holds one clone while it waits, and hands
+/// SQLite a second clone's pointer (via `Arc::into_raw`) to read back in the
+/// callback, so the notification outlives whichever of the two finishes
+/// last.
+struct UnlockNotification {
+    fired: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl UnlockNotification {
+    fn new() -> Self {
+        UnlockNotification {
+            fired: Mutex::new(false),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn fire(&self) {
+        *self.fired.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        self.condvar.notify_all();
+    }
+
+    fn wait(&self) {
+        let mut fired = self.fired.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        while !*fired {
+            fired = self.condvar.wait(fired).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+/// `sqlite3_unlock_notify`'s callback: invoked with every context pointer
+/// passed to a `sqlite3_unlock_notify` call that's now unblocked (SQLite
+/// coalesces multiple waiters on the same lock into one call), so each entry
+/// in `ap_arg` is one of our own `UnlockNotification` pointers.
+unsafe extern "C" fn unlock_notify_callback(ap_arg: *mut *mut c_void, n_arg: c_int) {
+    let contexts = unsafe { std::slice::from_raw_parts(ap_arg, n_arg as usize) };
+    for &ctx in contexts {
+        // Balances the `Arc::into_raw` in `wait_for_unlock`; `notification`
+        // below still holds its own clone, so this doesn't free the value.
+        let notification = unsafe { Arc::from_raw(ctx.cast::<UnlockNotification>()) };
+        notification.fire();
+    }
+}
+
+/// Block until whatever connection is holding the lock behind `conn`'s most
+/// recent `SQLITE_LOCKED`/`SQLITE_LOCKED_SHAREDCACHE` releases it, via
+/// `sqlite3_unlock_notify`. If SQLite detects that waiting would deadlock
+/// (two connections each waiting on a lock the other holds),
+/// `sqlite3_unlock_notify` itself returns `SQLITE_LOCKED` instead of
+/// registering a callback; this surfaces that immediately as an error
+/// rather than blocking forever.
+pub(crate) fn wait_for_unlock(conn: &Connection) -> Result<()> {
+    let notification = Arc::new(UnlockNotification::new());
+    let ctx = Arc::into_raw(notification.clone()).cast::<c_void>().cast_mut();
+    let rc = unsafe { ffi::sqlite3_unlock_notify(conn.as_ptr(), Some(unlock_notify_callback), ctx) };
+    if rc == ffi::SQLITE_LOCKED {
+        // No callback was registered, so the callback will never run to
+        // reclaim our extra ref -- drop it ourselves.
+        drop(unsafe { Arc::from_raw(ctx.cast::<UnlockNotification>()) });
+        return Err(Error::Sqlite {
+            code: rc,
+            message: "sqlite3_unlock_notify: waiting would deadlock".to_string(),
+            sql: None,
+            param_summary: None,
+        });
+    }
+    if rc != ffi::SQLITE_OK {
+        return Err(conn.last_error("sqlite3_unlock_notify failed"));
+    }
+    notification.wait();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::ffi;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::Connection;
+    use std::sync::mpsc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// A path in the system temp dir that's unique to this test run --
+    /// shared-cache locking (unlike `SQLITE_BUSY`) only kicks in between
+    /// connections to the same actual database file, not two independent
+    /// `:memory:` connections.
+    fn temp_db_path(name: &str) -> std::path::PathBuf {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        std::env::temp_dir().join(format!("arsw_unlock_notify_test_{name}_{}_{nanos}.db", std::process::id()))
+    }
+
+    #[test]
+    fn blocking_step_waits_for_the_writer_and_then_sees_its_row() {
+        let _guard = lock_global_sqlite_state();
+        unsafe { ffi::sqlite3_enable_shared_cache(1) };
+        let path = temp_db_path("blocks_then_succeeds");
+
+        let writer = Connection::open(path.to_str().unwrap()).unwrap();
+        writer.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        writer.execute("BEGIN IMMEDIATE").unwrap().step().unwrap();
+        writer.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        let (ready_tx, ready_rx) = mpsc::channel::<()>();
+        let reader_path = path.clone();
+        let handle = std::thread::spawn(move || {
+            let reader = Connection::open(reader_path.to_str().unwrap()).unwrap();
+            reader.set_unlock_notify_blocking(true);
+            ready_tx.send(()).unwrap();
+            let mut stmt = reader.execute("SELECT a FROM t").unwrap();
+            assert!(stmt.step().unwrap());
+            stmt.column_value(0).unwrap()
+        });
+
+        ready_rx.recv().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        writer.execute("COMMIT").unwrap().step().unwrap();
+
+        assert_eq!(handle.join().unwrap(), crate::value::Value::Integer(1));
+
+        unsafe { ffi::sqlite3_enable_shared_cache(0) };
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn non_blocking_step_reports_locked_immediately() {
+        let _guard = lock_global_sqlite_state();
+        unsafe { ffi::sqlite3_enable_shared_cache(1) };
+        let path = temp_db_path("errors_without_the_toggle");
+
+        let writer = Connection::open(path.to_str().unwrap()).unwrap();
+        writer.execute("CREATE TABLE t(a)").unwrap().step().unwrap();
+        writer.execute("BEGIN IMMEDIATE").unwrap().step().unwrap();
+        writer.execute("INSERT INTO t VALUES (1)").unwrap().step().unwrap();
+
+        let reader = Connection::open(path.to_str().unwrap()).unwrap();
+        let mut stmt = reader.execute("SELECT a FROM t").unwrap();
+        let err = stmt.step().unwrap_err();
+        assert!(matches!(err, Error::Sqlite { code, .. } if code == ffi::SQLITE_LOCKED || code == ffi::SQLITE_LOCKED_SHAREDCACHE));
+
+        writer.execute("COMMIT").unwrap().step().unwrap();
+        unsafe { ffi::sqlite3_enable_shared_cache(0) };
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn a_lock_cycle_between_two_blocking_connections_errors_instead_of_hanging() {
+        let _guard = lock_global_sqlite_state();
+        unsafe { ffi::sqlite3_enable_shared_cache(1) };
+        let path = temp_db_path("deadlock");
+
+        let setup = Connection::open(path.to_str().unwrap()).unwrap();
+        setup.execute("CREATE TABLE t1(a)").unwrap().step().unwrap();
+        setup.execute("CREATE TABLE t2(a)").unwrap().step().unwrap();
+        setup.execute("INSERT INTO t1 VALUES (1)").unwrap().step().unwrap();
+        setup.execute("INSERT INTO t2 VALUES (1)").unwrap().step().unwrap();
+        drop(setup);
+
+        let conn_a = Connection::open(path.to_str().unwrap()).unwrap();
+        conn_a.set_unlock_notify_blocking(true);
+        let conn_b = Connection::open(path.to_str().unwrap()).unwrap();
+        conn_b.set_unlock_notify_blocking(true);
+
+        // Each connection takes a plain read lock on its own table --
+        // compatible with each other, so both succeed.
+        conn_a.execute("BEGIN").unwrap().step().unwrap();
+        conn_a.execute("SELECT a FROM t1").unwrap().step().unwrap();
+        conn_b.execute("BEGIN").unwrap().step().unwrap();
+        conn_b.execute("SELECT a FROM t2").unwrap().step().unwrap();
+
+        // Now each tries to upgrade to a write lock on the *other's*
+        // read-locked table: `a` waits on `b`'s read lock on t2 first, and
+        // once it's registered with sqlite3_unlock_notify, `b` trying to
+        // write t1 (read-locked by `a`) closes the cycle -- SQLite reports
+        // the deadlock to `b` (the second caller) immediately instead of
+        // also blocking it.
+        let (a_waiting_tx, a_waiting_rx) = mpsc::channel::<()>();
+        let handle = std::thread::spawn(move || {
+            a_waiting_tx.send(()).unwrap();
+            conn_a.execute("UPDATE t2 SET a = a").unwrap().step()
+        });
+
+        a_waiting_rx.recv().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let b_result = conn_b.execute("UPDATE t1 SET a = a").unwrap().step();
+        assert!(matches!(b_result, Err(Error::Sqlite { code, .. }) if code == ffi::SQLITE_LOCKED));
+
+        // Release `b`'s read lock on t2 so `a`'s blocked wait finally fires
+        // and its write goes through.
+        conn_b.execute("ROLLBACK").unwrap().step().unwrap();
+        assert!(!handle.join().unwrap().unwrap());
+
+        unsafe { ffi::sqlite3_enable_shared_cache(0) };
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,131 @@
+//! String helpers that defer to SQLite's own implementations.
+//!
+//! `sqlite3_strglob`/`sqlite3_strlike` are the functions backing the GLOB and
+//! LIKE operators; `sqlite3_stricmp`/`sqlite3_strnicmp` are SQLite's ASCII
+//! case-insensitive `strcmp`/`strncmp`. Using them directly guarantees exact
+//! parity with SQLite's own operators (character classes, ESCAPE handling)
+//! and SQLite's iterative matcher avoids the exponential blowup a naive
+//! recursive GLOB/LIKE matcher has on patterns like `a*a*a*a*...`.
+
+use crate::error::Result;
+use crate::ffi;
+use std::ffi::CString;
+use std::os::raw::c_uint;
+
+/// `true` if `text` matches the GLOB `pattern`, per `sqlite3_strglob`.
+pub fn strglob(pattern: &str, text: &str) -> Result<bool> {
+    let pattern = CString::new(pattern)?;
+    let text = CString::new(text)?;
+    let rc = unsafe { ffi::sqlite3_strglob(pattern.as_ptr(), text.as_ptr()) };
+    Ok(rc == 0)
+}
+
+/// `true` if `text` matches the LIKE `pattern`, per `sqlite3_strlike`.
+///
+/// `escape` is the LIKE ESCAPE character, if any.
+pub fn strlike(pattern: &str, text: &str, escape: Option<char>) -> Result<bool> {
+    let pattern = CString::new(pattern)?;
+    let text = CString::new(text)?;
+    let esc = escape.map_or(0, |c| c as c_uint);
+    let rc = unsafe { ffi::sqlite3_strlike(pattern.as_ptr(), text.as_ptr(), esc) };
+    Ok(rc == 0)
+}
+
+/// ASCII case-insensitive comparison, per `sqlite3_stricmp`.
+///
+/// Only ASCII letters are case-folded; non-ASCII bytes compare byte-for-byte,
+/// matching SQLite's own behavior.
+pub fn stricmp(a: &str, b: &str) -> Result<std::cmp::Ordering> {
+    let a = CString::new(a)?;
+    let b = CString::new(b)?;
+    let rc = unsafe { ffi::sqlite3_stricmp(a.as_ptr(), b.as_ptr()) };
+    Ok(rc.cmp(&0))
+}
+
+/// Like [`stricmp`], but compares at most `n` bytes, per `sqlite3_strnicmp`.
+pub fn strnicmp(a: &str, b: &str, n: usize) -> Result<std::cmp::Ordering> {
+    let a = CString::new(a)?;
+    let b = CString::new(b)?;
+    let rc = unsafe { ffi::sqlite3_strnicmp(a.as_ptr(), b.as_ptr(), n as std::os::raw::c_int) };
+    Ok(rc.cmp(&0))
+}
+
+/// `true` if `sql` ends with a complete SQL statement -- trailing whitespace
+/// and comments after the final `;` don't count against it -- per
+/// `sqlite3_complete`. The empty string is never complete.
+pub fn is_complete(sql: &str) -> Result<bool> {
+    let sql = CString::new(sql)?;
+    let rc = unsafe { ffi::sqlite3_complete(sql.as_ptr()) };
+    Ok(rc != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn glob_character_classes() {
+        assert!(strglob("[a-z]at", "cat").unwrap());
+        assert!(!strglob("[a-z]at", "Cat").unwrap());
+        assert!(strglob("[^0-9]*", "cat").unwrap());
+    }
+
+    #[test]
+    fn like_with_escape() {
+        // `\%` is a literal percent sign, not a wildcard.
+        assert!(strlike("50\\%", "50%", Some('\\')).unwrap());
+        assert!(!strlike("50\\%", "50off", Some('\\')).unwrap());
+        // Without the escape, `%` still means "any suffix".
+        assert!(strlike("50%", "50off", Some('\\')).unwrap());
+    }
+
+    #[test]
+    fn pathological_glob_pattern_finishes_instantly() {
+        let pattern = "a*".repeat(40) + "b";
+        assert!(!strglob(&pattern, &"a".repeat(1000)).unwrap());
+    }
+
+    #[test]
+    fn stricmp_ascii_case_insensitive() {
+        assert_eq!(stricmp("Hello", "HELLO").unwrap(), Ordering::Equal);
+        assert_eq!(stricmp("abc", "abd").unwrap(), Ordering::Less);
+    }
+
+    #[test]
+    fn stricmp_non_ascii_is_byte_for_byte() {
+        // SQLite's ASCII-only fold leaves high-bit bytes untouched.
+        assert_eq!(stricmp("\u{e9}", "\u{c9}").unwrap(), "\u{e9}".cmp("\u{c9}"));
+    }
+
+    #[test]
+    fn embedded_nul_is_rejected() {
+        assert!(strglob("a\0b", "a").is_err());
+    }
+
+    #[test]
+    fn is_complete_rejects_the_empty_string() {
+        assert!(!is_complete("").unwrap());
+    }
+
+    #[test]
+    fn is_complete_is_false_mid_string_literal() {
+        assert!(!is_complete("SELECT 'unterminated;").unwrap());
+    }
+
+    #[test]
+    fn is_complete_is_false_mid_block_comment() {
+        assert!(!is_complete("SELECT 1; /* still open").unwrap());
+    }
+
+    #[test]
+    fn is_complete_handles_a_trigger_body_with_embedded_semicolons() {
+        let sql = "CREATE TRIGGER t AFTER INSERT ON a BEGIN SELECT 1; SELECT 2; END;";
+        assert!(is_complete(sql).unwrap());
+    }
+
+    #[test]
+    fn is_complete_true_with_trailing_whitespace_and_comments() {
+        assert!(is_complete("SELECT 1; -- trailing comment\n").unwrap());
+    }
+}
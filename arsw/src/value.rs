@@ -0,0 +1,53 @@
+//! SQLite's dynamic column value type.
+
+use crate::statement::ColumnType;
+
+/// A value as SQLite's type system sees it: NULL, INTEGER, REAL, TEXT, or
+/// BLOB (see <https://sqlite.org/datatype3.html>).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// This value's storage class, as the [`ColumnType`] returned by
+    /// [`crate::statement::Statement::column_type`] for an equivalent
+    /// column.
+    pub fn data_type(&self) -> ColumnType {
+        match self {
+            Value::Null => ColumnType::Null,
+            Value::Integer(_) => ColumnType::Integer,
+            Value::Real(_) => ColumnType::Float,
+            Value::Text(_) => ColumnType::Text,
+            Value::Blob(_) => ColumnType::Blob,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Integer(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Real(v)
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::Text(v)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(v: Vec<u8>) -> Self {
+        Value::Blob(v)
+    }
+}
@@ -0,0 +1,541 @@
+//! Low-level building blocks for a custom SQLite VFS: registering a raw
+//! `sqlite3_vfs` with SQLite, and opening/driving a file through an
+//! already-registered ("base") VFS so a shim VFS can delegate whatever it
+//! doesn't want to intercept itself. Like [`crate::function`], the raw
+//! callback shape is handed out directly rather than wrapped in a Rust
+//! trait; marshalling to Python `VFS`/`VFSFile` overrides is `arsw-py`'s
+//! job.
+
+use crate::error::{Error, Result};
+use crate::ffi;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_int, c_void};
+
+/// Every VFS name currently registered with SQLite. There's no dedicated
+/// "list every VFS" API; every `sqlite3_vfs_register` call links the VFS
+/// into one list (at the head when it becomes the default, right after the
+/// head otherwise), so walking forward from
+/// `sqlite3_vfs_find(NULL)` (the list head) reaches every one.
+pub fn vfs_names() -> Vec<String> {
+    let mut names = Vec::new();
+    let mut ptr = unsafe { ffi::sqlite3_vfs_find(std::ptr::null()) };
+    while !ptr.is_null() {
+        let vfs = unsafe { &*ptr };
+        if !vfs.zName.is_null() {
+            names.push(unsafe { CStr::from_ptr(vfs.zName) }.to_string_lossy().into_owned());
+        }
+        ptr = vfs.pNext;
+    }
+    names
+}
+
+/// Details about one registered VFS, as reported by [`vfs_details`].
+pub struct VfsDetails {
+    pub name: String,
+    pub version: i32,
+    pub max_pathname: i32,
+    pub os_file_size: i32,
+    /// Whether this is the VFS SQLite currently hands out for a VFS-less
+    /// open -- i.e. the head of the list [`vfs_names`] walks.
+    pub is_default: bool,
+}
+
+/// Every registered VFS's `iVersion`/`szOsFile`/`mxPathname`, and whether
+/// it's the current default.
+pub fn vfs_details() -> Vec<VfsDetails> {
+    let mut details = Vec::new();
+    let mut ptr = unsafe { ffi::sqlite3_vfs_find(std::ptr::null()) };
+    let mut is_default = true;
+    while !ptr.is_null() {
+        let vfs = unsafe { &*ptr };
+        if !vfs.zName.is_null() {
+            details.push(VfsDetails {
+                name: unsafe { CStr::from_ptr(vfs.zName) }.to_string_lossy().into_owned(),
+                version: vfs.iVersion,
+                max_pathname: vfs.mxPathname,
+                os_file_size: vfs.szOsFile,
+                is_default,
+            });
+        }
+        is_default = false;
+        ptr = vfs.pNext;
+    }
+    details
+}
+
+/// Make the VFS named `name` the default, via `sqlite3_vfs_register(vfs,
+/// 1)` -- re-registering an already-registered VFS just moves it to the
+/// head of the list SQLite consults for a VFS-less open, per
+/// `sqlite3_vfs_register`'s own semantics.
+pub fn set_default_vfs(name: &str) -> Result<()> {
+    let vfs_ptr = find_vfs_ptr(Some(name))?;
+    unsafe { register_vfs(vfs_ptr, true) }
+}
+
+/// Unregister the VFS named `name`, via `sqlite3_vfs_unregister`. This is
+/// the raw operation -- it doesn't check whether `name` is the default or
+/// still in use; callers wanting that protection check first.
+pub fn unregister_vfs_by_name(name: &str) -> Result<()> {
+    let vfs_ptr = find_vfs_ptr(Some(name))?;
+    unsafe { unregister_vfs(vfs_ptr) }
+}
+
+fn find_vfs_ptr(name: Option<&str>) -> Result<*mut ffi::sqlite3_vfs> {
+    let cname = name.map(CString::new).transpose()?;
+    let ptr = unsafe { ffi::sqlite3_vfs_find(cname.as_ref().map_or(std::ptr::null(), |c| c.as_ptr())) };
+    if ptr.is_null() {
+        return Err(Error::Misuse("no such VFS is registered"));
+    }
+    Ok(ptr)
+}
+
+/// Register a fully-populated `sqlite3_vfs`, via `sqlite3_vfs_register`.
+///
+/// # Safety
+///
+/// `vfs` must point to a `sqlite3_vfs` that stays valid (not moved, not
+/// freed) for as long as it stays registered, and every function pointer it
+/// sets must honor the corresponding `xMethod`'s contract.
+pub unsafe fn register_vfs(vfs: *mut ffi::sqlite3_vfs, make_default: bool) -> Result<()> {
+    let rc = unsafe { ffi::sqlite3_vfs_register(vfs, make_default as c_int) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_vfs_register failed"));
+    }
+    Ok(())
+}
+
+/// Unregister a previously-registered VFS, via `sqlite3_vfs_unregister`.
+/// Idempotent, matching `sqlite3_vfs_unregister` itself -- callers can
+/// always run this during their own cleanup without first checking whether
+/// registration actually succeeded.
+///
+/// # Safety
+///
+/// `vfs` must be a pointer previously passed to [`register_vfs`] (or
+/// already unregistered).
+pub unsafe fn unregister_vfs(vfs: *mut ffi::sqlite3_vfs) -> Result<()> {
+    let rc = unsafe { ffi::sqlite3_vfs_unregister(vfs) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_vfs_unregister failed"));
+    }
+    Ok(())
+}
+
+/// An open file obtained from some other, already-registered VFS -- the
+/// building block a shim VFS uses to delegate most I/O to (say) the
+/// platform default while intercepting only a handful of calls.
+pub struct BaseFile {
+    /// The VFS's own `sqlite3_file`-and-trailing-state struct, sized to
+    /// `szOsFile` and aligned like a pointer -- `sqlite3_file` starts with
+    /// a `pMethods` pointer field, so a plain `Vec<u8>` (1-byte aligned)
+    /// would be unsound to cast to `*mut sqlite3_file` and deref.
+    ptr: *mut u8,
+    layout: std::alloc::Layout,
+    methods: *const ffi::sqlite3_io_methods,
+}
+
+impl BaseFile {
+    fn file_ptr(&self) -> *mut ffi::sqlite3_file {
+        self.ptr.cast::<ffi::sqlite3_file>()
+    }
+
+    /// Open `filename` (or a private temporary file, if `filename` is
+    /// `None`) through the VFS named `vfs_name` (or the default VFS, if
+    /// `None`), via that VFS's own `xOpen`. Returns the file and the
+    /// `flags` SQLite actually granted.
+    pub fn open(vfs_name: Option<&str>, filename: Option<&str>, flags: i32) -> Result<(Self, i32)> {
+        let vfs_ptr = find_vfs_ptr(vfs_name)?;
+        let vfs = unsafe { &*vfs_ptr };
+        let cfilename = filename.map(CString::new).transpose()?;
+        let size = vfs.szOsFile.max(std::mem::size_of::<ffi::sqlite3_file>() as c_int) as usize;
+        let layout = std::alloc::Layout::from_size_align(size, std::mem::align_of::<*const c_void>())
+            .map_err(|_| Error::Misuse("base VFS reported an invalid szOsFile"))?;
+        let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+        let mut out_flags: c_int = 0;
+        let x_open = match vfs.xOpen.ok_or(Error::Misuse("base VFS has no xOpen")) {
+            Ok(x_open) => x_open,
+            Err(err) => {
+                unsafe { std::alloc::dealloc(ptr, layout) };
+                return Err(err);
+            }
+        };
+        let rc = unsafe {
+            x_open(
+                vfs_ptr,
+                cfilename.as_ref().map_or(std::ptr::null(), |c| c.as_ptr()),
+                ptr.cast(),
+                flags,
+                &mut out_flags,
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            unsafe { std::alloc::dealloc(ptr, layout) };
+            return Err(Error::sqlite_code(rc, "base VFS xOpen failed"));
+        }
+        let methods = unsafe { (*ptr.cast::<ffi::sqlite3_file>()).pMethods };
+        Ok((BaseFile { ptr, layout, methods }, out_flags))
+    }
+
+    /// A short read past end-of-file (`SQLITE_IOERR_SHORT_READ`) is not an
+    /// error -- it's how SQLite itself reads the header of a brand-new,
+    /// still-empty database file, and the base VFS has already zero-filled
+    /// the unread tail of `buf` by the time it reports one.
+    pub fn read(&self, buf: &mut [u8], offset: i64) -> Result<()> {
+        let x = unsafe { (*self.methods).xRead }.ok_or(Error::Misuse("base file has no xRead"))?;
+        let rc = unsafe { x(self.file_ptr(), buf.as_mut_ptr().cast(), buf.len() as c_int, offset) };
+        if rc != ffi::SQLITE_OK && rc != ffi::SQLITE_IOERR_SHORT_READ {
+            return Err(Error::sqlite_code(rc, "base file xRead failed"));
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, data: &[u8], offset: i64) -> Result<()> {
+        let x = unsafe { (*self.methods).xWrite }.ok_or(Error::Misuse("base file has no xWrite"))?;
+        let rc = unsafe { x(self.file_ptr(), data.as_ptr().cast(), data.len() as c_int, offset) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xWrite failed"));
+        }
+        Ok(())
+    }
+
+    pub fn truncate(&self, size: i64) -> Result<()> {
+        let x = unsafe { (*self.methods).xTruncate }.ok_or(Error::Misuse("base file has no xTruncate"))?;
+        let rc = unsafe { x(self.file_ptr(), size) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xTruncate failed"));
+        }
+        Ok(())
+    }
+
+    pub fn sync(&self, flags: i32) -> Result<()> {
+        let x = unsafe { (*self.methods).xSync }.ok_or(Error::Misuse("base file has no xSync"))?;
+        let rc = unsafe { x(self.file_ptr(), flags) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xSync failed"));
+        }
+        Ok(())
+    }
+
+    pub fn file_size(&self) -> Result<i64> {
+        let x = unsafe { (*self.methods).xFileSize }.ok_or(Error::Misuse("base file has no xFileSize"))?;
+        let mut size: i64 = 0;
+        let rc = unsafe { x(self.file_ptr(), &mut size) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xFileSize failed"));
+        }
+        Ok(size)
+    }
+
+    pub fn lock(&self, level: i32) -> Result<()> {
+        let x = unsafe { (*self.methods).xLock }.ok_or(Error::Misuse("base file has no xLock"))?;
+        let rc = unsafe { x(self.file_ptr(), level) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xLock failed"));
+        }
+        Ok(())
+    }
+
+    pub fn unlock(&self, level: i32) -> Result<()> {
+        let x = unsafe { (*self.methods).xUnlock }.ok_or(Error::Misuse("base file has no xUnlock"))?;
+        let rc = unsafe { x(self.file_ptr(), level) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xUnlock failed"));
+        }
+        Ok(())
+    }
+
+    pub fn check_reserved_lock(&self) -> Result<bool> {
+        let x =
+            unsafe { (*self.methods).xCheckReservedLock }.ok_or(Error::Misuse("base file has no xCheckReservedLock"))?;
+        let mut out: c_int = 0;
+        let rc = unsafe { x(self.file_ptr(), &mut out) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xCheckReservedLock failed"));
+        }
+        Ok(out != 0)
+    }
+
+    pub fn sector_size(&self) -> i32 {
+        match unsafe { (*self.methods).xSectorSize } {
+            Some(x) => unsafe { x(self.file_ptr()) },
+            None => 0,
+        }
+    }
+
+    pub fn device_characteristics(&self) -> i32 {
+        match unsafe { (*self.methods).xDeviceCharacteristics } {
+            Some(x) => unsafe { x(self.file_ptr()) },
+            None => 0,
+        }
+    }
+
+    /// Map shared-memory region `region` (each `size` bytes), extending the
+    /// backing file to cover it first if `extend` is set, via `xShmMap`.
+    /// Used to let WAL mode work through an inherited (non-overridden)
+    /// shared-memory implementation.
+    pub fn shm_map(&self, region: i32, size: i32, extend: bool) -> Result<*mut c_void> {
+        let x = unsafe { (*self.methods).xShmMap }.ok_or(Error::Misuse("base file has no xShmMap"))?;
+        let mut out: *mut c_void = std::ptr::null_mut();
+        let rc = unsafe { x(self.file_ptr(), region, size, extend as c_int, &mut out) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xShmMap failed"));
+        }
+        Ok(out)
+    }
+
+    pub fn shm_lock(&self, offset: i32, n: i32, flags: i32) -> Result<()> {
+        let x = unsafe { (*self.methods).xShmLock }.ok_or(Error::Misuse("base file has no xShmLock"))?;
+        let rc = unsafe { x(self.file_ptr(), offset, n, flags) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xShmLock failed"));
+        }
+        Ok(())
+    }
+
+    pub fn shm_barrier(&self) {
+        if let Some(x) = unsafe { (*self.methods).xShmBarrier } {
+            unsafe { x(self.file_ptr()) };
+        }
+    }
+
+    pub fn shm_unmap(&self, delete: bool) -> Result<()> {
+        let x = unsafe { (*self.methods).xShmUnmap }.ok_or(Error::Misuse("base file has no xShmUnmap"))?;
+        let rc = unsafe { x(self.file_ptr(), delete as c_int) };
+        if rc != ffi::SQLITE_OK {
+            return Err(Error::sqlite_code(rc, "base file xShmUnmap failed"));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BaseFile {
+    fn drop(&mut self) {
+        if let Some(x_close) = unsafe { (*self.methods).xClose } {
+            unsafe { x_close(self.file_ptr()) };
+        }
+        unsafe { std::alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// Delete `filename` through the VFS named `vfs_name` (or the default, if
+/// `None`), via that VFS's own `xDelete`.
+pub fn base_delete(vfs_name: Option<&str>, filename: &str, sync_dir: bool) -> Result<()> {
+    let vfs_ptr = find_vfs_ptr(vfs_name)?;
+    let vfs = unsafe { &*vfs_ptr };
+    let x = vfs.xDelete.ok_or(Error::Misuse("base VFS has no xDelete"))?;
+    let cfilename = CString::new(filename)?;
+    let rc = unsafe { x(vfs_ptr, cfilename.as_ptr(), sync_dir as c_int) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "base VFS xDelete failed"));
+    }
+    Ok(())
+}
+
+/// Check `filename`'s existence/permissions (`SQLITE_ACCESS_*`) through the
+/// VFS named `vfs_name` (or the default, if `None`), via that VFS's own
+/// `xAccess`.
+pub fn base_access(vfs_name: Option<&str>, filename: &str, flags: i32) -> Result<bool> {
+    let vfs_ptr = find_vfs_ptr(vfs_name)?;
+    let vfs = unsafe { &*vfs_ptr };
+    let x = vfs.xAccess.ok_or(Error::Misuse("base VFS has no xAccess"))?;
+    let cfilename = CString::new(filename)?;
+    let mut out: c_int = 0;
+    let rc = unsafe { x(vfs_ptr, cfilename.as_ptr(), flags, &mut out) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "base VFS xAccess failed"));
+    }
+    Ok(out != 0)
+}
+
+/// Canonicalize `filename` through the VFS named `vfs_name` (or the
+/// default, if `None`), via that VFS's own `xFullPathname`.
+pub fn base_full_pathname(vfs_name: Option<&str>, filename: &str) -> Result<String> {
+    let vfs_ptr = find_vfs_ptr(vfs_name)?;
+    let vfs = unsafe { &*vfs_ptr };
+    let x = vfs.xFullPathname.ok_or(Error::Misuse("base VFS has no xFullPathname"))?;
+    let cfilename = CString::new(filename)?;
+    let cap = vfs.mxPathname.max(512) as usize;
+    let mut out = vec![0u8; cap];
+    let rc = unsafe { x(vfs_ptr, cfilename.as_ptr(), cap as c_int, out.as_mut_ptr().cast()) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "base VFS xFullPathname failed"));
+    }
+    let end = out.iter().position(|&b| b == 0).unwrap_or(out.len());
+    Ok(String::from_utf8_lossy(&out[..end]).into_owned())
+}
+
+/// Sleep for (at least) `microseconds` through the VFS named `vfs_name`
+/// (or the default, if `None`), via that VFS's own `xSleep`. Returns the
+/// number of microseconds actually slept.
+pub fn base_sleep(vfs_name: Option<&str>, microseconds: i32) -> Result<i32> {
+    let vfs_ptr = find_vfs_ptr(vfs_name)?;
+    let vfs = unsafe { &*vfs_ptr };
+    let x = vfs.xSleep.ok_or(Error::Misuse("base VFS has no xSleep"))?;
+    Ok(unsafe { x(vfs_ptr, microseconds) })
+}
+
+/// The current time as a Julian day number, through the VFS named
+/// `vfs_name` (or the default, if `None`), via that VFS's own
+/// `xCurrentTime`.
+pub fn base_current_time(vfs_name: Option<&str>) -> Result<f64> {
+    let vfs_ptr = find_vfs_ptr(vfs_name)?;
+    let vfs = unsafe { &*vfs_ptr };
+    let x = vfs.xCurrentTime.ok_or(Error::Misuse("base VFS has no xCurrentTime"))?;
+    let mut out: f64 = 0.0;
+    let rc = unsafe { x(vfs_ptr, &mut out) };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "base VFS xCurrentTime failed"));
+    }
+    Ok(out)
+}
+
+/// The current time in milliseconds since the Julian epoch (SQLite's own
+/// unit for this call, not the Unix epoch), through the VFS named
+/// `vfs_name` (or the default, if `None`), via that VFS's own
+/// `xCurrentTimeInt64` -- or, for a VFS that predates it, derived from
+/// [`base_current_time`] the same way SQLite's own fallback does.
+pub fn base_current_time_int64(vfs_name: Option<&str>) -> Result<i64> {
+    let vfs_ptr = find_vfs_ptr(vfs_name)?;
+    let vfs = unsafe { &*vfs_ptr };
+    match vfs.xCurrentTimeInt64 {
+        Some(x) => {
+            let mut out: i64 = 0;
+            let rc = unsafe { x(vfs_ptr, &mut out) };
+            if rc != ffi::SQLITE_OK {
+                return Err(Error::sqlite_code(rc, "base VFS xCurrentTimeInt64 failed"));
+            }
+            Ok(out)
+        }
+        None => {
+            let jd = base_current_time(vfs_name)?;
+            Ok((jd * 86_400_000.0) as i64)
+        }
+    }
+}
+
+/// The last OS-level error reported by the VFS named `vfs_name` (or the
+/// default, if `None`), via that VFS's own `xGetLastError`. Empty if the
+/// VFS doesn't implement it.
+pub fn base_get_last_error(vfs_name: Option<&str>) -> Result<String> {
+    let vfs_ptr = find_vfs_ptr(vfs_name)?;
+    let vfs = unsafe { &*vfs_ptr };
+    let Some(x) = vfs.xGetLastError else {
+        return Ok(String::new());
+    };
+    let mut buf = vec![0u8; 512];
+    unsafe { x(vfs_ptr, buf.len() as c_int, buf.as_mut_ptr().cast()) };
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..end]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_global_sqlite_state;
+
+    #[test]
+    fn write_then_read_round_trips_through_the_default_vfs() {
+        let _guard = lock_global_sqlite_state();
+        let (file, _flags) = BaseFile::open(None, None, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE | ffi::SQLITE_OPEN_DELETEONCLOSE).unwrap();
+        file.write(b"hello, vfs", 0).unwrap();
+        assert_eq!(file.file_size().unwrap(), 10);
+        let mut buf = [0u8; 10];
+        file.read(&mut buf, 0).unwrap();
+        assert_eq!(&buf, b"hello, vfs");
+    }
+
+    #[test]
+    fn truncate_shrinks_reported_file_size() {
+        let _guard = lock_global_sqlite_state();
+        let (file, _flags) = BaseFile::open(None, None, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE | ffi::SQLITE_OPEN_DELETEONCLOSE).unwrap();
+        file.write(b"0123456789", 0).unwrap();
+        file.truncate(4).unwrap();
+        assert_eq!(file.file_size().unwrap(), 4);
+    }
+
+    #[test]
+    fn lock_and_unlock_round_trip_without_error() {
+        let _guard = lock_global_sqlite_state();
+        let (file, _flags) = BaseFile::open(None, None, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE | ffi::SQLITE_OPEN_DELETEONCLOSE).unwrap();
+        assert!(!file.check_reserved_lock().unwrap());
+        file.lock(ffi::SQLITE_LOCK_SHARED).unwrap();
+        file.unlock(ffi::SQLITE_LOCK_NONE).unwrap();
+    }
+
+    #[test]
+    fn vfs_names_lists_the_builtin_default() {
+        let _guard = lock_global_sqlite_state();
+        assert!(!vfs_names().is_empty());
+    }
+
+    #[test]
+    fn vfs_details_marks_exactly_one_vfs_as_default() {
+        let _guard = lock_global_sqlite_state();
+        let details = vfs_details();
+        assert!(!details.is_empty());
+        assert_eq!(details.iter().filter(|d| d.is_default).count(), 1);
+        assert!(details[0].is_default);
+        assert!(details.iter().any(|d| d.os_file_size > 0));
+    }
+
+    #[test]
+    fn set_default_vfs_moves_the_named_vfs_to_the_head() {
+        let _guard = lock_global_sqlite_state();
+        let original_default = vfs_names()[0].clone();
+        let other = vfs_names().into_iter().find(|n| n != &original_default);
+        let Some(other) = other else {
+            return;
+        };
+        set_default_vfs(&other).unwrap();
+        assert_eq!(vfs_names()[0], other);
+        set_default_vfs(&original_default).unwrap();
+        assert_eq!(vfs_names()[0], original_default);
+    }
+
+    #[test]
+    fn set_default_vfs_rejects_an_unknown_name() {
+        let _guard = lock_global_sqlite_state();
+        assert!(set_default_vfs("no-such-vfs").is_err());
+    }
+
+    #[test]
+    fn unregister_vfs_by_name_rejects_an_unknown_name() {
+        let _guard = lock_global_sqlite_state();
+        assert!(unregister_vfs_by_name("no-such-vfs").is_err());
+    }
+
+    #[test]
+    fn base_delete_removes_a_file_created_through_open() {
+        let _guard = lock_global_sqlite_state();
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("arsw-vfs-test-{:p}", &dir));
+        let path = path.to_str().unwrap().to_string();
+        {
+            let (file, _flags) = BaseFile::open(None, Some(&path), ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE).unwrap();
+            file.write(b"x", 0).unwrap();
+        }
+        assert!(base_access(None, &path, ffi::SQLITE_ACCESS_EXISTS).unwrap());
+        base_delete(None, &path, false).unwrap();
+        assert!(!base_access(None, &path, ffi::SQLITE_ACCESS_EXISTS).unwrap());
+    }
+
+    #[test]
+    fn full_pathname_returns_an_absolute_path() {
+        let _guard = lock_global_sqlite_state();
+        let full = base_full_pathname(None, "relative-name.db").unwrap();
+        assert!(full.contains("relative-name.db"));
+        assert!(std::path::Path::new(&full).is_absolute());
+    }
+
+    #[test]
+    fn current_time_and_int64_variants_agree_within_a_second() {
+        let _guard = lock_global_sqlite_state();
+        let jd = base_current_time(None).unwrap();
+        let millis_from_jd = (jd * 86_400_000.0) as i64;
+        let millis = base_current_time_int64(None).unwrap();
+        assert!((millis - millis_from_jd).abs() < 1_000);
+    }
+}
@@ -0,0 +1,314 @@
+//! Registration of aggregate window functions, via
+//! `sqlite3_create_window_function`.
+//!
+//! Like [`crate::function`], this hands out the raw `xStep`/`xFinal`/
+//! `xValue`/`xInverse`/`destroy` callback shapes directly rather than
+//! wrapping them in a Rust trait or closure -- callers build their own
+//! trampolines and are responsible for stashing per-aggregate state behind
+//! `sqlite3_aggregate_context`. Marshalling Python callables (the
+//! object-shape and tuple-shape factories) is `arsw-py`'s job.
+
+use crate::connection::Connection;
+use crate::error::{Error, Result};
+use crate::ffi;
+use std::ffi::CString;
+use std::os::raw::{c_int, c_void};
+
+/// Register an aggregate window function callable as `name(...)`, via
+/// `sqlite3_create_window_function`. `flags` is validated against
+/// [`crate::function::KNOWN_FUNCTION_FLAGS`] exactly like
+/// [`crate::function::create_scalar_function`].
+///
+/// SQLite calls these back in one of two patterns depending on whether the
+/// function is used as a plain aggregate or as a windowed one:
+/// - Plain aggregate: `step` (once per row), then `final` (once).
+/// - Window (sliding frame): `step` for rows entering the frame, `inverse`
+///   for rows leaving it, and `value` whenever the current frame's result is
+///   needed, in whatever interleaving the frame's movement requires, ending
+///   with a final `final` call once the last frame is done.
+///
+/// `final` always runs exactly once and is responsible for freeing any
+/// state `step` allocated (mirroring `sqlite3_aggregate_context`'s own
+/// "allocated on first call, freed by returning zero size" contract --
+/// SQLite does not call `destroy` per aggregate instance, only once when
+/// the function itself is unregistered or the connection closes).
+///
+/// # Safety
+///
+/// `step`, `final_`, `value`, and `inverse` must honor the `xStep`/`xFinal`/
+/// `xValue`/`xInverse` contracts in `sqlite3.h`, and `user_data` must remain
+/// valid until `destroy` runs (or forever, if `destroy` is `None`).
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn create_window_function(
+    conn: &Connection,
+    name: &str,
+    nargs: i32,
+    flags: i32,
+    user_data: *mut c_void,
+    step: unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value),
+    final_: unsafe extern "C" fn(*mut ffi::sqlite3_context),
+    value: unsafe extern "C" fn(*mut ffi::sqlite3_context),
+    inverse: unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value),
+    destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+) -> Result<()> {
+    if flags & !crate::function::KNOWN_FUNCTION_FLAGS != 0 {
+        return Err(Error::Misuse("create_window_function: unknown flag bits"));
+    }
+    let cname = CString::new(name)?;
+    let rc = unsafe {
+        ffi::sqlite3_create_window_function(
+            conn.as_ptr(),
+            cname.as_ptr(),
+            nargs as c_int,
+            ffi::SQLITE_UTF8 | flags,
+            user_data,
+            Some(step),
+            Some(final_),
+            Some(value),
+            Some(inverse),
+            destroy,
+        )
+    };
+    if rc != ffi::SQLITE_OK {
+        return Err(Error::sqlite_code(rc, "sqlite3_create_window_function failed"));
+    }
+    Ok(())
+}
+
+/// The per-aggregate scratch memory `step`/`inverse`/`value`/`final` share,
+/// via `sqlite3_aggregate_context`. Returns `None` only if SQLite's
+/// allocation itself fails (out of memory) -- it never returns `None` just
+/// because `size` is `0` the way the raw C API does, since every caller of
+/// this wrapper wants a `T`-sized slot.
+///
+/// # Safety
+///
+/// `ctx` must be a valid `sqlite3_context*` for the duration of the call,
+/// e.g. the one an `xStep`/`xInverse`/`xValue`/`xFinal` callback was given.
+/// The memory is zero-initialized by SQLite on first access and is only
+/// valid while a single row group is being aggregated; callers that put a
+/// `T` with a non-trivial `Drop` in it must arrange to drop it themselves
+/// from `xFinal` (SQLite merely frees the bytes, it does not run
+/// destructors).
+pub unsafe fn aggregate_context<T>(ctx: *mut ffi::sqlite3_context) -> Option<*mut T> {
+    let size = std::mem::size_of::<T>().max(1) as c_int;
+    let ptr = unsafe { ffi::sqlite3_aggregate_context(ctx, size) };
+    if ptr.is_null() {
+        None
+    } else {
+        Some(ptr.cast())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::test_support::lock_global_sqlite_state;
+    use crate::value::Value;
+    use std::os::raw::c_int;
+
+    fn run(conn: &Connection, sql: &str) -> Result<()> {
+        conn.execute(sql)?.step()?;
+        Ok(())
+    }
+
+    /// `sum`-like window function: state is a single `i64` running total.
+    /// `step` adds, `inverse` subtracts, `value`/`final` report the total.
+    struct SumState(i64);
+
+    unsafe extern "C" fn sum_step(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let state = unsafe { aggregate_context::<SumState>(ctx) }.unwrap();
+        let n = unsafe { ffi::sqlite3_value_int64(*argv) };
+        unsafe { (*state).0 += n };
+    }
+
+    unsafe extern "C" fn sum_inverse(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let state = unsafe { aggregate_context::<SumState>(ctx) }.unwrap();
+        let n = unsafe { ffi::sqlite3_value_int64(*argv) };
+        unsafe { (*state).0 -= n };
+    }
+
+    unsafe extern "C" fn sum_value(ctx: *mut ffi::sqlite3_context) {
+        let state = unsafe { aggregate_context::<SumState>(ctx) }.unwrap();
+        unsafe { ffi::sqlite3_result_int64(ctx, (*state).0) };
+    }
+
+    unsafe extern "C" fn sum_final(ctx: *mut ffi::sqlite3_context) {
+        sum_value(ctx);
+    }
+
+    fn column_values(conn: &Connection, sql: &str) -> Vec<Value> {
+        let mut stmt = conn.execute(sql).unwrap();
+        let mut out = Vec::new();
+        while stmt.step().unwrap() {
+            out.push(stmt.column_value(0).unwrap());
+        }
+        out
+    }
+
+    #[test]
+    fn sliding_sum_matches_the_builtin_sum_over_the_same_frame() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe {
+            create_window_function(
+                &conn,
+                "my_sum",
+                1,
+                0,
+                std::ptr::null_mut(),
+                sum_step,
+                sum_final,
+                sum_value,
+                sum_inverse,
+                None,
+            )
+            .unwrap();
+        }
+        run(&conn, "CREATE TABLE t(x)").unwrap();
+        for x in [1, 2, 3, 4, 5] {
+            run(&conn, &format!("INSERT INTO t VALUES ({x})")).unwrap();
+        }
+        let expected = column_values(
+            &conn,
+            "SELECT sum(x) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) FROM t",
+        );
+        let actual = column_values(
+            &conn,
+            "SELECT my_sum(x) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) FROM t",
+        );
+        assert_eq!(actual, expected);
+        assert_eq!(actual, vec![Value::Integer(3), Value::Integer(6), Value::Integer(9), Value::Integer(12), Value::Integer(9)]);
+    }
+
+    #[test]
+    fn plain_aggregate_use_returns_the_total() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe {
+            create_window_function(
+                &conn,
+                "my_sum2",
+                1,
+                0,
+                std::ptr::null_mut(),
+                sum_step,
+                sum_final,
+                sum_value,
+                sum_inverse,
+                None,
+            )
+            .unwrap();
+        }
+        run(&conn, "CREATE TABLE t(x)").unwrap();
+        for x in [1, 2, 3] {
+            run(&conn, &format!("INSERT INTO t VALUES ({x})")).unwrap();
+        }
+        assert_eq!(column_values(&conn, "SELECT my_sum2(x) FROM t"), vec![Value::Integer(6)]);
+    }
+
+    /// `first_value`-like window function: unlike [`SumState`], the frame's
+    /// oldest-surviving value can't be tracked with fixed-size state, so
+    /// this stashes a heap-allocated `Vec` behind the fixed-size aggregate
+    /// context slot instead -- the same shape `arsw-py`'s window-function
+    /// marshalling uses to hold a Python object there.
+    unsafe fn first_value_slot(ctx: *mut ffi::sqlite3_context) -> *mut *mut Vec<i64> {
+        unsafe { aggregate_context::<*mut Vec<i64>>(ctx) }.unwrap()
+    }
+
+    unsafe extern "C" fn first_value_step(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let slot = unsafe { first_value_slot(ctx) };
+        if unsafe { (*slot).is_null() } {
+            unsafe { *slot = Box::into_raw(Box::new(Vec::new())) };
+        }
+        let n = unsafe { ffi::sqlite3_value_int64(*argv) };
+        unsafe { (**slot).push(n) };
+    }
+
+    unsafe extern "C" fn first_value_inverse(ctx: *mut ffi::sqlite3_context, argc: c_int, _argv: *mut *mut ffi::sqlite3_value) {
+        assert_eq!(argc, 1);
+        let slot = unsafe { first_value_slot(ctx) };
+        unsafe { (**slot).remove(0) };
+    }
+
+    unsafe extern "C" fn first_value_value(ctx: *mut ffi::sqlite3_context) {
+        let slot = unsafe { first_value_slot(ctx) };
+        match unsafe { (**slot).first() } {
+            Some(n) => unsafe { ffi::sqlite3_result_int64(ctx, *n) },
+            None => unsafe { ffi::sqlite3_result_null(ctx) },
+        }
+    }
+
+    unsafe extern "C" fn first_value_final(ctx: *mut ffi::sqlite3_context) {
+        first_value_value(ctx);
+        let slot = unsafe { first_value_slot(ctx) };
+        let state_ptr = unsafe { *slot };
+        if !state_ptr.is_null() {
+            drop(unsafe { Box::from_raw(state_ptr) });
+        }
+    }
+
+    #[test]
+    fn sliding_first_value_matches_the_builtin_first_value_over_the_same_frame() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        unsafe {
+            create_window_function(
+                &conn,
+                "my_first_value",
+                1,
+                0,
+                std::ptr::null_mut(),
+                first_value_step,
+                first_value_final,
+                first_value_value,
+                first_value_inverse,
+                None,
+            )
+            .unwrap();
+        }
+        run(&conn, "CREATE TABLE t(x)").unwrap();
+        for x in [10, 20, 30, 40, 50] {
+            run(&conn, &format!("INSERT INTO t VALUES ({x})")).unwrap();
+        }
+        let expected = column_values(
+            &conn,
+            "SELECT first_value(x) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) FROM t",
+        );
+        let actual = column_values(
+            &conn,
+            "SELECT my_first_value(x) OVER (ORDER BY x ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) FROM t",
+        );
+        assert_eq!(actual, expected);
+        assert_eq!(
+            actual,
+            vec![Value::Integer(10), Value::Integer(10), Value::Integer(20), Value::Integer(30), Value::Integer(40)]
+        );
+    }
+
+    #[test]
+    fn create_window_function_rejects_unknown_flag_bits() {
+        let _guard = lock_global_sqlite_state();
+        let conn = Connection::open(":memory:").unwrap();
+        let bogus_bit = 1 << 30;
+        let result = unsafe {
+            create_window_function(
+                &conn,
+                "bogus",
+                1,
+                bogus_bit,
+                std::ptr::null_mut(),
+                sum_step,
+                sum_final,
+                sum_value,
+                sum_inverse,
+                None,
+            )
+        };
+        assert!(matches!(result, Err(Error::Misuse(_))));
+    }
+}